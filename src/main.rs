@@ -1,16 +1,18 @@
 use rmcp::{
-    ServerHandler, ServiceExt,
+    Peer, RoleServer, ServerHandler, ServiceExt,
     handler::server::router::tool::ToolRouter,
-    model::{ProtocolVersion, ServerCapabilities, ServerInfo},
+    model::{Meta, ProgressNotificationParam, ProgressToken, ProtocolVersion, ServerCapabilities, ServerInfo},
     schemars::{self, JsonSchema},
     tool, tool_handler, tool_router,
     transport::stdio,
     handler::server::tool::Parameters,
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::future::Future;
 use std::process::Command;
-use git2::{Repository, StatusOptions};
+use std::sync::{Arc, Mutex};
+use git2::{Repository, RepositoryState, StatusOptions};
 
 /// 提交类型定义
 struct CommitType {
@@ -37,375 +39,9769 @@ const COMMIT_TYPES: &[CommitType] = &[
     CommitType { emoji: "🔀", name: "merge", desc: "合并分支" },
 ];
 
+/// 常见应忽略的文件：（分类, 规则），以 / 结尾的匹配目录名，*. 开头的匹配扩展名，其余匹配文件名
+const IGNORE_SUGGESTIONS: &[(&str, &str)] = &[
+    ("依赖目录", "node_modules/"),
+    ("构建产物", "target/"),
+    ("构建产物", "dist/"),
+    ("Python 缓存", "__pycache__/"),
+    ("Python 缓存", "*.pyc"),
+    ("日志文件", "*.log"),
+    ("系统文件", ".DS_Store"),
+    ("系统文件", "Thumbs.db"),
+];
+
+// ============================================
+// 仓库配置（.gitmcp.toml）
+// ============================================
+
+/// 仓库根目录下的 .gitmcp.toml，文件不存在时使用默认值
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct RepoConfig {
+    policy: PolicyConfig,
+    footer: FooterConfig,
+    lint: LintConfig,
+}
+
+/// 提交信息规范
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct PolicyConfig {
+    /// 简短描述的最大字符数
+    subject_max_length: usize,
+    /// 必须填写详细描述的提交类型
+    require_body_for_types: Vec<String>,
+    /// 简短描述的最大词数，不设置则不限制
+    max_subject_words: Option<usize>,
+    /// 禁止直接提交的分支，例如 ["main", "master"]
+    protected_branches: Vec<String>,
+}
+
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        Self {
+            subject_max_length: 50,
+            require_body_for_types: Vec::new(),
+            max_subject_words: None,
+            protected_branches: Vec::new(),
+        }
+    }
+}
+
+/// 根据当前分支名生成 issue 脚注，例如 `feature/ABC-123-foo` → `Refs: ABC-123`
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct FooterConfig {
+    /// 从分支名中提取工单号的正则，有捕获组时取第一个捕获组；不设置则不生成脚注
+    ticket_pattern: Option<String>,
+    /// 脚注模板，`{ticket}` 会被替换为工单号
+    template: String,
+}
+
+impl Default for FooterConfig {
+    fn default() -> Self {
+        Self {
+            ticket_pattern: None,
+            template: "Refs: {ticket}".to_string(),
+        }
+    }
+}
+
+/// lint_commit_message 的规则配置
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct LintConfig {
+    /// 正文每行的最大字符数
+    body_max_line_length: usize,
+    /// 覆盖规则级别：规则名 → error/warning/off
+    rules: HashMap<String, String>,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self {
+            body_max_line_length: 72,
+            rules: HashMap::new(),
+        }
+    }
+}
+
+impl LintConfig {
+    /// 规则的生效级别，off 表示关闭
+    fn severity(&self, rule: &str, default: &'static str) -> String {
+        self.rules.get(rule).cloned().unwrap_or_else(|| default.to_string())
+    }
+}
+
+/// 读取仓库的 .gitmcp.toml；路径不是仓库时直接在该目录下查找
+fn load_config(repo_path: &str) -> Result<RepoConfig, String> {
+    let root = Repository::discover(repo_path)
+        .ok()
+        .and_then(|r| r.workdir().map(|p| p.to_path_buf()))
+        .unwrap_or_else(|| std::path::PathBuf::from(repo_path));
+
+    let content = match std::fs::read_to_string(root.join(".gitmcp.toml")) {
+        Ok(c) => c,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(RepoConfig::default()),
+        Err(e) => return Err(format!("❌ 读取 .gitmcp.toml 失败: {}", e)),
+    };
+
+    toml::from_str(&content).map_err(|e| format!("❌ 解析 .gitmcp.toml 失败: {}", e))
+}
+
+/// 按规范检查一条提交信息，返回所有违规项
+fn check_commit_policy(
+    policy: &PolicyConfig,
+    commit_type: &str,
+    short_desc: &str,
+    details: &[String],
+    body: Option<&str>,
+) -> Vec<String> {
+    let mut violations = Vec::new();
+
+    let len = short_desc.chars().count();
+    if len > policy.subject_max_length {
+        violations.push(format!(
+            "简短描述长度 {} 超过上限 {} 个字符",
+            len, policy.subject_max_length
+        ));
+    }
+
+    if let Some(max_words) = policy.max_subject_words {
+        let words = short_desc.split_whitespace().count();
+        if words > max_words {
+            violations.push(format!("简短描述包含 {} 个词，超过上限 {} 个", words, max_words));
+        }
+    }
+
+    let has_body = body.is_some_and(|b| !b.trim().is_empty()) || details.iter().any(|d| !d.trim().is_empty());
+    if !has_body && policy.require_body_for_types.iter().any(|t| t == commit_type) {
+        violations.push(format!("{} 类型的提交必须填写详细描述", commit_type));
+    }
+
+    violations
+}
+
+/// 当前分支属于 protected_branches 时返回分支名
+fn protected_branch(policy: &PolicyConfig, repo_path: &str) -> Option<String> {
+    let repo = Repository::open(repo_path).ok()?;
+    let head = repo.head().ok().filter(|h| h.is_branch())?;
+    let branch = head.shorthand()?;
+    policy
+        .protected_branches
+        .iter()
+        .any(|b| b == branch)
+        .then(|| branch.to_string())
+}
+
+/// 提交前检查受保护分支：auto_branch 时从当前提交新建功能分支并切换过去，返回提示；否则拒绝提交
+fn guard_protected_branch(policy: &PolicyConfig, repo_path: &str, auto_branch: bool) -> Result<Option<String>, String> {
+    let Some(branch) = protected_branch(policy, repo_path) else {
+        return Ok(None);
+    };
+    if !auto_branch {
+        return Err(format!(
+            "❌ 分支 {} 受保护（.gitmcp.toml 中的 policy.protected_branches），禁止直接提交\n💡 请先创建功能分支，或传入 auto_branch=true 自动创建",
+            branch
+        ));
+    }
+
+    let secs = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let new_branch = format!("feature/auto-{}", secs);
+    let created = Repository::open(repo_path).and_then(|repo| {
+        let head = repo.head()?.peel_to_commit()?;
+        repo.branch(&new_branch, &head, false)?;
+        repo.set_head(&format!("refs/heads/{}", new_branch))
+    });
+    match created {
+        Ok(()) => Ok(Some(format!(
+            "🌿 分支 {} 受保护，已自动创建并切换到 {}",
+            branch, new_branch
+        ))),
+        Err(e) => Err(format!("❌ 自动创建功能分支 {} 失败: {}", new_branch, e)),
+    }
+}
+
+/// 从当前分支名提取工单号并生成脚注，未配置、游离 HEAD 或未匹配时返回 None
+fn ticket_footer(footer: &FooterConfig, repo_path: &str) -> Result<Option<String>, String> {
+    let Some(pattern) = footer.ticket_pattern.as_deref() else {
+        return Ok(None);
+    };
+    let re = regex::Regex::new(pattern)
+        .map_err(|e| format!("❌ .gitmcp.toml 中 footer.ticket_pattern 无效: {}", e))?;
+
+    let branch = Repository::open(repo_path)
+        .ok()
+        .and_then(|repo| {
+            let head = repo.head().ok()?;
+            if head.is_branch() {
+                head.shorthand().map(|s| s.to_string())
+            } else {
+                None
+            }
+        });
+    let Some(branch) = branch else {
+        return Ok(None);
+    };
+
+    let ticket = re.captures(&branch).and_then(|caps| caps.get(1).or_else(|| caps.get(0)));
+    Ok(ticket.map(|m| footer.template.replace("{ticket}", m.as_str())))
+}
+
+/// 提交信息检查发现的问题
+#[derive(Debug, Serialize)]
+struct LintProblem {
+    rule: &'static str,
+    severity: String,
+    line: usize,
+    message: String,
+}
+
+/// 从标题中解析提交类型，兼容 emoji 前缀、scope 和破坏性变更标记，例如 `✨ feat(api)!: ...`
+fn parse_subject_type(subject: &str) -> Option<&str> {
+    let (prefix, _) = subject.split_once(':')?;
+    let token = prefix.split_whitespace().last()?;
+    let token = token.trim_end_matches('!');
+    Some(token.split('(').next().unwrap_or(token))
+}
+
+/// 按 commitlint 风格的规则检查提交信息
+fn lint_message(message: &str, lint: &LintConfig) -> Vec<LintProblem> {
+    let mut problems = Vec::new();
+    let mut report = |rule: &'static str, default: &'static str, line: usize, message: String| {
+        let severity = lint.severity(rule, default);
+        if severity != "off" {
+            problems.push(LintProblem { rule, severity, line, message });
+        }
+    };
+
+    let lines: Vec<&str> = message.lines().collect();
+    let subject = lines.first().copied().unwrap_or("");
+
+    match parse_subject_type(subject) {
+        Some(t) if COMMIT_TYPES.iter().any(|ct| ct.name == t) => {}
+        Some(t) => report("type-enum", "error", 1, format!("未知的提交类型: {}", t)),
+        None => report("type-enum", "error", 1, "标题缺少 `<type>: ` 前缀".to_string()),
+    }
+
+    if subject.trim_end().ends_with('.') || subject.trim_end().ends_with('。') {
+        report("subject-full-stop", "error", 1, "标题不应以句号结尾".to_string());
+    }
+
+    if lines.len() > 1 && !lines[1].trim().is_empty() {
+        report("body-leading-blank", "error", 2, "标题和正文之间需要空一行".to_string());
+    }
+
+    for (idx, line) in lines.iter().enumerate() {
+        if line.ends_with(' ') || line.ends_with('\t') {
+            report("trailing-whitespace", "warning", idx + 1, "行尾有多余空白".to_string());
+        }
+        let len = line.chars().count();
+        if idx > 0 && len > lint.body_max_line_length {
+            report(
+                "body-max-line-length",
+                "warning",
+                idx + 1,
+                format!("正文行长度 {} 超过上限 {}", len, lint.body_max_line_length),
+            );
+        }
+    }
+
+    problems
+}
+
 // ============================================
 // 工具参数定义
 // ============================================
 
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct PathParam {
-    #[schemars(description = "Git 仓库路径，默认为当前目录")]
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
     pub path: Option<String>,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct CommitMessageParam {
-    #[schemars(description = "提交类型: feat/fix/docs/style/refactor/perf/test/chore/build/ci/revert/init/ui/config/merge")]
+    #[schemars(
+        description = "提交类型: feat/fix/docs/style/refactor/perf/test/chore/build/ci/revert/init/ui/config/merge",
+        example = &"feat"
+    )]
     pub commit_type: String,
-    #[schemars(description = "简短描述（不超过50字符）")]
+    #[schemars(description = "简短描述（不超过50字符）", example = &"添加用户登录功能")]
     pub short_desc: String,
-    #[schemars(description = "详细描述列表，每项一个变更点")]
+    #[schemars(
+        description = "详细描述列表，每项一个变更点",
+        example = ["新增: src/login.rs 登录接口", "修改: src/main.rs 注册登录路由"]
+    )]
     pub details: Vec<String>,
+    #[schemars(description = "Git 仓库路径，用于读取 .gitmcp.toml 提交规范，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+    #[schemars(description = "是否替换详细描述中的 {branch}（当前分支）、{date}（今天日期）、{user}（user.name）占位符，默认 false 原样保留")]
+    pub interpolate: Option<bool>,
+    #[schemars(description = "标题是否带 emoji 前缀，默认跟随 GIT_MCP_EMOJI 环境变量（off 表示关闭）")]
+    pub emoji: Option<bool>,
+    #[schemars(description = "提交信息中固定文字的语言：zh（默认，正文标题为「详细描述：」）或 en（「Details:」），不影响用户填写的描述内容")]
+    pub language: Option<String>,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct GitCommitParam {
     #[schemars(description = "提交信息")]
     pub message: String,
-    #[schemars(description = "Git 仓库路径，默认为当前目录")]
+    #[schemars(description = "自由格式的正文，按原样（保留分段）追加在 message 之后，中间自动空一行")]
+    pub body: Option<String>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
     pub path: Option<String>,
+    #[schemars(description = "仅预览将要暂存的文件和提交信息，不实际提交，默认 false")]
+    pub dry_run: Option<bool>,
+    #[schemars(description = "允许创建没有任何文件变更的空提交，默认 false")]
+    pub allow_empty: Option<bool>,
+    #[schemars(description = "提交成功后自动推送到已配置的上游分支，默认 false")]
+    pub auto_push: Option<bool>,
+    #[schemars(description = "当前分支受保护（.gitmcp.toml 的 policy.protected_branches）时自动创建并切换到功能分支再提交，默认 false")]
+    pub auto_branch: Option<bool>,
+    #[schemars(description = "输出详细程度：quiet（仅返回 ✅ 和提交哈希）、normal（默认）或 verbose（附带提交的文件列表和完整提交信息），默认跟随 GIT_MCP_VERBOSITY 环境变量")]
+    pub verbosity: Option<String>,
+    #[schemars(description = "传入 --no-verify 跳过 pre-commit 和 commit-msg 钩子，默认 false；跳过时会在返回结果中注明")]
+    pub no_verify: Option<bool>,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct CommitGroup {
-    #[schemars(description = "要提交的文件路径列表")]
+    #[schemars(description = "要提交的文件路径列表", example = ["src/login.rs", "src/main.rs"])]
     pub files: Vec<String>,
-    #[schemars(description = "提交类型: feat/fix/docs/style/refactor/perf/test/chore/build/ci/revert/init/ui/config/merge")]
+    #[schemars(
+        description = "提交类型: feat/fix/docs/style/refactor/perf/test/chore/build/ci/revert/init/ui/config/merge",
+        example = &"feat"
+    )]
     pub commit_type: String,
-    #[schemars(description = "简短描述（不超过50字符）")]
+    #[schemars(description = "简短描述（不超过50字符）", example = &"添加用户登录功能")]
     pub short_desc: String,
-    #[schemars(description = "详细描述列表，每项一个变更点")]
+    #[schemars(
+        description = "详细描述列表，每项一个变更点；提供 body 时忽略",
+        example = ["新增: src/login.rs 登录接口", "修改: src/main.rs 注册登录路由"]
+    )]
+    #[serde(default)]
     pub details: Vec<String>,
+    #[schemars(description = "自由格式的正文，按原样（保留分段）写在标题之后，优先于 details")]
+    pub body: Option<String>,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
+#[schemars(example = smart_commit_example())]
 pub struct SmartCommitParam {
     #[schemars(description = "提交组列表，每组包含文件列表和提交信息，按优先级排序（fix优先，然后feat，最后其他）")]
     pub commits: Vec<CommitGroup>,
-    #[schemars(description = "Git 仓库路径，默认为当前目录")]
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
     pub path: Option<String>,
+    #[schemars(description = "标题是否带 emoji 前缀，默认跟随 GIT_MCP_EMOJI 环境变量（off 表示关闭）")]
+    pub emoji: Option<bool>,
+    #[schemars(description = "提交信息中固定文字的语言：zh（默认，正文标题为「详细描述：」）或 en（「Details:」），不影响用户填写的描述内容")]
+    pub language: Option<String>,
+    #[schemars(description = "所有组都提交成功后自动推送到已配置的上游分支，默认 false")]
+    pub auto_push: Option<bool>,
+    #[schemars(description = "当前分支受保护（.gitmcp.toml 的 policy.protected_branches）时自动创建并切换到功能分支再提交，默认 false")]
+    pub auto_branch: Option<bool>,
+    #[schemars(description = "输出详细程度：quiet（仅返回 ✅ 和提交哈希）、normal（默认）或 verbose（附带提交的文件列表和完整提交信息），默认跟随 GIT_MCP_VERBOSITY 环境变量")]
+    pub verbosity: Option<String>,
+    #[schemars(description = "传入 --no-verify 跳过 pre-commit 和 commit-msg 钩子，默认 false；跳过时会在返回结果中注明")]
+    pub no_verify: Option<bool>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ListCommitTypesParam {
+    #[schemars(description = "输出格式：markdown（默认，表格）或 json（{name, emoji, desc} 数组）")]
+    pub format: Option<String>,
 }
 
 #[derive(Debug, Deserialize, JsonSchema)]
 pub struct GitLogParam {
     #[schemars(description = "显示的提交数量，默认10条")]
     pub count: Option<u32>,
-    #[schemars(description = "Git 仓库路径，默认为当前目录")]
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
     pub path: Option<String>,
+    #[schemars(description = "只显示涉及这些文件或目录的提交，例如 [\"src/\"]")]
+    pub paths: Option<Vec<String>>,
+    #[schemars(description = "是否显示每条提交的完整正文（缩进显示在标题下方），默认 false 只显示标题")]
+    pub show_body: Option<bool>,
+    #[schemars(description = "是否只沿第一父提交显示主线历史（git log --first-parent），合并进来的分支只保留其合并提交，默认 false")]
+    pub first_parent: Option<bool>,
 }
 
-// ============================================
-// MCP Server
-// ============================================
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct VerifyCommitParam {
+    #[schemars(description = "要检查的提交，可以是哈希、分支名或 HEAD~1 等表达式")]
+    pub revision: String,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
 
-#[derive(Clone)]
-pub struct GitMcpServer {
-    tool_router: ToolRouter<Self>,
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ArchiveParam {
+    #[schemars(description = "要导出的修订，默认为 HEAD")]
+    pub revision: Option<String>,
+    #[schemars(description = "输出文件路径，相对路径基于仓库目录")]
+    pub output: String,
+    #[schemars(description = "归档格式: tar/tar.gz/zip，默认根据输出文件扩展名推断")]
+    pub format: Option<String>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
 }
 
-impl GitMcpServer {
-    pub fn new() -> Self {
-        Self {
-            tool_router: Self::tool_router(),
-        }
-    }
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct PullPreviewParam {
+    #[schemars(description = "远程仓库名，默认为 origin")]
+    pub remote: Option<String>,
+    #[schemars(description = "远程分支名，默认为当前分支同名分支")]
+    pub branch: Option<String>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
 }
 
-impl Default for GitMcpServer {
-    fn default() -> Self {
-        Self::new()
-    }
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SetUpstreamParam {
+    #[schemars(description = "远程仓库名，默认为 origin；remote 和 branch 都不传时仅显示当前上游分支")]
+    pub remote: Option<String>,
+    #[schemars(description = "远程分支名，默认为当前分支同名分支")]
+    pub branch: Option<String>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
 }
 
-#[tool_router]
-impl GitMcpServer {
-    /// 获取 Git 仓库状态
-    #[tool(description = "获取 Git 仓库状态，显示所有变更文件（新增、修改、删除）")]
-    async fn git_status(&self, Parameters(param): Parameters<PathParam>) -> String {
-        let repo_path = param.path.unwrap_or_else(|| ".".to_string());
-        
-        let repo = match Repository::open(&repo_path) {
-            Ok(r) => r,
-            Err(e) => return format!("❌ 无法打开 Git 仓库: {}", e),
-        };
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ShowFileParam {
+    #[schemars(description = "文件路径（相对仓库根目录）")]
+    pub file: String,
+    #[schemars(description = "修订，默认为 HEAD")]
+    pub revision: Option<String>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
 
-        let mut opts = StatusOptions::new();
-        opts.include_untracked(true);
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CompareBranchesParam {
+    #[schemars(description = "基准分支或修订，例如 main")]
+    pub base: String,
+    #[schemars(description = "对比分支或修订，例如 feature/login")]
+    pub head: String,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
 
-        let statuses = match repo.statuses(Some(&mut opts)) {
-            Ok(s) => s,
-            Err(e) => return format!("❌ 获取状态失败: {}", e),
-        };
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct MaintenanceParam {
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+    #[schemars(description = "是否使用 --aggressive 深度压缩（耗时较长），默认 false")]
+    pub aggressive: Option<bool>,
+}
 
-        if statuses.is_empty() {
-            return "✅ 工作区干净，没有变更".to_string();
-        }
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GroupDiffParam {
+    #[schemars(description = "要预览的文件路径列表，通常是 smart_commit 中某一组的 files")]
+    pub files: Vec<String>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+    #[schemars(description = "输出格式：text（默认，统一 diff 文本）或 json（按文件和 hunk 结构化输出）")]
+    pub format: Option<String>,
+}
 
-        let mut result = String::from("📊 变更导图：\n\n");
-        
-        for entry in statuses.iter() {
-            let path = entry.path().unwrap_or("unknown");
-            let status = entry.status();
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct MergeBaseParam {
+    #[schemars(description = "第一个修订，例如 main")]
+    pub a: String,
+    #[schemars(description = "第二个修订，例如 feature/login")]
+    pub b: String,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
 
-            let (icon, status_str) = if status.is_index_new() || status.is_wt_new() {
-                ("➕", "新增")
-            } else if status.is_index_modified() || status.is_wt_modified() {
-                ("📝", "修改")
-            } else if status.is_index_deleted() || status.is_wt_deleted() {
-                ("➖", "删除")
-            } else {
-                continue;
-            };
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct InitParam {
+    #[schemars(description = "要初始化的目录，不存在时自动创建，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+    #[schemars(description = "初始分支名，例如 main，默认使用 git 配置的 init.defaultBranch")]
+    pub initial_branch: Option<String>,
+    #[schemars(description = "是否创建裸仓库，默认 false")]
+    pub bare: Option<bool>,
+    #[schemars(description = "目录位于其他仓库的工作区内时是否仍然创建嵌套仓库，默认 false 拒绝创建")]
+    pub allow_nested: Option<bool>,
+}
 
-            result.push_str(&format!("{} {} {}\n", icon, status_str, path));
-        }
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct LintMessageParam {
+    #[schemars(description = "要检查的完整提交信息")]
+    pub message: String,
+    #[schemars(description = "Git 仓库路径，用于读取 .gitmcp.toml 中的 lint 配置，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
 
-        result
-    }
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct PlanAddParam {
+    #[schemars(description = "要加入暂存计划的文件路径列表")]
+    pub files: Vec<String>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
 
-    /// 生成符合规范的 Git 提交信息
-    #[tool(description = "根据提交类型和描述生成符合规范的 Git 提交信息")]
-    async fn generate_commit_message(&self, Parameters(param): Parameters<CommitMessageParam>) -> String {
-        let type_info = COMMIT_TYPES
-            .iter()
-            .find(|t| t.name == param.commit_type)
-            .unwrap_or(&COMMIT_TYPES[0]);
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct PlanCommitParam {
+    #[schemars(description = "提交信息")]
+    pub message: String,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
 
-        let details_str = param.details
-            .iter()
-            .map(|d| format!("- {}", d))
-            .collect::<Vec<_>>()
-            .join("\n");
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RenameBranchParam {
+    #[schemars(description = "新分支名")]
+    pub new_name: String,
+    #[schemars(description = "要重命名的分支，默认为当前分支")]
+    pub old_name: Option<String>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
 
-        let commit_msg = format!(
-            "{} {}: {}\n\n详细描述：\n{}",
-            type_info.emoji, type_info.name, param.short_desc, details_str
-        );
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct BlameParam {
+    #[schemars(description = "文件路径（相对仓库根目录）")]
+    pub file: String,
+    #[schemars(description = "修订，默认为 HEAD")]
+    pub revision: Option<String>,
+    #[schemars(description = "起始行号（从 1 开始），与 end_line 一起限定范围")]
+    pub start_line: Option<usize>,
+    #[schemars(description = "结束行号（包含）")]
+    pub end_line: Option<usize>,
+    #[schemars(description = "blame 时忽略的提交列表（如大规模格式化提交）；仓库根目录存在 .git-blame-ignore-revs 时也会自动读取", example = ["a1b2c3d"])]
+    pub ignore_revs: Option<Vec<String>>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
 
-        format!("📝 生成的提交信息：\n\n```\n{}\n```", commit_msg)
-    }
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ChurnParam {
+    #[schemars(description = "起始修订（不包含），统计 since..HEAD 之间的提交，例如 v1.0.0 或 main~50")]
+    pub since: String,
+    #[schemars(description = "返回变动最多的前 N 个文件，默认 20")]
+    pub top: Option<usize>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
 
-    /// 执行 Git 提交
-    #[tool(description = "执行 git add 和 git commit，使用指定的提交信息")]
-    async fn git_commit(&self, Parameters(param): Parameters<GitCommitParam>) -> String {
-        let repo_path = param.path.unwrap_or_else(|| ".".to_string());
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct FileDiffParam {
+    #[schemars(description = "文件路径（相对仓库根目录）")]
+    pub file: String,
+    #[schemars(description = "对比的修订，默认为 HEAD")]
+    pub revision: Option<String>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+    #[schemars(description = "输出格式：text（默认，统一 diff 文本）或 json（按文件和 hunk 结构化输出）")]
+    pub format: Option<String>,
+}
 
-        // git add .
-        let add_output = Command::new("git")
-            .args(["add", "."])
-            .current_dir(&repo_path)
-            .output();
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct LsTreeParam {
+    #[schemars(description = "修订，默认为 HEAD")]
+    pub revision: Option<String>,
+    #[schemars(description = "只列出该子目录（相对仓库根目录），默认为根目录")]
+    pub directory: Option<String>,
+    #[schemars(description = "是否递归列出子目录中的文件，默认 false")]
+    pub recursive: Option<bool>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
 
-        match add_output {
-            Ok(output) if !output.status.success() => {
-                return format!("❌ git add 失败: {}", String::from_utf8_lossy(&output.stderr));
-            }
-            Err(e) => return format!("❌ 执行 git add 失败: {}", e),
-            _ => {}
-        }
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct BranchContainsParam {
+    #[schemars(description = "要查询的提交或修订，例如 a1b2c3d")]
+    pub revision: String,
+    #[schemars(description = "是否同时检查远程跟踪分支，默认 false")]
+    pub include_remote: Option<bool>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
 
-        // git commit
-        let commit_output = Command::new("git")
-            .args(["commit", "-m", &param.message])
-            .current_dir(&repo_path)
-            .output();
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct PruneBranchesParam {
+    #[schemars(description = "合并目标分支，已完全合入该分支的本地分支会被清理，默认为当前分支")]
+    pub target: Option<String>,
+    #[schemars(description = "只列出待删除的分支而不实际删除，默认 true")]
+    pub dry_run: Option<bool>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
 
-        match commit_output {
-            Ok(output) if output.status.success() => {
-                format!("✅ 提交成功！\n\n💡 如需推送，请执行: git push")
-            }
-            Ok(output) => {
-                format!("❌ git commit 失败: {}", String::from_utf8_lossy(&output.stderr))
-            }
-            Err(e) => format!("❌ 执行 git commit 失败: {}", e),
-        }
-    }
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct StashPushParam {
+    #[schemars(description = "要储藏的文件路径列表，其余改动保留在工作区", example = ["src/login.rs"])]
+    pub files: Vec<String>,
+    #[schemars(description = "储藏说明，默认由 git 生成")]
+    pub message: Option<String>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
 
-    /// 获取支持的提交类型列表
-    #[tool(description = "获取所有支持的提交类型及其说明")]
-    async fn list_commit_types(&self) -> String {
-        let mut result = String::from("📋 支持的提交类型：\n\n");
-        result.push_str("| Type | Emoji | 说明 |\n");
-        result.push_str("|------|-------|------|\n");
-        
-        for t in COMMIT_TYPES {
-            result.push_str(&format!("| {} | {} | {} |\n", t.name, t.emoji, t.desc));
-        }
-        
-        result
-    }
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct PushParam {
+    #[schemars(description = "远程仓库名，默认 origin")]
+    pub remote: Option<String>,
+    #[schemars(description = "要推送的本地分支列表，默认为当前分支；每个分支单独报告成功或失败", example = ["main", "release/1.2"])]
+    pub branches: Option<Vec<String>>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
 
-    /// 查看 Git 提交历史
-    #[tool(description = "查看最近的 Git 提交历史")]
-    async fn git_log(&self, Parameters(param): Parameters<GitLogParam>) -> String {
-        let repo_path = param.path.unwrap_or_else(|| ".".to_string());
-        let n = param.count.unwrap_or(10).to_string();
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct BumpTagParam {
+    #[schemars(description = "递增的版本位: major / minor / patch", example = &"patch")]
+    pub level: String,
+    #[schemars(description = "标签前缀，例如 v1.2.3 中的 v；不传时同时识别 v1.2.3 和 1.2.3，新标签沿用最新版本标签的写法（没有版本标签时为 v）")]
+    pub prefix: Option<String>,
+    #[schemars(description = "是否创建 GPG 签名标签（git tag -s），需要先配置 user.signingkey，默认 false")]
+    pub sign: Option<bool>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
 
-        let output = Command::new("git")
-            .args(["log", "--oneline", "-n", &n])
-            .current_dir(&repo_path)
-            .output();
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RestoreDeletedParam {
+    #[schemars(description = "被删除的文件路径（相对仓库根目录）")]
+    pub file: String,
+    #[schemars(description = "恢复后是否加入暂存区，默认 false")]
+    pub stage: Option<bool>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
 
-        match output {
-            Ok(o) if o.status.success() => {
-                format!("📜 最近 {} 条提交：\n\n{}", n, String::from_utf8_lossy(&o.stdout))
-            }
-            Ok(o) => format!("❌ 获取日志失败: {}", String::from_utf8_lossy(&o.stderr)),
-            Err(e) => format!("❌ 执行失败: {}", e),
-        }
-    }
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ConflictDiffParam {
+    #[schemars(description = "存在冲突的文件路径（相对仓库根目录）")]
+    pub file: String,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
 
-    /// 查看当前分支
-    #[tool(description = "查看当前所在的 Git 分支")]
-    async fn git_branch(&self, Parameters(param): Parameters<PathParam>) -> String {
-        let repo_path = param.path.unwrap_or_else(|| ".".to_string());
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ListChangedParam {
+    #[schemars(description = "只列出某一类文件：staged / unstaged / untracked / conflicted，默认按类别分组返回全部")]
+    pub category: Option<String>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
 
-        let output = Command::new("git")
-            .args(["branch", "--show-current"])
-            .current_dir(&repo_path)
-            .output();
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct StashDiffParam {
+    #[schemars(description = "储藏序号，即 stash@{N} 中的 N，默认 0（最新的储藏）")]
+    pub index: Option<u32>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
 
-        match output {
-            Ok(o) if o.status.success() => {
-                format!("🌿 当前分支: {}", String::from_utf8_lossy(&o.stdout).trim())
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct LineHistoryParam {
+    #[schemars(description = "文件路径（相对仓库根目录）")]
+    pub file: String,
+    #[schemars(description = "行号（从 1 开始），以 HEAD 中的文件内容为准")]
+    pub line: u32,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RevParseParam {
+    #[schemars(description = "修订表达式，例如 HEAD~3、main@{yesterday}、v1.2.0 或部分哈希", example = &"HEAD~3")]
+    pub expression: String,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct AmendAuthorParam {
+    #[schemars(description = "新的作者姓名，不传则保持原作者姓名")]
+    pub name: Option<String>,
+    #[schemars(description = "新的作者邮箱，不传则保持原作者邮箱", example = &"dev@example.com")]
+    pub email: Option<String>,
+    #[schemars(description = "新的作者时间，支持 now、Unix 时间戳或 YYYY-MM-DD[ HH:MM[:SS]][ +08:00]，不传则保持原时间", example = &"2024-05-01 10:30:00 +08:00")]
+    pub date: Option<String>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ParentsParam {
+    #[schemars(description = "要查看父提交的修订，例如 HEAD、main~2 或提交哈希", example = &"HEAD")]
+    pub revision: String,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SparseCheckoutParam {
+    #[schemars(description = "操作：set（限定工作区为指定目录）、list（查看当前规则）、disable（恢复完整工作区）", example = &"set")]
+    pub action: String,
+    #[schemars(description = "set 时的目录或模式列表；只含目录时使用 cone 模式，含通配符时自动切换为非 cone 模式", example = ["services/api", "libs/common"])]
+    pub patterns: Option<Vec<String>>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RecentBranchesParam {
+    #[schemars(description = "返回最近访问的分支数量，默认 10")]
+    pub count: Option<u32>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CommitTreeParam {
+    #[schemars(description = "统一 diff 格式的补丁内容（git diff 输出），相对父提交的文件树应用")]
+    pub patch: String,
+    #[schemars(description = "提交信息")]
+    pub message: String,
+    #[schemars(description = "父提交修订，默认 HEAD", example = &"main")]
+    pub parent: Option<String>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct EstimateCommitSizeParam {
+    #[schemars(description = "计划提交的文件路径列表，通常是 smart_commit 中某一组的 files", example = ["src/login.rs", "src/main.rs"])]
+    pub files: Vec<String>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct NotesParam {
+    #[schemars(description = "操作：show（查看注释）、add（添加注释，已有注释时覆盖）、remove（删除注释）", example = &"add")]
+    pub action: String,
+    #[schemars(description = "要操作的提交修订，默认 HEAD")]
+    pub revision: Option<String>,
+    #[schemars(description = "add 时的注释内容", example = &"review: approved")]
+    pub message: Option<String>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct IsRebasedOnParam {
+    #[schemars(description = "要检查的分支或修订", example = &"feature/login")]
+    pub branch: String,
+    #[schemars(description = "基准分支或修订", example = &"main")]
+    pub base: String,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct WebUrlParam {
+    #[schemars(description = "要指向的修订（解析为提交哈希生成永久链接），不传且未指定 file 时返回仓库主页")]
+    pub revision: Option<String>,
+    #[schemars(description = "要指向的文件路径（相对仓库根目录），不传 revision 时使用 HEAD", example = &"src/main.rs")]
+    pub file: Option<String>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CheckoutFromParam {
+    #[schemars(description = "取文件版本的来源分支或修订", example = &"hotfix/login")]
+    pub branch: String,
+    #[schemars(description = "要取来的文件或目录列表，不传则取来与来源分支的全部差异", example = ["src/login.rs"])]
+    pub files: Option<Vec<String>>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DefaultBranchParam {
+    #[schemars(description = "远程仓库名，默认 origin")]
+    pub remote: Option<String>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct PreviewCommitMessageParam {
+    #[serde(flatten)]
+    pub group: CommitGroup,
+    #[schemars(description = "Git 仓库路径，用于读取 .gitmcp.toml 和分支工单号，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+    #[schemars(description = "标题是否带 emoji 前缀，默认跟随 GIT_MCP_EMOJI 环境变量（off 表示关闭）")]
+    pub emoji: Option<bool>,
+    #[schemars(description = "提交信息中固定文字的语言：zh（默认）或 en，与 smart_commit 一致")]
+    pub language: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ChangesBetweenTagsParam {
+    #[schemars(description = "起始标签（不包含），例如上一个发布版本", example = &"v1.2.0")]
+    pub from_tag: String,
+    #[schemars(description = "结束标签（包含），默认 HEAD", example = &"v1.3.0")]
+    pub to_tag: Option<String>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CanFfParam {
+    #[schemars(description = "要合入当前分支的分支或修订", example = &"feature/login")]
+    pub branch: String,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct WithStashParam {
+    #[schemars(description = "要执行的操作：checkout（切换分支）、pull（拉取当前分支的上游）或 merge（合并分支）", example = &"checkout")]
+    pub operation: String,
+    #[schemars(description = "checkout / merge 的目标分支；pull 时可选，指定要拉取的远程分支，默认上游分支", example = &"main")]
+    pub branch: Option<String>,
+    #[schemars(description = "pull 指定 branch 时拉取的远程仓库名，默认当前分支上游所在的远程，没有上游时为 origin")]
+    pub remote: Option<String>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GraphParam {
+    #[schemars(description = "返回的提交数量，默认 50")]
+    pub count: Option<u32>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct IdentityParam {
+    #[schemars(description = "要设置的 user.name，需与 email 同时提供；都不传时只查询当前身份")]
+    pub name: Option<String>,
+    #[schemars(description = "要设置的 user.email，需与 name 同时提供", example = &"dev@example.com")]
+    pub email: Option<String>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CommitCountParam {
+    #[schemars(description = "起点：修订（统计 since..HEAD，不含起点）或日期（YYYY-MM-DD[ HH:MM[:SS]][ +08:00]，统计此后提交的）；不传则统计 HEAD 可达的全部提交", example = &"v1.0.0")]
+    pub since: Option<String>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SyncCheckParam {
+    #[schemars(description = "远程仓库名，默认 origin")]
+    pub remote: Option<String>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct IgnoreParam {
+    #[schemars(description = "操作：list（列出 .gitignore 中的规则）、add（追加规则，已存在时跳过）、check（检查路径是否被忽略）", example = &"check")]
+    pub action: String,
+    #[schemars(description = "add 时为要追加的规则，check 时为要检查的路径（相对仓库根目录）", example = &"target/")]
+    pub pattern: Option<String>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct FindCommitsParam {
+    #[schemars(description = "匹配提交标题和正文的正则表达式", example = &"(?i)hotfix")]
+    pub pattern: String,
+    #[schemars(description = "最多返回的匹配提交数，默认 20")]
+    pub count: Option<u32>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RestoreFileParam {
+    #[schemars(description = "要恢复的文件路径（相对仓库根目录）", example = &"src/main.rs")]
+    pub file: String,
+    #[schemars(description = "从哪个修订读取文件内容，默认 HEAD", example = &"HEAD~1")]
+    pub revision: Option<String>,
+    #[schemars(description = "为 true 时只恢复暂存区（相当于 git restore --staged），工作区不变；默认 false 只恢复工作区")]
+    pub staged: Option<bool>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct IsAncestorParam {
+    #[schemars(description = "可能是祖先的提交或修订", example = &"HEAD")]
+    pub commit: String,
+    #[schemars(description = "要检查的后代提交或修订", example = &"origin/main")]
+    pub of: String,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SquashParam {
+    #[schemars(description = "要合并的最近提交数量，至少为 2", example = &3)]
+    pub count: u32,
+    #[schemars(description = "合并后的提交信息；不传则以最早一条提交的标题为标题，其余标题列在正文中")]
+    pub message: Option<String>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TagListParam {
+    #[schemars(description = "按通配符过滤标签名，例如 \"v1.*\"；不传则列出全部标签", example = &"v1.*")]
+    pub pattern: Option<String>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct AddToLastCommitParam {
+    #[schemars(description = "要补充进最近一次提交的文件列表", example = &["src/forgotten.rs"])]
+    pub files: Vec<String>,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct MergePreviewParam {
+    #[schemars(description = "要试合并到当前分支的分支或修订", example = &"feature/login")]
+    pub branch: String,
+    #[schemars(description = "Git 仓库路径，默认为 GIT_MCP_DEFAULT_REPO 环境变量或当前目录")]
+    pub path: Option<String>,
+}
+
+/// smart_commit 参数示例：先提交修复，再提交新功能
+fn smart_commit_example() -> serde_json::Value {
+    serde_json::json!({
+        "commits": [
+            {
+                "files": ["src/parser.rs"],
+                "commit_type": "fix",
+                "short_desc": "修复空输入时解析崩溃",
+                "details": ["修复: parser 对空字符串的越界访问"]
+            },
+            {
+                "files": ["src/login.rs", "src/main.rs"],
+                "commit_type": "feat",
+                "short_desc": "添加用户登录功能",
+                "details": ["新增: src/login.rs 登录接口", "修改: src/main.rs 注册登录路由"]
             }
-            Ok(o) => format!("❌ 获取分支失败: {}", String::from_utf8_lossy(&o.stderr)),
-            Err(e) => format!("❌ 执行失败: {}", e),
+        ]
+    })
+}
+
+// ============================================
+// 辅助函数
+// ============================================
+
+/// 解析仓库路径：优先使用参数，其次是 GIT_MCP_DEFAULT_REPO 环境变量，最后才是当前目录
+fn resolve_repo_path(path: Option<String>) -> String {
+    path.or_else(|| std::env::var("GIT_MCP_DEFAULT_REPO").ok().filter(|p| !p.is_empty()))
+        .unwrap_or_else(|| ".".to_string())
+}
+
+/// 校验必填文本参数：去除首尾空白后不能为空，返回去除空白后的值
+fn require_text(field: &str, value: &str) -> Result<String, String> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        Err(format!("❌ 参数 {} 不能为空", field))
+    } else {
+        Ok(trimmed.to_string())
+    }
+}
+
+/// 将修订表达式解析为提交
+fn resolve_commit<'r>(repo: &'r Repository, revision: &str) -> Result<git2::Commit<'r>, git2::Error> {
+    repo.revparse_single(revision)?.peel_to_commit()
+}
+
+/// 文件状态对应的图标和中文说明，无法归类时返回 None
+fn change_label(status: git2::Status) -> Option<(&'static str, &'static str)> {
+    if status.is_index_new() || status.is_wt_new() {
+        Some(("➕", "新增"))
+    } else if status.is_index_modified() || status.is_wt_modified() {
+        Some(("📝", "修改"))
+    } else if status.is_index_deleted() || status.is_wt_deleted() {
+        Some(("➖", "删除"))
+    } else {
+        None
+    }
+}
+
+/// 暂存区相对 HEAD 是否有变更（无法判断时视为有变更）
+fn has_staged_changes(repo_path: &str) -> bool {
+    let Ok(repo) = Repository::open(repo_path) else {
+        return true;
+    };
+    let head_tree = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+    repo.diff_tree_to_index(head_tree.as_ref(), None, None)
+        .map_or(true, |diff| diff.deltas().len() > 0)
+}
+
+/// 差异条目状态对应的中文说明
+fn delta_label(delta: git2::Delta) -> &'static str {
+    match delta {
+        git2::Delta::Added | git2::Delta::Untracked => "新增",
+        git2::Delta::Deleted => "删除",
+        git2::Delta::Modified => "修改",
+        git2::Delta::Renamed => "重命名",
+        git2::Delta::Copied => "复制",
+        git2::Delta::Typechange => "类型变更",
+        _ => "变更",
+    }
+}
+
+/// 是否在提交标题前加 emoji：参数优先，其次 GIT_MCP_EMOJI=off/false/0 关闭，默认开启
+fn emoji_enabled(emoji: Option<bool>) -> bool {
+    emoji.unwrap_or_else(|| {
+        !matches!(
+            std::env::var("GIT_MCP_EMOJI").map(|v| v.trim().to_lowercase()).as_deref(),
+            Ok("off" | "false" | "0")
+        )
+    })
+}
+
+/// 提交标题：`<emoji> <type>: <简短描述>`，关闭 emoji 时为纯 Conventional Commits 格式
+fn format_subject(type_info: &CommitType, short_desc: &str, emoji: bool) -> String {
+    if emoji {
+        format!("{} {}: {}", type_info.emoji, type_info.name, short_desc)
+    } else {
+        format!("{}: {}", type_info.name, short_desc)
+    }
+}
+
+/// 生成提交信息时的格式选项
+struct MessageOptions {
+    /// 标题是否带 emoji
+    emoji: bool,
+    /// 追加在正文之后的脚注，例如 `Refs: ABC-123`
+    footers: Vec<String>,
+    /// 详细描述列表前的标题，例如 `详细描述：`
+    details_header: &'static str,
+}
+
+/// 根据 language 参数选择详细描述标题：zh（默认）或 en
+fn details_header(language: Option<&str>) -> Result<&'static str, String> {
+    match language.map(|l| l.trim().to_lowercase()).as_deref() {
+        None | Some("") | Some("zh") => Ok("详细描述："),
+        Some("en") => Ok("Details:"),
+        Some(other) => Err(format!("❌ 不支持的语言 {}，可选值: zh / en", other)),
+    }
+}
+
+/// 规范化自由格式正文：统一换行符为 \n，去掉首尾空行，内容为空时返回 None
+fn normalize_body(body: Option<&str>) -> Option<String> {
+    let body = body?.replace("\r\n", "\n").replace('\r', "\n");
+    let body = body.trim_matches('\n').trim_end();
+    (!body.trim().is_empty()).then(|| body.to_string())
+}
+
+/// 组装完整的提交信息：标题、正文（body 优先，否则为详细描述列表）和脚注
+fn build_commit_message(
+    commit_type: &str,
+    short_desc: &str,
+    details: &[String],
+    body: Option<&str>,
+    opts: &MessageOptions,
+) -> String {
+    let type_info = COMMIT_TYPES
+        .iter()
+        .find(|t| t.name == commit_type)
+        .unwrap_or(&COMMIT_TYPES[0]);
+
+    let mut msg = format_subject(type_info, short_desc, opts.emoji);
+
+    if let Some(body) = normalize_body(body) {
+        msg.push_str(&format!("\n\n{}", body));
+    } else if !details.is_empty() {
+        let details_str = details
+            .iter()
+            .map(|d| format!("- {}", d))
+            .collect::<Vec<_>>()
+            .join("\n");
+        msg.push_str(&format!("\n\n{}\n{}", opts.details_header, details_str));
+    }
+
+    if !opts.footers.is_empty() {
+        msg.push_str(&format!("\n\n{}", opts.footers.join("\n")));
+    }
+
+    msg
+}
+
+/// 执行 smart_commit 中的单个提交组，成功和失败都返回一行结果说明
+fn commit_group(
+    repo_path: &str,
+    idx: usize,
+    group: &CommitGroup,
+    opts: &MessageOptions,
+    no_verify: bool,
+) -> Result<String, String> {
+    let short_desc = group.short_desc.trim();
+
+    // 先清空暂存区，确保只提交当前组的文件
+    let _ = run_git(repo_path, ["reset", "HEAD"]);
+
+    // 构建提交信息
+    let commit_msg = build_commit_message(&group.commit_type, short_desc, &group.details, group.body.as_deref(), opts);
+
+    // git add 指定文件
+    let mut add_args = vec!["add".to_string(), "--".to_string()];
+    add_args.extend(group.files.clone());
+
+    let add_output = run_git(repo_path, &add_args);
+
+    match add_output {
+        Ok(output) if !output.status.success() => {
+            return Err(format!(
+                "❌ 第{}组 [{}] git add 失败: {}",
+                idx + 1,
+                group.commit_type,
+                String::from_utf8_lossy(&output.stderr)
+            ));
         }
+        Err(e) => {
+            return Err(format!(
+                "❌ 第{}组 [{}] 执行 git add 失败: {}",
+                idx + 1,
+                group.commit_type,
+                e
+            ));
+        }
+        _ => {}
     }
 
-    /// 智能分类提交
-    #[tool(description = "智能分类提交：根据变更类型分组，依次执行多次提交。每组指定文件列表和提交信息，实现 fix/feat/style 等分类提交")]
-    async fn smart_commit(&self, Parameters(param): Parameters<SmartCommitParam>) -> String {
-        let repo_path = param.path.unwrap_or_else(|| ".".to_string());
-        let mut results = Vec::new();
-        let mut success_count = 0;
+    // git commit
+    let mut commit_args = vec!["commit", "-m", &commit_msg];
+    if no_verify {
+        commit_args.push("--no-verify");
+    }
+    let commit_output = run_git(repo_path, &commit_args);
 
-        for (idx, group) in param.commits.iter().enumerate() {
-            // 先清空暂存区，确保只提交当前组的文件
-            let _ = Command::new("git")
-                .args(["reset", "HEAD"])
-                .current_dir(&repo_path)
-                .output();
-
-            // 获取提交类型信息
-            let type_info = COMMIT_TYPES
-                .iter()
-                .find(|t| t.name == group.commit_type)
-                .unwrap_or(&COMMIT_TYPES[0]);
+    match commit_output {
+        Ok(output) if output.status.success() => Ok(format!(
+            "✅ 第{}组 [{}]: {} ({} 个文件){}",
+            idx + 1,
+            group.commit_type,
+            short_desc,
+            group.files.len(),
+            if no_verify { "（已跳过钩子）" } else { "" }
+        )),
+        Ok(output) => Err(format!(
+            "❌ 第{}组 [{}] git commit 失败: {}",
+            idx + 1,
+            group.commit_type,
+            String::from_utf8_lossy(&output.stderr)
+        )),
+        Err(e) => Err(format!(
+            "❌ 第{}组 [{}] 执行 git commit 失败: {}",
+            idx + 1,
+            group.commit_type,
+            e
+        )),
+    }
+}
 
-            // 构建提交信息
-            let details_str = group.details
-                .iter()
-                .map(|d| format!("- {}", d))
-                .collect::<Vec<_>>()
-                .join("\n");
+/// 汇报 smart_commit 进度：始终写入 stderr，客户端提供 progressToken 时同时发送进度通知
+async fn report_progress(
+    peer: &Peer<RoleServer>,
+    token: Option<&ProgressToken>,
+    progress: usize,
+    total: usize,
+    message: &str,
+) {
+    eprintln!("[smart_commit {}/{}] {}", progress, total, message.trim_end());
+    if let Some(token) = token {
+        let _ = peer
+            .notify_progress(ProgressNotificationParam {
+                progress_token: token.clone(),
+                progress: progress as u32,
+                total: Some(total as u32),
+                message: Some(message.to_string()),
+            })
+            .await;
+    }
+}
 
-            let commit_msg = if group.details.is_empty() {
-                format!("{} {}: {}", type_info.emoji, type_info.name, group.short_desc)
-            } else {
-                format!(
-                    "{} {}: {}\n\n详细描述：\n{}",
-                    type_info.emoji, type_info.name, group.short_desc, details_str
-                )
-            };
+/// 解析带 K/M/G 后缀的大小，例如 `10M`、`512KB`
+fn parse_size(raw: &str) -> Option<u64> {
+    let raw = raw.trim().to_uppercase();
+    let raw = raw.trim_end_matches('B');
+    let (num, unit) = match raw.chars().last() {
+        Some('K') => (&raw[..raw.len() - 1], 1024),
+        Some('M') => (&raw[..raw.len() - 1], 1024 * 1024),
+        Some('G') => (&raw[..raw.len() - 1], 1024 * 1024 * 1024),
+        _ => (raw, 1),
+    };
+    // 数值过大导致溢出时视为无效，由调用方使用默认值
+    num.trim().parse::<u64>().ok().and_then(|n| n.checked_mul(unit))
+}
+
+/// 单文件大小上限（字节），来自 GIT_MCP_MAX_FILE_SIZE，支持 K/M/G 后缀，默认 10MB
+fn max_file_size() -> u64 {
+    const DEFAULT: u64 = 10 * 1024 * 1024;
+    std::env::var("GIT_MCP_MAX_FILE_SIZE")
+        .ok()
+        .and_then(|raw| parse_size(&raw))
+        .unwrap_or(DEFAULT)
+}
 
-            // git add 指定文件
-            let mut add_args = vec!["add".to_string(), "--".to_string()];
-            add_args.extend(group.files.clone());
+/// smart_commit 单次允许的提交组数量上限，来自 GIT_MCP_MAX_COMMIT_GROUPS，默认 50
+fn max_commit_groups() -> usize {
+    std::env::var("GIT_MCP_MAX_COMMIT_GROUPS")
+        .ok()
+        .and_then(|raw| raw.trim().parse::<usize>().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(50)
+}
 
-            let add_output = Command::new("git")
-                .args(&add_args)
-                .current_dir(&repo_path)
-                .output();
+/// 工具输出的详细程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+}
 
-            match add_output {
-                Ok(output) if !output.status.success() => {
-                    results.push(format!(
-                        "❌ 第{}组 [{}] git add 失败: {}",
-                        idx + 1,
-                        group.commit_type,
-                        String::from_utf8_lossy(&output.stderr)
-                    ));
-                    continue;
-                }
-                Err(e) => {
-                    results.push(format!(
-                        "❌ 第{}组 [{}] 执行 git add 失败: {}",
-                        idx + 1,
-                        group.commit_type,
-                        e
-                    ));
-                    continue;
-                }
-                _ => {}
-            }
+/// 输出详细程度：参数优先，其次 GIT_MCP_VERBOSITY 环境变量（无法识别时忽略），默认 normal
+fn verbosity(param: Option<&str>) -> Result<Verbosity, String> {
+    let parse = |raw: &str| match raw.trim().to_lowercase().as_str() {
+        "quiet" => Some(Verbosity::Quiet),
+        "normal" => Some(Verbosity::Normal),
+        "verbose" => Some(Verbosity::Verbose),
+        _ => None,
+    };
+    match param.map(str::trim).filter(|v| !v.is_empty()) {
+        Some(raw) => parse(raw).ok_or_else(|| format!("❌ 不支持的 verbosity {}，可选 quiet、normal、verbose", raw)),
+        None => Ok(std::env::var("GIT_MCP_VERBOSITY")
+            .ok()
+            .and_then(|raw| parse(&raw))
+            .unwrap_or(Verbosity::Normal)),
+    }
+}
 
-            // git commit
-            let commit_output = Command::new("git")
-                .args(["commit", "-m", &commit_msg])
-                .current_dir(&repo_path)
-                .output();
+/// verbose 输出中的提交详情：哈希、提交的文件列表和完整提交信息
+fn describe_commit(repo: &Repository, commit: &git2::Commit) -> String {
+    let parent_tree = commit.parent(0).ok().and_then(|p| p.tree().ok());
+    let files: Vec<String> = commit
+        .tree()
+        .and_then(|tree| repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None))
+        .map(|diff| {
+            diff.deltas()
+                .map(|d| {
+                    let path = d.new_file().path().or_else(|| d.old_file().path());
+                    format!("- {} {}", delta_label(d.status()), path.map(|p| p.display().to_string()).unwrap_or_default())
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let files_str = if files.is_empty() {
+        "（没有文件变更）".to_string()
+    } else {
+        files.join("\n")
+    };
+    format!(
+        "🔖 {}\n📂 提交的文件（{} 个）：\n{}\n\n```\n{}\n```",
+        commit.id(),
+        files.len(),
+        files_str,
+        commit.message().unwrap_or("").trim_end()
+    )
+}
 
-            match commit_output {
-                Ok(output) if output.status.success() => {
-                    success_count += 1;
-                    results.push(format!(
-                        "✅ 第{}组 [{}]: {} ({} 个文件)",
-                        idx + 1,
-                        group.commit_type,
-                        group.short_desc,
-                        group.files.len()
-                    ));
-                }
-                Ok(output) => {
-                    results.push(format!(
-                        "❌ 第{}组 [{}] git commit 失败: {}",
-                        idx + 1,
-                        group.commit_type,
-                        String::from_utf8_lossy(&output.stderr)
-                    ));
-                }
-                Err(e) => {
-                    results.push(format!(
-                        "❌ 第{}组 [{}] 执行 git commit 失败: {}",
-                        idx + 1,
-                        group.commit_type,
-                        e
-                    ));
-                }
-            }
+/// git 子命令的超时时间，来自 GIT_MCP_COMMAND_TIMEOUT（秒），默认 60 秒
+fn command_timeout() -> std::time::Duration {
+    let secs = std::env::var("GIT_MCP_COMMAND_TIMEOUT")
+        .ok()
+        .and_then(|raw| raw.trim().parse::<u64>().ok())
+        .filter(|secs| *secs > 0)
+        .unwrap_or(60);
+    std::time::Duration::from_secs(secs)
+}
+
+/// git 子命令 stdout / stderr 各自的输出上限（字节），来自 GIT_MCP_MAX_OUTPUT，默认 1MB
+fn max_output_size() -> u64 {
+    const DEFAULT: u64 = 1024 * 1024;
+    std::env::var("GIT_MCP_MAX_OUTPUT")
+        .ok()
+        .and_then(|raw| parse_size(&raw))
+        .filter(|size| *size > 0)
+        .unwrap_or(DEFAULT)
+}
+
+/// 在后台线程读取子进程输出，超过上限时标记并停止读取
+fn read_capped<R: std::io::Read + Send + 'static>(
+    pipe: Option<R>,
+    cap: u64,
+    oversized: Arc<std::sync::atomic::AtomicBool>,
+) -> std::thread::JoinHandle<Vec<u8>> {
+    use std::io::Read;
+
+    std::thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(pipe) = pipe {
+            let _ = pipe.take(cap + 1).read_to_end(&mut buf);
+        }
+        if buf.len() as u64 > cap {
+            buf.truncate(cap as usize);
+            oversized.store(true, std::sync::atomic::Ordering::Relaxed);
         }
+        buf
+    })
+}
 
-        let summary = format!(
-            "📊 分类提交完成：{}/{} 组成功\n\n{}",
-            success_count,
-            param.commits.len(),
-            results.join("\n")
-        );
+/// 系统是否安装了 git 命令，首次调用时执行 git --version 探测并缓存结果
+fn git_available() -> bool {
+    static AVAILABLE: std::sync::OnceLock<bool> = std::sync::OnceLock::new();
+    *AVAILABLE.get_or_init(|| {
+        Command::new("git")
+            .arg("--version")
+            .stdin(std::process::Stdio::null())
+            .output()
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+    })
+}
 
-        if success_count > 0 {
-            format!("{}\n\n💡 如需推送，请执行: git push", summary)
-        } else {
-            summary
+/// 在仓库目录执行 git 子命令，超时或输出过大时终止进程并返回错误，避免卡住整个服务
+fn run_git<I, S>(repo_path: &str, args: I) -> std::io::Result<std::process::Output>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<std::ffi::OsStr>,
+{
+    use std::process::Stdio;
+
+    if !git_available() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "系统未安装 git 或不在 PATH 中，该工具依赖 git 命令行，请安装 git 后重启服务",
+        ));
+    }
+
+    let timeout = command_timeout();
+    let cap = max_output_size();
+    // 不继承标准输入，需要交互（如凭据提示）的命令会直接失败而不是挂起
+    let mut child = Command::new("git")
+        .args(args)
+        .current_dir(repo_path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let oversized = Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let stdout = read_capped(child.stdout.take(), cap, oversized.clone());
+    let stderr = read_capped(child.stderr.take(), cap, oversized.clone());
+
+    let deadline = std::time::Instant::now() + timeout;
+    let mut timed_out = false;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
         }
+        timed_out = std::time::Instant::now() >= deadline;
+        if timed_out || oversized.load(std::sync::atomic::Ordering::Relaxed) {
+            let _ = child.kill();
+            break child.wait()?;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    };
+
+    // 被终止时不等待读取线程：钩子等孙进程可能仍占用管道
+    if timed_out {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            format!(
+                "命令执行超过 {} 秒，已终止（可通过 GIT_MCP_COMMAND_TIMEOUT 调整）",
+                timeout.as_secs()
+            ),
+        ));
     }
+    if oversized.load(std::sync::atomic::Ordering::Relaxed) {
+        return Err(std::io::Error::other(format!(
+            "输出超过上限 {}，已终止命令（可通过 GIT_MCP_MAX_OUTPUT 调整）",
+            format_size(cap)
+        )));
+    }
+
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+    if oversized.load(std::sync::atomic::Ordering::Relaxed) {
+        return Err(std::io::Error::other(format!(
+            "输出超过上限 {}（可通过 GIT_MCP_MAX_OUTPUT 调整）",
+            format_size(cap)
+        )));
+    }
+
+    Ok(std::process::Output { status, stdout, stderr })
 }
 
-#[tool_handler]
-impl ServerHandler for GitMcpServer {
-    fn get_info(&self) -> ServerInfo {
-        ServerInfo {
-            protocol_version: ProtocolVersion::V_2025_03_26,
-            instructions: Some("Git MCP Server - 提供 Git 操作工具，支持查看状态、生成规范提交信息、执行提交等功能".to_string()),
-            capabilities: ServerCapabilities::builder().enable_tools().build(),
-            ..Default::default()
-        }
+/// 自动化署名脚注，来自 GIT_MCP_ATTRIBUTION，必须是 `Key: value` 形式，例如 `Generated-by: git-mcp`
+fn attribution_trailer() -> Result<Option<String>, String> {
+    let Ok(raw) = std::env::var("GIT_MCP_ATTRIBUTION") else {
+        return Ok(None);
+    };
+    let trailer = raw.trim();
+    if trailer.is_empty() {
+        return Ok(None);
+    }
+    let valid = regex::Regex::new(r"^[A-Za-z0-9][A-Za-z0-9-]*: \S")
+        .map(|re| re.is_match(trailer) && !trailer.contains('\n'))
+        .unwrap_or(false);
+    if !valid {
+        return Err(format!(
+            "❌ GIT_MCP_ATTRIBUTION 格式无效: {}，应为 `Key: value`，例如 `Generated-by: git-mcp`",
+            trailer
+        ));
     }
+    Ok(Some(trailer.to_string()))
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let server = GitMcpServer::new().serve(stdio()).await?;
-    server.waiting().await?;
-    Ok(())
+/// 在提交信息末尾追加 trailer：末段已是 trailer 时直接续写，已包含相同 trailer 时不重复添加
+fn append_trailer(message: &str, trailer: &str) -> String {
+    let message = message.trim_end();
+    if message.lines().any(|line| line.trim() == trailer) {
+        return message.to_string();
+    }
+    let last_paragraph = message.rsplit("\n\n").next().unwrap_or("");
+    let is_trailer_block = message.contains("\n\n")
+        && last_paragraph.lines().all(|line| {
+            line.split_once(": ")
+                .is_some_and(|(key, _)| !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'))
+        });
+    if is_trailer_block {
+        format!("{}\n{}", message, trailer)
+    } else {
+        format!("{}\n\n{}", message, trailer)
+    }
+}
+
+/// 将字节数格式化为易读的大小
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// 找出即将被暂存（新增或修改）且超过大小上限的文件，按大小降序
+fn find_large_files(repo: &Repository, threshold: u64) -> Result<Vec<(String, u64)>, git2::Error> {
+    let workdir = match repo.workdir() {
+        Some(w) => w.to_path_buf(),
+        None => return Ok(Vec::new()),
+    };
+
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(true).recurse_untracked_dirs(true);
+
+    let mut large = Vec::new();
+    for entry in repo.statuses(Some(&mut opts))?.iter() {
+        let status = entry.status();
+        let pending = status.is_wt_new()
+            || status.is_wt_modified()
+            || status.is_index_new()
+            || status.is_index_modified();
+        if !pending {
+            continue;
+        }
+        let Some(path) = entry.path() else { continue };
+        if let Ok(meta) = std::fs::metadata(workdir.join(path)) {
+            if meta.is_file() && meta.len() > threshold {
+                large.push((path.to_string(), meta.len()));
+            }
+        }
+    }
+    large.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+    Ok(large)
+}
+
+/// 递归统计目录占用的字节数
+fn dir_size(path: &std::path::Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(meta) if meta.is_dir() => dir_size(&entry.path()),
+            Ok(meta) => meta.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// 大文件列表，每行一个文件
+fn format_large_files(large: &[(String, u64)]) -> String {
+    large
+        .iter()
+        .map(|(path, size)| format!("- {} ({})", path, format_size(*size)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 指定文件（含未跟踪文件）相对 HEAD 的差异，即把它们作为一组提交时会包含的改动
+fn group_diff<'r>(repo: &'r Repository, files: &[String]) -> Result<git2::Diff<'r>, git2::Error> {
+    let mut opts = git2::DiffOptions::new();
+    opts.include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .show_untracked_content(true)
+        .disable_pathspec_match(true);
+    for file in files {
+        opts.pathspec(file);
+    }
+
+    let head_tree = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+    repo.diff_tree_to_workdir_with_index(head_tree.as_ref(), Some(&mut opts))
+}
+
+/// 二进制文件变更的说明，附带变更前后的大小
+fn binary_change(delta: &git2::DiffDelta) -> String {
+    format!(
+        "二进制文件已变更 ({} → {})",
+        format_size(delta.old_file().size()),
+        format_size(delta.new_file().size())
+    )
+}
+
+/// 差异中每个文件的增删行数，格式类似 git diff --stat
+fn diff_stat_lines(diff: &git2::Diff) -> Vec<String> {
+    let mut lines = Vec::new();
+    for (idx, delta) in diff.deltas().enumerate() {
+        let file = delta
+            .new_file()
+            .path()
+            .or_else(|| delta.old_file().path())
+            .map(|p| p.display().to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        // 生成补丁后 delta 才带有二进制标记和文件大小
+        let patch = git2::Patch::from_diff(diff, idx);
+        let stat = match patch {
+            Ok(Some(patch)) if patch.delta().flags().is_binary() => binary_change(&patch.delta()),
+            Ok(Some(patch)) => match patch.line_stats() {
+                Ok((_, additions, deletions)) => format!("+{} -{}", additions, deletions),
+                Err(_) => "?".to_string(),
+            },
+            _ => "二进制".to_string(),
+        };
+        lines.push(format!("- {} {} | {}", delta_label(delta.status()), file, stat));
+    }
+    lines
+}
+
+/// 将差异渲染为统一 diff 文本
+fn diff_to_patch_text(diff: &git2::Diff) -> Result<String, git2::Error> {
+    let mut text = String::new();
+    diff.print(git2::DiffFormat::Patch, |delta, _, line| {
+        match line.origin() {
+            '+' | '-' | ' ' => text.push(line.origin()),
+            // 二进制内容不做文本对比，只说明大小变化
+            'B' => {
+                text.push_str(&format!("{}\n", binary_change(&delta)));
+                return true;
+            }
+            _ => {}
+        }
+        text.push_str(&String::from_utf8_lossy(line.content()));
+        true
+    })?;
+    Ok(text)
+}
+
+/// 结构化差异中的单个文件
+#[derive(Debug, Serialize)]
+struct DiffFileJson {
+    old_path: Option<String>,
+    new_path: Option<String>,
+    status: &'static str,
+    binary: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    old_size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    new_size: Option<u64>,
+    hunks: Vec<DiffHunkJson>,
+}
+
+/// 结构化差异中的单个 hunk
+#[derive(Debug, Serialize)]
+struct DiffHunkJson {
+    old_start: u32,
+    old_lines: u32,
+    new_start: u32,
+    new_lines: u32,
+    lines: Vec<DiffLineJson>,
+}
+
+/// hunk 中的一行，origin 为 `+` / `-` / ` `
+#[derive(Debug, Serialize)]
+struct DiffLineJson {
+    origin: String,
+    content: String,
+}
+
+/// 差异工具的输出格式是否为 json，默认 text
+fn wants_json(format: Option<&str>) -> Result<bool, String> {
+    match format.map(|f| f.trim().to_lowercase()).as_deref() {
+        None | Some("") | Some("text") => Ok(false),
+        Some("json") => Ok(true),
+        Some(other) => Err(format!("❌ 不支持的输出格式 {}，可选值: text / json", other)),
+    }
+}
+
+/// 将差异按文件和 hunk 转为结构化数据
+fn diff_to_json(diff: &git2::Diff) -> Result<Vec<DiffFileJson>, git2::Error> {
+    let files = std::cell::RefCell::new(Vec::<DiffFileJson>::new());
+    let path_of = |file: git2::DiffFile| file.path().map(|p| p.display().to_string());
+
+    diff.foreach(
+        &mut |delta, _| {
+            // 新增文件没有旧路径，删除文件没有新路径
+            let added = matches!(delta.status(), git2::Delta::Added | git2::Delta::Untracked);
+            let deleted = delta.status() == git2::Delta::Deleted;
+            // 二进制文件没有 hunk，改为给出变更前后的大小
+            let binary = delta.flags().is_binary();
+            files.borrow_mut().push(DiffFileJson {
+                old_path: if added { None } else { path_of(delta.old_file()) },
+                new_path: if deleted { None } else { path_of(delta.new_file()) },
+                status: delta_label(delta.status()),
+                binary,
+                old_size: binary.then(|| delta.old_file().size()),
+                new_size: binary.then(|| delta.new_file().size()),
+                hunks: Vec::new(),
+            });
+            true
+        },
+        None,
+        Some(&mut |_, hunk| {
+            if let Some(file) = files.borrow_mut().last_mut() {
+                file.hunks.push(DiffHunkJson {
+                    old_start: hunk.old_start(),
+                    old_lines: hunk.old_lines(),
+                    new_start: hunk.new_start(),
+                    new_lines: hunk.new_lines(),
+                    lines: Vec::new(),
+                });
+            }
+            true
+        }),
+        Some(&mut |_, _, line| {
+            if let Some(hunk) = files.borrow_mut().last_mut().and_then(|f| f.hunks.last_mut()) {
+                hunk.lines.push(DiffLineJson {
+                    origin: line.origin().to_string(),
+                    content: String::from_utf8_lossy(line.content()).trim_end_matches('\n').to_string(),
+                });
+            }
+            true
+        }),
+    )?;
+
+    Ok(files.into_inner())
+}
+
+/// 差异工具 json 格式的返回内容
+fn diff_json_response(diff: &git2::Diff) -> Result<String, String> {
+    let files = diff_to_json(diff).map_err(|e| format!("❌ 生成差异失败: {}", e))?;
+    let json = serde_json::to_string_pretty(&files).unwrap_or_default();
+    Ok(format!("🔍 {} 个文件的差异：\n\n```json\n{}\n```", files.len(), json))
+}
+
+/// HEAD 处于游离状态时返回其短哈希
+fn detached_head(repo_path: &str) -> Option<String> {
+    let repo = Repository::open(repo_path).ok()?;
+    if !repo.head_detached().ok()? {
+        return None;
+    }
+    let oid = repo.head().ok()?.target()?;
+    Some(short_id(oid))
+}
+
+/// 提交后的提示：游离 HEAD 时提醒先创建分支，否则提示推送
+fn post_commit_hint(repo_path: &str) -> String {
+    match detached_head(repo_path) {
+        Some(id) => format!(
+            "⚠️ 当前处于游离 HEAD @ {}，提交不属于任何分支，切换分支后可能丢失\n💡 请先创建分支保留提交: git switch -c <分支名>",
+            id
+        ),
+        None => "💡 如需推送，请执行: git push".to_string(),
+    }
+}
+
+/// 推送当前分支到已配置的上游分支
+fn push_upstream(repo_path: &str) -> String {
+    let output = run_git(repo_path, ["push"]);
+
+    match output {
+        Ok(o) if o.status.success() => "🚀 已推送到上游分支".to_string(),
+        Ok(o) => format!(
+            "❌ 自动推送失败: {}\n💡 如未配置上游分支，可先调用 git_set_upstream",
+            String::from_utf8_lossy(&o.stderr).trim()
+        ),
+        Err(e) => format!("❌ 执行 git push 失败: {}", e),
+    }
+}
+
+/// 提交完成后的收尾：需要自动推送且不在游离 HEAD 时执行推送，否则给出提示
+fn after_commit(repo_path: &str, auto_push: bool) -> String {
+    if auto_push && detached_head(repo_path).is_none() {
+        push_upstream(repo_path)
+    } else {
+        post_commit_hint(repo_path)
+    }
+}
+
+/// 读取 commit.template 配置的模板文件，返回（模板路径, 内容）
+fn commit_template(repo_path: &str) -> Option<(String, String)> {
+    let repo = Repository::discover(repo_path).ok()?;
+    let configured = repo.config().ok()?.get_string("commit.template").ok()?;
+
+    let file = if let Some(rest) = configured.strip_prefix("~/") {
+        let home = std::env::var("HOME").or_else(|_| std::env::var("USERPROFILE")).ok()?;
+        std::path::Path::new(&home).join(rest)
+    } else {
+        let path = std::path::PathBuf::from(&configured);
+        match repo.workdir() {
+            Some(workdir) if path.is_relative() => workdir.join(path),
+            _ => path,
+        }
+    };
+
+    let content = std::fs::read_to_string(&file).ok()?;
+    Some((configured, content.trim_end().to_string()))
+}
+
+/// 钩子所在目录：优先使用 core.hooksPath（相对路径基于工作区根目录），否则为 .git/hooks
+fn hooks_dir(repo: &Repository) -> std::path::PathBuf {
+    let configured = repo.config().ok().and_then(|c| c.get_path("core.hooksPath").ok());
+    match configured {
+        Some(path) if path.is_relative() => repo.workdir().unwrap_or(repo.path()).join(path),
+        Some(path) => path,
+        None => repo.path().join("hooks"),
+    }
+}
+
+/// 仓库的唯一标识（工作区根目录），用于区分 "." 和绝对路径等不同写法
+fn repo_key(repo_path: &str) -> String {
+    Repository::discover(repo_path)
+        .ok()
+        .and_then(|r| r.workdir().map(|p| p.to_path_buf()))
+        .or_else(|| std::fs::canonicalize(repo_path).ok())
+        .map(|p| p.display().to_string())
+        .unwrap_or_else(|| repo_path.to_string())
+}
+
+/// 执行 `git rebase --abort` 等中止命令
+fn run_abort_command(repo_path: &str, args: &[&str]) -> Result<(), String> {
+    match run_git(repo_path, args) {
+        Ok(o) if o.status.success() => Ok(()),
+        Ok(o) => Err(format!("❌ git {} 失败: {}", args.join(" "), String::from_utf8_lossy(&o.stderr))),
+        Err(e) => Err(format!("❌ 执行 git {} 失败: {}", args.join(" "), e)),
+    }
+}
+
+/// 解析 `<prefix>MAJOR.MINOR.PATCH` 形式的标签，带预发布等后缀的标签不计入
+fn parse_semver_tag(tag: &str, prefix: &str) -> Option<(u64, u64, u64)> {
+    let mut parts = tag.strip_prefix(prefix)?.split('.');
+    let version = (
+        parts.next()?.parse().ok()?,
+        parts.next()?.parse().ok()?,
+        parts.next()?.parse().ok()?,
+    );
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(version)
+}
+
+/// 构造带认证回调的远程操作回调，依次尝试 ssh-agent、GIT_MCP_SSH_KEY 私钥和 GIT_MCP_TOKEN，
+/// 已尝试的方式记录在 attempted 中，用于失败时说明原因
+fn credential_callbacks(attempted: &std::cell::RefCell<Vec<String>>) -> git2::RemoteCallbacks<'_> {
+    let mut callbacks = git2::RemoteCallbacks::new();
+    callbacks.credentials(move |url, username_from_url, allowed| {
+        // 同一次连接中认证失败会再次回调，按已尝试次数依次切换到下一种方式
+        let mut attempted = attempted.borrow_mut();
+        let user = username_from_url.unwrap_or("git");
+
+        if allowed.contains(git2::CredentialType::USERNAME) {
+            return git2::Cred::username(user);
+        }
+        if allowed.contains(git2::CredentialType::SSH_KEY) {
+            if !attempted.iter().any(|m| m == "ssh-agent") {
+                attempted.push("ssh-agent".to_string());
+                return git2::Cred::ssh_key_from_agent(user);
+            }
+            if let Ok(key) = std::env::var("GIT_MCP_SSH_KEY") {
+                let method = format!("GIT_MCP_SSH_KEY（{}）", key);
+                if !attempted.contains(&method) {
+                    attempted.push(method);
+                    return git2::Cred::ssh_key(user, None, std::path::Path::new(&key), None);
+                }
+            }
+        }
+        if allowed.contains(git2::CredentialType::USER_PASS_PLAINTEXT) {
+            if let Ok(token) = std::env::var("GIT_MCP_TOKEN") {
+                let method = "GIT_MCP_TOKEN".to_string();
+                if !attempted.contains(&method) {
+                    attempted.push(method);
+                    // 支持 `用户名:令牌`，只提供令牌时用户名取 URL 中的用户名
+                    let (token_user, token) = match token.split_once(':') {
+                        Some((u, t)) => (u.to_string(), t.to_string()),
+                        None => (username_from_url.unwrap_or("x-access-token").to_string(), token),
+                    };
+                    return git2::Cred::userpass_plaintext(&token_user, &token);
+                }
+            }
+        }
+        Err(git2::Error::from_str(&format!("{} 没有可用的认证方式", url)))
+    });
+    callbacks
+}
+
+/// 远程操作失败的说明，认证相关时附上已尝试的认证方式
+fn remote_error(e: &git2::Error, attempted: &std::cell::RefCell<Vec<String>>) -> String {
+    let attempted = attempted.borrow();
+    let auth_related = e.code() == git2::ErrorCode::Auth
+        || matches!(e.class(), git2::ErrorClass::Ssh | git2::ErrorClass::Http);
+    if !auth_related {
+        return e.message().to_string();
+    }
+    if attempted.is_empty() {
+        format!(
+            "{}\n💡 未尝试任何认证方式，可通过 ssh-agent、GIT_MCP_SSH_KEY 或 GIT_MCP_TOKEN 提供凭据",
+            e.message()
+        )
+    } else {
+        format!("{}\n💡 已尝试的认证方式均失败: {}", e.message(), attempted.join("、"))
+    }
+}
+
+/// 某一行在一次提交中的变化
+struct LineChange {
+    commit: git2::Oid,
+    /// 修改前的内容，None 表示该行由这次提交新增
+    before: Option<String>,
+    after: String,
+}
+
+/// 读取提交中文件第 line 行（从 1 开始）的内容
+fn line_at(repo: &Repository, commit: &git2::Commit, file: &str, line: usize) -> Option<String> {
+    let entry = commit.tree().ok()?.get_path(std::path::Path::new(file)).ok()?;
+    let blob = entry.to_object(repo).ok()?.peel_to_blob().ok()?;
+    String::from_utf8_lossy(blob.content())
+        .lines()
+        .nth(line.checked_sub(1)?)
+        .map(|l| l.to_string())
+}
+
+/// 从 HEAD 开始沿 blame 逐级回溯某一行的修改历史：每次 blame 找到最后修改该行的提交，
+/// 再通过该提交的差异把行号映射到父提交，直到该行被新增或达到 max 条
+fn line_history(repo: &Repository, file: &str, line: usize, max: usize) -> Result<Vec<LineChange>, git2::Error> {
+    let mut changes = Vec::new();
+    let mut rev = repo.head()?.peel_to_commit()?.id();
+    let mut file = file.to_string();
+    let mut line = line;
+
+    while changes.len() < max {
+        let mut opts = git2::BlameOptions::new();
+        opts.newest_commit(rev);
+        let blame = repo.blame_file(std::path::Path::new(&file), Some(&mut opts))?;
+        let Some(hunk) = blame.get_line(line) else { break };
+
+        let commit = repo.find_commit(hunk.final_commit_id())?;
+        let path = hunk.path().map(|p| p.display().to_string()).unwrap_or(file.clone());
+        let orig_line = hunk.orig_start_line() + (line - hunk.final_start_line());
+        let after = line_at(repo, &commit, &path, orig_line).unwrap_or_default();
+
+        let Ok(parent) = commit.parent(0) else {
+            changes.push(LineChange { commit: commit.id(), before: None, after });
+            break;
+        };
+
+        // 在该提交的差异中找到这一行，按其在新增行中的位置对应到被删除的旧行
+        let mut diff_opts = git2::DiffOptions::new();
+        diff_opts.pathspec(&path).disable_pathspec_match(true);
+        let diff = repo.diff_tree_to_tree(Some(&parent.tree()?), Some(&commit.tree()?), Some(&mut diff_opts))?;
+        let mut previous = None;
+        if let Some(patch) = git2::Patch::from_diff(&diff, 0)? {
+            for h in 0..patch.num_hunks() {
+                let mut removed = Vec::new();
+                let mut added = 0;
+                for l in 0..patch.num_lines_in_hunk(h)? {
+                    let diff_line = patch.line_in_hunk(h, l)?;
+                    match diff_line.origin() {
+                        '-' => removed.push((
+                            diff_line.old_lineno().unwrap_or(0) as usize,
+                            String::from_utf8_lossy(diff_line.content()).trim_end_matches('\n').to_string(),
+                        )),
+                        '+' if diff_line.new_lineno() == Some(orig_line as u32) => {
+                            previous = removed.get(added).cloned();
+                            break;
+                        }
+                        '+' => added += 1,
+                        // 上下文行分隔不同的修改块，只在同一块内配对增删行
+                        _ => {
+                            removed.clear();
+                            added = 0;
+                        }
+                    }
+                }
+            }
+        }
+
+        changes.push(LineChange {
+            commit: commit.id(),
+            before: previous.as_ref().map(|(_, content)| content.clone()),
+            after,
+        });
+        match previous {
+            Some((old_line, _)) if old_line > 0 => {
+                rev = parent.id();
+                file = path;
+                line = old_line;
+            }
+            _ => break,
+        }
+    }
+
+    Ok(changes)
+}
+
+/// 按路径推断文件所属的提交分组，返回（分组键, 提交类型）；普通代码按顶层目录分组，类型留给调用方决定
+fn classify_change(path: &str) -> (String, Option<&'static str>) {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let ext = name.rsplit_once('.').map(|(_, e)| e.to_lowercase()).unwrap_or_default();
+    const BUILD_FILES: [&str; 10] = [
+        "Cargo.toml", "Cargo.lock", "build.rs", "package.json", "package-lock.json",
+        "go.mod", "go.sum", "pyproject.toml", "requirements.txt", "Makefile",
+    ];
+
+    if path.starts_with(".github/") || path.starts_with(".circleci/") || name == ".gitlab-ci.yml" {
+        ("ci".to_string(), Some("ci"))
+    } else if path.starts_with("tests/") || path.starts_with("test/") || name.contains("_test.") || name.contains(".test.") || name.starts_with("test_") {
+        ("test".to_string(), Some("test"))
+    } else if path.starts_with("docs/") || ["md", "rst", "adoc"].contains(&ext.as_str()) {
+        ("docs".to_string(), Some("docs"))
+    } else if BUILD_FILES.contains(&name) {
+        ("build".to_string(), Some("build"))
+    } else if name.starts_with('.') || ["toml", "yml", "yaml", "ini", "json"].contains(&ext.as_str()) {
+        ("config".to_string(), Some("config"))
+    } else {
+        let dir = path.split_once('/').map(|(d, _)| d).unwrap_or("根目录");
+        (dir.to_string(), None)
+    }
+}
+
+/// 将 Unix 时间戳（已按时区偏移）格式化为 YYYY-MM-DD
+fn format_date(secs: i64) -> String {
+    // 公历日期换算（Howard Hinnant 的 civil_from_days 算法）
+    let days = secs.div_euclid(86400);
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// 将 git2 时间格式化为 YYYY-MM-DD HH:MM:SS +hhmm（按其自身时区）
+fn format_time(time: git2::Time) -> String {
+    let local = time.seconds() + i64::from(time.offset_minutes()) * 60;
+    let secs = local.rem_euclid(86400);
+    let offset = time.offset_minutes();
+    format!(
+        "{} {:02}:{:02}:{:02} {}{:02}{:02}",
+        format_date(local),
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60,
+        if offset < 0 { '-' } else { '+' },
+        offset.abs() / 60,
+        offset.abs() % 60
+    )
+}
+
+/// 公历日期距 1970-01-01 的天数（format_date 的逆运算）
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// 解析作者时间：now、Unix 时间戳或 YYYY-MM-DD[ HH:MM[:SS]][ ±HH:MM|±HHMM|Z]，未写时区时为 UTC
+fn parse_commit_date(raw: &str) -> Result<git2::Time, String> {
+    let raw = raw.trim();
+    let invalid = || {
+        format!(
+            "❌ 无法解析时间 {}，支持 now、Unix 时间戳或 YYYY-MM-DD[ HH:MM[:SS]][ +08:00]",
+            raw
+        )
+    };
+    if raw.eq_ignore_ascii_case("now") {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or_default();
+        return Ok(git2::Time::new(now, 0));
+    }
+    if let Ok(secs) = raw.trim_start_matches('@').parse::<i64>() {
+        return Ok(git2::Time::new(secs, 0));
+    }
+
+    static DATE: std::sync::OnceLock<regex::Regex> = std::sync::OnceLock::new();
+    let re = DATE.get_or_init(|| {
+        regex::Regex::new(
+            r"^(\d{4})-(\d{2})-(\d{2})(?:[ T](\d{2}):(\d{2})(?::(\d{2}))?)?\s*(Z|[+-]\d{2}:?\d{2})?$",
+        )
+        .unwrap()
+    });
+    let caps = re.captures(raw).ok_or_else(invalid)?;
+    let num = |i: usize| caps.get(i).map_or(0, |m| m.as_str().parse::<i64>().unwrap_or(0));
+    let (year, month, day) = (num(1), num(2), num(3));
+    let (hour, minute, second) = (num(4), num(5), num(6));
+    // 超出当月天数的日期（如 2024-02-30）直接拒绝，不顺延到下个月
+    let month_days = match month {
+        2 if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    };
+    if !(1..=12).contains(&month) || !(1..=month_days).contains(&day) || hour > 23 || minute > 59 || second > 60 {
+        return Err(invalid());
+    }
+
+    let offset = match caps.get(7).map(|m| m.as_str()) {
+        None | Some("Z") => 0,
+        Some(tz) => {
+            let digits = tz[1..].replace(':', "");
+            let minutes = digits[..2].parse::<i32>().unwrap_or(0) * 60 + digits[2..].parse::<i32>().unwrap_or(0);
+            if tz.starts_with('-') { -minutes } else { minutes }
+        }
+    };
+    let local = days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second;
+    Ok(git2::Time::new(local - i64::from(offset) * 60, offset))
+}
+
+/// 提交是否已出现在任一远程跟踪分支上（即已推送）
+fn pushed_to_remote(repo: &Repository, oid: git2::Oid) -> Option<String> {
+    let refs = repo.references_glob("refs/remotes/*").ok()?;
+    refs.flatten().find_map(|r| {
+        let target = r.target()?;
+        let name = r.shorthand()?.to_string();
+        if name.ends_with("/HEAD") {
+            return None;
+        }
+        let contains = target == oid || repo.graph_descendant_of(target, oid).unwrap_or(false);
+        contains.then_some(name)
+    })
+}
+
+/// 替换详细描述中的 {branch} / {date} / {user} 占位符，返回替换结果和无法解析的占位符
+fn interpolate_details(details: &[String], repo_path: &str) -> (Vec<String>, Vec<&'static str>) {
+    let repo = Repository::open(repo_path).ok();
+    let branch = repo.as_ref().and_then(|r| {
+        let head = r.head().ok().filter(|h| h.is_branch())?;
+        head.shorthand().map(|s| s.to_string())
+    });
+    let signature = repo.as_ref().and_then(|r| r.signature().ok());
+    let user = signature.as_ref().and_then(|s| s.name().map(|n| n.to_string()));
+    // 日期按提交者所在时区计算，取不到身份时使用 UTC
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default();
+    let offset = signature.as_ref().map(|s| s.when().offset_minutes()).unwrap_or(0);
+    let date = format_date(now + i64::from(offset) * 60);
+
+    let values = [("{branch}", branch), ("{date}", Some(date)), ("{user}", user)];
+    let mut unresolved = Vec::new();
+    let details = details
+        .iter()
+        .map(|detail| {
+            let mut detail = detail.clone();
+            for (token, value) in &values {
+                if !detail.contains(token) {
+                    continue;
+                }
+                match value {
+                    Some(v) => detail = detail.replace(token, v),
+                    None if !unresolved.contains(token) => unresolved.push(*token),
+                    None => {}
+                }
+            }
+            detail
+        })
+        .collect();
+    (details, unresolved)
+}
+
+/// 从 HEAD 的 reflog 中提取切换分支记录，按时间从新到旧返回（目标, 切换时间）
+fn checkout_history(repo: &Repository) -> Result<Vec<(String, git2::Time)>, git2::Error> {
+    let reflog = repo.reflog("HEAD")?;
+    Ok(reflog
+        .iter()
+        .filter_map(|entry| {
+            let message = entry.message()?;
+            let (_, target) = message.strip_prefix("checkout: moving from ")?.rsplit_once(" to ")?;
+            Some((target.to_string(), entry.committer().when()))
+        })
+        .collect())
+}
+
+/// 代码托管平台，决定网页链接的路径格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WebHost {
+    GitHub,
+    GitLab,
+    Bitbucket,
+    Unknown,
+}
+
+/// 将远程地址（SSH / HTTPS）转为仓库网页地址，无法识别的地址返回 None
+fn web_base_url(remote_url: &str) -> Option<(String, WebHost)> {
+    let url = remote_url.trim();
+    // scp 形式 git@host:owner/repo.git，其余形式带协议头
+    let (host, repo_path) = if let Some((_, rest)) = url.split_once("://") {
+        let (authority, path) = rest.split_once('/')?;
+        let host = authority.rsplit('@').next()?;
+        // https 地址的端口属于网页地址，ssh 端口则不是
+        let host = if url.starts_with("http") { host } else { host.split(':').next()? };
+        (host.to_string(), path.to_string())
+    } else {
+        let (user_host, path) = url.split_once(':')?;
+        (user_host.rsplit('@').next()?.to_string(), path.to_string())
+    };
+    let repo_path = repo_path.trim_matches('/').trim_end_matches(".git").to_string();
+    if host.is_empty() || repo_path.is_empty() {
+        return None;
+    }
+
+    let lower = host.to_lowercase();
+    let kind = if lower.contains("github") {
+        WebHost::GitHub
+    } else if lower.contains("gitlab") {
+        WebHost::GitLab
+    } else if lower.contains("bitbucket") {
+        WebHost::Bitbucket
+    } else {
+        WebHost::Unknown
+    };
+    Some((format!("https://{}/{}", host, repo_path), kind))
+}
+
+/// 按提交类型分组的变更列表（发布说明格式），分组顺序与 COMMIT_TYPES 一致，无法识别类型的归入「其他」
+fn changelog_sections(commits: &[git2::Commit]) -> String {
+    let mut groups: Vec<(&str, Vec<String>)> = Vec::new();
+    for commit in commits {
+        let subject = commit.summary().unwrap_or("");
+        let kind = parse_subject_type(subject)
+            .and_then(|t| COMMIT_TYPES.iter().find(|ct| ct.name == t))
+            .map(|ct| ct.name)
+            .unwrap_or("other");
+        let line = format!("- {} {}", short_id(commit.id()), subject);
+        match groups.iter_mut().find(|(k, _)| *k == kind) {
+            Some((_, lines)) => lines.push(line),
+            None => groups.push((kind, vec![line])),
+        }
+    }
+
+    let order = |kind: &str| COMMIT_TYPES.iter().position(|ct| ct.name == kind).unwrap_or(COMMIT_TYPES.len());
+    groups.sort_by_key(|(kind, _)| order(kind));
+    groups
+        .iter()
+        .map(|(kind, lines)| {
+            let title = match COMMIT_TYPES.iter().find(|ct| ct.name == *kind) {
+                Some(ct) => format!("### {} {}（{}）", ct.emoji, ct.desc, ct.name),
+                None => "### 其他".to_string(),
+            };
+            format!("{}\n\n{}", title, lines.join("\n"))
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// 为拓扑序的提交分配泳道（列号），返回每个提交的泳道和各父提交所在的泳道
+fn assign_lanes(commits: &[(git2::Oid, Vec<git2::Oid>)]) -> Vec<(usize, Vec<usize>)> {
+    // lanes[i] 为第 i 列接下来期望出现的提交
+    let mut lanes: Vec<Option<git2::Oid>> = Vec::new();
+    let mut result = Vec::new();
+    for (oid, parents) in commits {
+        let lane = match lanes.iter().position(|l| *l == Some(*oid)) {
+            Some(i) => i,
+            None => match lanes.iter().position(|l| l.is_none()) {
+                Some(i) => i,
+                None => {
+                    lanes.push(None);
+                    lanes.len() - 1
+                }
+            },
+        };
+        // 多个分支汇聚到同一提交时，释放其余泳道
+        for l in lanes.iter_mut() {
+            if *l == Some(*oid) {
+                *l = None;
+            }
+        }
+
+        let mut parent_lanes = Vec::new();
+        for (idx, parent) in parents.iter().enumerate() {
+            let parent_lane = if idx == 0 {
+                // 父提交已在其他泳道等待时汇入该泳道，当前泳道随之释放
+                match lanes.iter().position(|l| *l == Some(*parent)) {
+                    Some(i) => i,
+                    None => {
+                        lanes[lane] = Some(*parent);
+                        lane
+                    }
+                }
+            } else if let Some(i) = lanes.iter().position(|l| *l == Some(*parent)) {
+                i
+            } else if let Some(i) = lanes.iter().position(|l| l.is_none()) {
+                lanes[i] = Some(*parent);
+                i
+            } else {
+                lanes.push(Some(*parent));
+                lanes.len() - 1
+            };
+            parent_lanes.push(parent_lane);
+        }
+        result.push((lane, parent_lanes));
+    }
+    result
+}
+
+/// 找出路径命中的第一条常见忽略规则
+fn ignore_suggestion(path: &str) -> Option<(&'static str, &'static str)> {
+    let is_dir = path.ends_with('/');
+    let parts: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+    let (dirs, name) = parts.split_at(parts.len() - 1);
+    IGNORE_SUGGESTIONS.iter().copied().find(|(_, rule)| {
+        if let Some(dir) = rule.strip_suffix('/') {
+            dirs.contains(&dir) || (is_dir && name[0] == dir)
+        } else if let Some(ext) = rule.strip_prefix('*') {
+            !is_dir && name[0].ends_with(ext)
+        } else {
+            !is_dir && name[0] == *rule
+        }
+    })
+}
+
+/// 内存合并结果中的冲突文件及冲突类型
+fn merge_conflicts(index: &git2::Index) -> Vec<(String, &'static str)> {
+    let Ok(conflicts) = index.conflicts() else {
+        return Vec::new();
+    };
+    conflicts
+        .flatten()
+        .filter_map(|c| {
+            let kind = match (&c.ancestor, &c.our, &c.their) {
+                (Some(_), Some(_), Some(_)) => "both_modified",
+                (None, Some(_), Some(_)) => "both_added",
+                (Some(_), Some(_), None) => "deleted_by_them",
+                (Some(_), None, Some(_)) => "deleted_by_us",
+                _ => "other",
+            };
+            let entry = c.our.or(c.their).or(c.ancestor)?;
+            Some((String::from_utf8_lossy(&entry.path).to_string(), kind))
+        })
+        .collect()
+}
+
+/// 校验文件参数是仓库内的相对路径：拒绝绝对路径、`..` 和 .git 目录，
+/// 并确认已存在的上级目录解析符号链接后仍位于工作区内
+fn repo_relative_path(workdir: &std::path::Path, file: &str) -> Result<std::path::PathBuf, String> {
+    use std::path::Component;
+
+    let mut rel = std::path::PathBuf::new();
+    for component in std::path::Path::new(file).components() {
+        match component {
+            Component::Normal(part) if part == ".git" => {
+                return Err(format!("❌ 不允许操作 .git 目录中的文件: {}", file));
+            }
+            Component::Normal(part) => rel.push(part),
+            Component::CurDir => {}
+            _ => return Err(format!("❌ 文件路径必须是仓库内的相对路径，不能是绝对路径或包含 ..: {}", file)),
+        }
+    }
+    if rel.as_os_str().is_empty() {
+        return Err(format!("❌ 无效的文件路径: {}", file));
+    }
+
+    let root = std::fs::canonicalize(workdir).map_err(|e| format!("❌ 无法解析工作区路径: {}", e))?;
+    let mut existing = workdir.join(&rel);
+    while !existing.exists() {
+        match existing.parent() {
+            Some(parent) => existing = parent.to_path_buf(),
+            None => break,
+        }
+    }
+    let resolved = std::fs::canonicalize(&existing).map_err(|e| format!("❌ 无法解析文件路径: {}", e))?;
+    if !resolved.starts_with(&root) {
+        return Err(format!("❌ 文件路径经符号链接指向了仓库之外: {}", file));
+    }
+    Ok(rel)
+}
+
+/// 当前 refs/stash 指向的储藏提交，没有储藏时为 None
+fn stash_top(repo_path: &str) -> Option<git2::Oid> {
+    Repository::open(repo_path).ok()?.refname_to_id("refs/stash").ok()
+}
+
+/// 在储藏列表中查找指定储藏提交，返回其 stash@{n} 引用
+fn stash_ref(repo_path: &str, oid: git2::Oid) -> Option<String> {
+    let mut repo = Repository::open(repo_path).ok()?;
+    let mut found = None;
+    let _ = repo.stash_foreach(|index, _, id| {
+        if *id == oid {
+            found = Some(format!("stash@{{{}}}", index));
+            return false;
+        }
+        true
+    });
+    found
+}
+
+/// 从 git verify-commit --raw 的输出中提取签名者：GPG 取 GOODSIG 状态行，SSH 取 ssh-keygen 的结果行
+fn verify_signer(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        if let Some(rest) = line.strip_prefix("[GNUPG:] GOODSIG ") {
+            // GOODSIG <长密钥 ID> <用户 ID>
+            rest.split_once(' ').map(|(_, user)| user.trim().to_string())
+        } else {
+            line.strip_prefix("Good \"git\" signature for ")
+                .and_then(|rest| rest.split(" with ").next())
+                .map(|s| s.to_string())
+        }
+    })
+}
+
+/// 提交的短哈希（7 位）
+fn short_id(oid: git2::Oid) -> String {
+    oid.to_string().chars().take(7).collect()
+}
+
+// ============================================
+// MCP Server
+// ============================================
+
+#[derive(Clone)]
+pub struct GitMcpServer {
+    tool_router: ToolRouter<Self>,
+    /// 各仓库的暂存计划（仓库根目录 → 计划提交的文件），跨多次调用逐步累积
+    plans: Arc<Mutex<HashMap<String, Vec<String>>>>,
+    /// 各仓库的写锁（仓库根目录 → 锁），串行化同一仓库上修改索引的操作，只读工具不受影响
+    repo_locks: Arc<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>>,
+}
+
+impl GitMcpServer {
+    pub fn new() -> Self {
+        Self {
+            tool_router: Self::tool_router(),
+            plans: Arc::new(Mutex::new(HashMap::new())),
+            repo_locks: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// 获取仓库的写锁，持有期间同一仓库的其他写操作会等待
+    async fn lock_repo(&self, repo_path: &str) -> tokio::sync::OwnedMutexGuard<()> {
+        let lock = {
+            let mut locks = self.repo_locks.lock().unwrap_or_else(|e| e.into_inner());
+            locks.entry(repo_key(repo_path)).or_default().clone()
+        };
+        lock.lock_owned().await
+    }
+}
+
+impl Default for GitMcpServer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[tool_router]
+impl GitMcpServer {
+    /// 获取 Git 仓库状态
+    #[tool(description = "获取 Git 仓库状态，显示所有变更文件（新增、修改、删除）")]
+    async fn git_status(&self, Parameters(param): Parameters<PathParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(true);
+
+        let statuses = match repo.statuses(Some(&mut opts)) {
+            Ok(s) => s,
+            Err(e) => return Err(format!("❌ 获取状态失败: {}", e)),
+        };
+
+        if statuses.is_empty() {
+            return Ok("✅ 工作区干净，没有变更".to_string());
+        }
+
+        let mut result = String::from("📊 变更导图：\n\n");
+        
+        for entry in statuses.iter() {
+            let path = entry.path().unwrap_or("unknown");
+            let status = entry.status();
+            let delta = entry.index_to_workdir().or_else(|| entry.head_to_index());
+
+            // 子模块以提交（gitlink）形式记录，显示其指针的变化而不是笼统的“修改”
+            if let Some(delta) = delta.as_ref().filter(|d| {
+                d.old_file().mode() == git2::FileMode::Commit || d.new_file().mode() == git2::FileMode::Commit
+            }) {
+                let (old_id, new_id) = (delta.old_file().id(), delta.new_file().id());
+                if old_id == new_id || new_id.is_zero() {
+                    result.push_str(&format!("🔗 子模块 {} 内有未提交的改动\n", path));
+                } else if old_id.is_zero() {
+                    result.push_str(&format!("🔗 新增子模块 {} @ {}\n", path, short_id(new_id)));
+                } else {
+                    result.push_str(&format!(
+                        "🔗 子模块更新 {} ({} → {})\n",
+                        path,
+                        short_id(old_id),
+                        short_id(new_id)
+                    ));
+                }
+                continue;
+            }
+
+            let (icon, status_str) = match change_label(status) {
+                Some(label) => label,
+                None => continue,
+            };
+            let link = delta
+                .filter(|d| d.old_file().mode() == git2::FileMode::Link || d.new_file().mode() == git2::FileMode::Link)
+                .map(|_| "（符号链接）")
+                .unwrap_or("");
+
+            result.push_str(&format!("{} {} {}{}\n", icon, status_str, path, link));
+        }
+
+        Ok(result)
+    }
+
+    /// 生成符合规范的 Git 提交信息
+    #[tool(description = "根据提交类型和描述生成符合规范的 Git 提交信息")]
+    async fn generate_commit_message(&self, Parameters(param): Parameters<CommitMessageParam>) -> Result<String, String> {
+        let short_desc = require_text("short_desc", &param.short_desc)?;
+
+        let repo_path = resolve_repo_path(param.path);
+        let (details, unresolved) = if param.interpolate.unwrap_or(false) {
+            interpolate_details(&param.details, &repo_path)
+        } else {
+            (param.details.clone(), Vec::new())
+        };
+
+        let config = load_config(&repo_path)?;
+        let violations = check_commit_policy(&config.policy, &param.commit_type, &short_desc, &details, None);
+        if !violations.is_empty() {
+            return Err(format!(
+                "❌ 提交信息不符合规范（.gitmcp.toml）：\n\n{}",
+                violations.iter().map(|v| format!("- {}", v)).collect::<Vec<_>>().join("\n")
+            ));
+        }
+
+        let footers = ticket_footer(&config.footer, &repo_path)?.into_iter().collect();
+        let opts = MessageOptions {
+            emoji: emoji_enabled(param.emoji),
+            footers,
+            details_header: details_header(param.language.as_deref())?,
+        };
+        let commit_msg = build_commit_message(&param.commit_type, &short_desc, &details, None, &opts);
+        let mut result = format!("📝 生成的提交信息：\n\n```\n{}\n```", commit_msg);
+        if !unresolved.is_empty() {
+            result.push_str(&format!(
+                "\n\n⚠️ 以下占位符无法解析，已原样保留: {}",
+                unresolved.join(", ")
+            ));
+        }
+
+        // 未提供详细描述时，提示团队配置的提交模板
+        if param.details.is_empty() {
+            if let Some((template_path, template)) = commit_template(&repo_path) {
+                result.push_str(&format!(
+                    "\n\n📋 仓库配置了提交模板（{}），可参考补充详细描述：\n\n```\n{}\n```",
+                    template_path, template
+                ));
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// 执行 Git 提交
+    #[tool(description = "执行 git add 和 git commit，使用指定的提交信息")]
+    async fn git_commit(&self, Parameters(param): Parameters<GitCommitParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let message = require_text("message", &param.message)?;
+        let message = match normalize_body(param.body.as_deref()) {
+            Some(body) => format!("{}\n\n{}", message, body),
+            None => message,
+        };
+        let verbosity = verbosity(param.verbosity.as_deref())?;
+        let message = match attribution_trailer()? {
+            Some(trailer) => append_trailer(&message, &trailer),
+            None => message,
+        };
+
+        if param.dry_run.unwrap_or(false) {
+            let repo = match Repository::open(&repo_path) {
+                Ok(r) => r,
+                Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+            };
+
+            let mut opts = StatusOptions::new();
+            opts.include_untracked(true).recurse_untracked_dirs(true);
+
+            let statuses = match repo.statuses(Some(&mut opts)) {
+                Ok(s) => s,
+                Err(e) => return Err(format!("❌ 获取状态失败: {}", e)),
+            };
+
+            let files: Vec<String> = statuses
+                .iter()
+                .filter_map(|entry| {
+                    let (icon, status_str) = change_label(entry.status())?;
+                    Some(format!("{} {} {}", icon, status_str, entry.path().unwrap_or("unknown")))
+                })
+                .collect();
+
+            let files_str = if files.is_empty() {
+                "（没有可暂存的变更）".to_string()
+            } else {
+                files.join("\n")
+            };
+
+            let mut preview = format!(
+                "🔍 预览模式，未执行提交\n\n📂 将要暂存的文件（{} 个）：\n{}\n\n📝 提交信息：\n\n```\n{}\n```",
+                files.len(),
+                files_str,
+                message
+            );
+
+            let threshold = max_file_size();
+            if let Ok(large) = find_large_files(&repo, threshold) {
+                if !large.is_empty() {
+                    preview.push_str(&format!(
+                        "\n\n⚠️ 以下文件超过大小上限 {}，实际提交时将被拒绝：\n{}",
+                        format_size(threshold),
+                        format_large_files(&large)
+                    ));
+                }
+            }
+
+            if let Some(id) = detached_head(&repo_path) {
+                preview.push_str(&format!(
+                    "\n\n⚠️ 当前处于游离 HEAD @ {}，提交将不属于任何分支，建议先创建分支",
+                    id
+                ));
+            }
+
+            if param.no_verify.unwrap_or(false) {
+                preview.push_str("\n\n⚠️ 将使用 --no-verify 提交，pre-commit 和 commit-msg 钩子不会执行");
+            }
+
+            if let Some(branch) = protected_branch(&load_config(&repo_path)?.policy, &repo_path) {
+                preview.push_str(&format!(
+                    "\n\n⚠️ 分支 {} 受保护，实际提交时将被拒绝，可传入 auto_branch=true 自动创建功能分支",
+                    branch
+                ));
+            }
+
+            return Ok(preview);
+        }
+
+        let _guard = self.lock_repo(&repo_path).await;
+
+        // 大文件检查，避免误提交构建产物或二进制文件
+        if let Ok(repo) = Repository::open(&repo_path) {
+            let threshold = max_file_size();
+            if let Ok(large) = find_large_files(&repo, threshold) {
+                if !large.is_empty() {
+                    return Err(format!(
+                        "❌ 以下文件超过大小上限 {}，已取消提交：\n\n{}\n\n💡 请将其加入 .gitignore，或调整 GIT_MCP_MAX_FILE_SIZE 后重试",
+                        format_size(threshold),
+                        format_large_files(&large)
+                    ));
+                }
+            }
+        }
+
+        let config = load_config(&repo_path)?;
+        let branch_notice = guard_protected_branch(&config.policy, &repo_path, param.auto_branch.unwrap_or(false))?
+            .map(|notice| format!("{}\n\n", notice))
+            .unwrap_or_default();
+
+        // git add .
+        let add_output = run_git(&repo_path, ["add", "."]);
+
+        match add_output {
+            Ok(output) if !output.status.success() => {
+                return Err(format!("❌ git add 失败: {}", String::from_utf8_lossy(&output.stderr)));
+            }
+            Err(e) => return Err(format!("❌ 执行 git add 失败: {}", e)),
+            _ => {}
+        }
+
+        // git commit
+        let allow_empty = param.allow_empty.unwrap_or(false);
+        let auto_push = param.auto_push.unwrap_or(false);
+        let no_verify = param.no_verify.unwrap_or(false);
+        let mut commit_args = vec!["commit", "-m", &message];
+        if allow_empty {
+            commit_args.push("--allow-empty");
+        }
+        if no_verify {
+            commit_args.push("--no-verify");
+        }
+
+        // 提交前记录暂存区是否为空，用于提示空提交
+        let nothing_staged = allow_empty && !has_staged_changes(&repo_path);
+
+        let commit_output = run_git(&repo_path, &commit_args);
+
+        match commit_output {
+            Ok(output) if output.status.success() => {
+                let head = Repository::open(&repo_path).ok().and_then(|repo| {
+                    let commit = repo.head().ok()?.peel_to_commit().ok()?;
+                    Some((short_id(commit.id()), describe_commit(&repo, &commit)))
+                });
+                if verbosity == Verbosity::Quiet {
+                    let hash = head.map(|(hash, _)| hash).unwrap_or_default();
+                    let hash = if no_verify { format!("{}（--no-verify）", hash) } else { hash };
+                    return Ok(if auto_push {
+                        format!("✅ {}\n{}", hash, after_commit(&repo_path, true))
+                    } else {
+                        format!("✅ {}", hash)
+                    });
+                }
+                // 跳过钩子需要在结果中留痕，便于事后审计
+                let branch_notice = if no_verify {
+                    format!("{}⚠️ 已使用 --no-verify 跳过 pre-commit 和 commit-msg 钩子\n\n", branch_notice)
+                } else {
+                    branch_notice
+                };
+
+                let mut result = if nothing_staged {
+                    format!(
+                        "{}✅ 提交成功！（空提交，没有包含任何文件变更）\n\n{}",
+                        branch_notice,
+                        after_commit(&repo_path, auto_push)
+                    )
+                } else {
+                    format!("{}✅ 提交成功！\n\n{}", branch_notice, after_commit(&repo_path, auto_push))
+                };
+                if let (Verbosity::Verbose, Some((_, details))) = (verbosity, head) {
+                    result.push_str(&format!("\n\n{}", details));
+                }
+                Ok(result)
+            }
+            Ok(output) => {
+                Err(format!("❌ git commit 失败: {}", String::from_utf8_lossy(&output.stderr)))
+            }
+            Err(e) => Err(format!("❌ 执行 git commit 失败: {}", e)),
+        }
+    }
+
+    /// 获取支持的提交类型列表
+    #[tool(description = "获取所有支持的提交类型及其说明，format=json 时返回结构化数组")]
+    async fn list_commit_types(&self, Parameters(param): Parameters<ListCommitTypesParam>) -> Result<String, String> {
+        match param.format.map(|f| f.trim().to_lowercase()).as_deref() {
+            None | Some("") | Some("markdown") => {}
+            Some("json") => {
+                let types: Vec<serde_json::Value> = COMMIT_TYPES
+                    .iter()
+                    .map(|t| serde_json::json!({ "name": t.name, "emoji": t.emoji, "desc": t.desc }))
+                    .collect();
+                return Ok(serde_json::to_string_pretty(&types).unwrap_or_default());
+            }
+            Some(other) => return Err(format!("❌ 不支持的输出格式 {}，可选值: markdown / json", other)),
+        }
+
+        let mut result = String::from("📋 支持的提交类型：\n\n");
+        result.push_str("| Type | Emoji | 说明 |\n");
+        result.push_str("|------|-------|------|\n");
+        
+        for t in COMMIT_TYPES {
+            result.push_str(&format!("| {} | {} | {} |\n", t.name, t.emoji, t.desc));
+        }
+        
+        Ok(result)
+    }
+
+    /// 查看 Git 提交历史
+    #[tool(description = "查看最近的 Git 提交历史")]
+    async fn git_log(&self, Parameters(param): Parameters<GitLogParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let n = param.count.unwrap_or(10).to_string();
+
+        let format = if param.show_body.unwrap_or(false) {
+            // 正文整体缩进 4 格，每条提交之间空一行
+            "--pretty=format:%h %s%n%w(0,4,4)%b"
+        } else {
+            "--oneline"
+        };
+        let first_parent = param.first_parent.unwrap_or(false);
+        let mut args = vec!["log".to_string(), format.to_string(), "-n".to_string(), n.clone()];
+        if first_parent {
+            args.push("--first-parent".to_string());
+        }
+        let paths = param.paths.unwrap_or_default();
+        if !paths.is_empty() {
+            args.push("--".to_string());
+            args.extend(paths.iter().cloned());
+        }
+
+        let output = run_git(&repo_path, &args);
+
+        match output {
+            Ok(o) if o.status.success() => {
+                let mut scope = if paths.is_empty() {
+                    String::new()
+                } else {
+                    format!("（涉及 {}）", paths.join(", "))
+                };
+                if first_parent {
+                    scope.push_str("（仅主线）");
+                }
+                Ok(format!("📜 最近 {} 条提交{}：\n\n{}", n, scope, String::from_utf8_lossy(&o.stdout)))
+            }
+            Ok(o) => Err(format!("❌ 获取日志失败: {}", String::from_utf8_lossy(&o.stderr))),
+            Err(e) => Err(format!("❌ 执行失败: {}", e)),
+        }
+    }
+
+    /// 查看当前分支
+    #[tool(description = "查看当前所在的 Git 分支")]
+    async fn git_branch(&self, Parameters(param): Parameters<PathParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+
+        if let Some(id) = detached_head(&repo_path) {
+            return Ok(format!("🔗 游离 HEAD @ {}（不在任何分支上）", id));
+        }
+
+        let output = run_git(&repo_path, ["branch", "--show-current"]);
+
+        match output {
+            Ok(o) if o.status.success() => {
+                Ok(format!("🌿 当前分支: {}", String::from_utf8_lossy(&o.stdout).trim()))
+            }
+            Ok(o) => Err(format!("❌ 获取分支失败: {}", String::from_utf8_lossy(&o.stderr))),
+            Err(e) => Err(format!("❌ 执行失败: {}", e)),
+        }
+    }
+
+    /// 智能分类提交
+    #[tool(description = "智能分类提交：根据变更类型分组，依次执行多次提交。每组指定文件列表和提交信息，实现 fix/feat/style 等分类提交")]
+    async fn smart_commit(
+        &self,
+        Parameters(param): Parameters<SmartCommitParam>,
+        meta: Meta,
+        peer: Peer<RoleServer>,
+    ) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let mut results = Vec::new();
+        let mut success_count = 0;
+
+        // 执行任何提交前先整体校验，避免部分提交后才发现参数错误
+        let max_groups = max_commit_groups();
+        if param.commits.len() > max_groups {
+            return Err(format!(
+                "❌ 提交组数量 {} 超过上限 {}，未执行任何提交\n💡 请拆分为多次调用，或调整 GIT_MCP_MAX_COMMIT_GROUPS",
+                param.commits.len(),
+                max_groups
+            ));
+        }
+        for (idx, group) in param.commits.iter().enumerate() {
+            require_text(&format!("commits[{}].short_desc", idx), &group.short_desc)?;
+            if group.files.iter().all(|f| f.trim().is_empty()) {
+                return Err(format!("❌ 参数 commits[{}].files 不能为空，未执行任何提交", idx));
+            }
+        }
+
+        let config = load_config(&repo_path)?;
+        let violations: Vec<String> = param
+            .commits
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, group)| {
+                check_commit_policy(
+                    &config.policy,
+                    &group.commit_type,
+                    group.short_desc.trim(),
+                    &group.details,
+                    group.body.as_deref(),
+                )
+                    .into_iter()
+                    .map(move |v| format!("- 第{}组 [{}]: {}", idx + 1, group.commit_type, v))
+            })
+            .collect();
+        if !violations.is_empty() {
+            return Err(format!(
+                "❌ 提交信息不符合规范（.gitmcp.toml），未执行任何提交：\n\n{}",
+                violations.join("\n")
+            ));
+        }
+
+        let footers = ticket_footer(&config.footer, &repo_path)?
+            .into_iter()
+            .chain(attribution_trailer()?)
+            .collect();
+        let opts = MessageOptions {
+            emoji: emoji_enabled(param.emoji),
+            footers,
+            details_header: details_header(param.language.as_deref())?,
+        };
+        let verbosity = verbosity(param.verbosity.as_deref())?;
+        let no_verify = param.no_verify.unwrap_or(false);
+        let progress_token = meta.get_progress_token();
+        let total = param.commits.len();
+        let _guard = self.lock_repo(&repo_path).await;
+        let branch_notice =
+            guard_protected_branch(&config.policy, &repo_path, param.auto_branch.unwrap_or(false))?
+                .map(|notice| format!("{}\n\n", notice))
+                .unwrap_or_default();
+
+        for (idx, group) in param.commits.iter().enumerate() {
+            let line = match commit_group(&repo_path, idx, group, &opts, no_verify) {
+                Ok(line) => {
+                    success_count += 1;
+                    line
+                }
+                Err(line) => line,
+            };
+            report_progress(&peer, progress_token.as_ref(), idx + 1, total, &line).await;
+            results.push(line);
+        }
+
+        // 只有全部组都成功时才自动推送，避免推送不完整的提交序列
+        let auto_push = param.auto_push.unwrap_or(false) && success_count == total;
+        if verbosity == Verbosity::Quiet {
+            let failures: Vec<&String> = results.iter().filter(|line| line.starts_with("❌")).collect();
+            let mut summary = format!("{} {}/{}", if failures.is_empty() { "✅" } else { "⚠️" }, success_count, total);
+            if no_verify {
+                summary.push_str("（--no-verify）");
+            }
+            for line in failures {
+                summary.push_str(&format!("\n{}", line));
+            }
+            return match success_count {
+                0 => Err(summary),
+                _ if auto_push => Ok(format!("{}\n{}", summary, after_commit(&repo_path, true))),
+                _ => Ok(summary),
+            };
+        }
+
+        let hooks_notice = if no_verify {
+            "⚠️ 已使用 --no-verify 跳过 pre-commit 和 commit-msg 钩子\n\n"
+        } else {
+            ""
+        };
+        let summary = format!(
+            "{}{}📊 分类提交完成：{}/{} 组成功\n\n{}",
+            branch_notice,
+            hooks_notice,
+            success_count,
+            param.commits.len(),
+            results.join("\n")
+        );
+
+        if success_count > 0 {
+            let mut result = format!("{}\n\n{}", summary, after_commit(&repo_path, auto_push));
+            if verbosity == Verbosity::Verbose {
+                // 新提交是 HEAD 往下连续的 success_count 个，按提交顺序展示
+                if let Ok(repo) = Repository::open(&repo_path) {
+                    let mut commits = Vec::new();
+                    let mut current = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+                    while let Some(commit) = current.filter(|_| commits.len() < success_count) {
+                        current = commit.parent(0).ok();
+                        commits.push(describe_commit(&repo, &commit));
+                    }
+                    commits.reverse();
+                    result.push_str(&format!("\n\n{}", commits.join("\n\n")));
+                }
+            }
+            Ok(result)
+        } else {
+            Err(summary)
+        }
+    }
+
+    /// 导出归档
+    #[tool(description = "将指定修订的文件树导出为 tar/tar.gz/zip 归档文件，用于构建发布产物")]
+    async fn git_archive(&self, Parameters(param): Parameters<ArchiveParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let revision = param.revision.unwrap_or_else(|| "HEAD".to_string());
+
+        let format = match param.format.map(|f| f.trim().to_lowercase()) {
+            Some(f) => f,
+            None if param.output.ends_with(".zip") => "zip".to_string(),
+            None if param.output.ends_with(".tar.gz") || param.output.ends_with(".tgz") => {
+                "tar.gz".to_string()
+            }
+            None => "tar".to_string(),
+        };
+        if !["tar", "tar.gz", "tgz", "zip"].contains(&format.as_str()) {
+            return Err(format!("❌ 不支持的归档格式: {}（可选 tar/tar.gz/zip）", format));
+        }
+
+        // git archive -o 不会创建目录，先确认输出目录存在
+        let output_file = std::path::Path::new(&repo_path).join(&param.output);
+        if let Some(parent) = output_file.parent().filter(|p| !p.as_os_str().is_empty()) {
+            if !parent.is_dir() {
+                return Err(format!("❌ 输出目录不存在: {}", parent.display()));
+            }
+        }
+
+        let format_arg = format!("--format={}", format);
+        let output = run_git(&repo_path, ["archive", &format_arg, "-o", &param.output, &revision]);
+
+        match output {
+            Ok(o) if o.status.success() => {
+                let size = std::fs::metadata(&output_file).map(|m| m.len()).unwrap_or(0);
+                Ok(format!(
+                    "📦 归档已导出: {}\n\n🔖 修订: {}\n🗂️ 格式: {}\n📏 大小: {} 字节",
+                    param.output, revision, format, size
+                ))
+            }
+            Ok(o) => Err(format!("❌ 导出归档失败: {}", String::from_utf8_lossy(&o.stderr))),
+            Err(e) => Err(format!("❌ 执行 git archive 失败: {}", e)),
+        }
+    }
+
+    /// 检查提交签名
+    #[tool(description = "检查提交是否带有 GPG/SSH 签名，并报告签名者及验证结果")]
+    async fn git_verify_commit(&self, Parameters(param): Parameters<VerifyCommitParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+
+        let commit = match resolve_commit(&repo, &param.revision) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 无法解析提交 {}: {}", param.revision, e)),
+        };
+        let oid = commit.id();
+        let subject = commit.summary().unwrap_or("");
+
+        let signature = match repo.extract_signature(&oid, None) {
+            Ok((sig, _)) => String::from_utf8_lossy(&sig).to_string(),
+            Err(e) if e.code() == git2::ErrorCode::NotFound => {
+                return Ok(format!("🔓 未签名: {} {}", short_id(oid), subject));
+            }
+            Err(e) => return Err(format!("❌ 读取签名失败: {}", e)),
+        };
+
+        let kind = if signature.contains("BEGIN SSH SIGNATURE") {
+            "SSH"
+        } else if signature.contains("BEGIN PGP SIGNATURE") {
+            "GPG"
+        } else {
+            "未知类型"
+        };
+
+        // git2 只能提取签名，验证交给 git verify-commit（依赖本地 gpg/ssh 配置）；
+        // --raw 输出 GnuPG 的机器可读状态行，签名者的识别不受本地语言设置影响
+        let output = run_git(&repo_path, ["verify-commit", "--raw", &oid.to_string()]);
+
+        match output {
+            Ok(o) => {
+                let stderr = String::from_utf8_lossy(&o.stderr);
+                let signer = verify_signer(&stderr);
+
+                if o.status.success() {
+                    Ok(format!(
+                        "🔏 已签名 ({}): {} {}\n✅ 签名者: {}",
+                        kind,
+                        short_id(oid),
+                        subject,
+                        signer.unwrap_or_else(|| "未知".to_string())
+                    ))
+                } else {
+                    Err(format!(
+                        "🔏 已签名 ({}): {} {}\n❌ 签名验证失败: {}",
+                        kind,
+                        short_id(oid),
+                        subject,
+                        stderr.trim()
+                    ))
+                }
+            }
+            Err(e) => Err(format!(
+                "🔏 已签名 ({}): {} {}\n❌ 执行 git verify-commit 失败: {}",
+                kind,
+                short_id(oid),
+                subject,
+                e
+            )),
+        }
+    }
+
+    /// 预览拉取内容
+    #[tool(description = "拉取前预览：仅 fetch 不合并，列出将要合并进来的提交及其涉及的文件")]
+    async fn git_pull_preview(&self, Parameters(param): Parameters<PullPreviewParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let remote_name = param.remote.unwrap_or_else(|| "origin".to_string());
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+
+        let head = match repo.head() {
+            Ok(h) => h,
+            Err(e) => return Err(format!("❌ 无法读取 HEAD: {}", e)),
+        };
+        let branch = match param.branch.or_else(|| head.shorthand().map(|s| s.to_string())) {
+            Some(b) if b != "HEAD" => b,
+            _ => return Err("❌ 当前处于游离 HEAD，请指定 branch".to_string()),
+        };
+        let head_oid = match head.target() {
+            Some(oid) => oid,
+            None => return Err("❌ 无法解析 HEAD 指向的提交".to_string()),
+        };
+
+        // 只更新远程跟踪分支，不触碰本地分支和工作区
+        let mut remote = match repo.find_remote(&remote_name) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 找不到远程仓库 {}: {}", remote_name, e)),
+        };
+        let refspec = format!("+refs/heads/{0}:refs/remotes/{1}/{0}", branch, remote_name);
+        let attempted = Default::default();
+        let mut fetch_opts = git2::FetchOptions::new();
+        fetch_opts.remote_callbacks(credential_callbacks(&attempted));
+        if let Err(e) = remote.fetch(&[&refspec], Some(&mut fetch_opts), None) {
+            return Err(format!("❌ fetch 失败: {}", remote_error(&e, &attempted)));
+        }
+
+        let tracking = format!("refs/remotes/{}/{}", remote_name, branch);
+        let upstream_oid = match repo.refname_to_id(&tracking) {
+            Ok(oid) => oid,
+            Err(e) => return Err(format!("❌ 找不到远程分支 {}/{}: {}", remote_name, branch, e)),
+        };
+
+        let (ahead, behind) = match repo.graph_ahead_behind(head_oid, upstream_oid) {
+            Ok(v) => v,
+            Err(e) => return Err(format!("❌ 计算分叉失败: {}", e)),
+        };
+
+        if behind == 0 {
+            return Ok(format!(
+                "✅ 已是最新，{}/{} 没有新的提交（本地领先 {} 个提交）",
+                remote_name, branch, ahead
+            ));
+        }
+
+        let mut revwalk = match repo.revwalk() {
+            Ok(w) => w,
+            Err(e) => return Err(format!("❌ 遍历提交失败: {}", e)),
+        };
+        if let Err(e) = revwalk.push(upstream_oid).and_then(|_| revwalk.hide(head_oid)) {
+            return Err(format!("❌ 遍历提交失败: {}", e));
+        }
+
+        let mut commits = Vec::new();
+        for oid in revwalk.flatten() {
+            if let Ok(commit) = repo.find_commit(oid) {
+                commits.push(format!(
+                    "- {} {} ({})",
+                    short_id(oid),
+                    commit.summary().unwrap_or(""),
+                    commit.author().name().unwrap_or("unknown")
+                ));
+            }
+        }
+
+        // 涉及的文件：从分叉点到远程分支的整体差异
+        let mut files = Vec::new();
+        let diff = repo
+            .merge_base(head_oid, upstream_oid)
+            .and_then(|base| repo.find_commit(base)?.tree())
+            .and_then(|base_tree| {
+                let upstream_tree = repo.find_commit(upstream_oid)?.tree()?;
+                repo.diff_tree_to_tree(Some(&base_tree), Some(&upstream_tree), None)
+            });
+        match diff {
+            Ok(diff) => {
+                for delta in diff.deltas() {
+                    let file = delta
+                        .new_file()
+                        .path()
+                        .or_else(|| delta.old_file().path())
+                        .map(|p| p.display().to_string())
+                        .unwrap_or_else(|| "unknown".to_string());
+                    files.push(format!("- {} {}", delta_label(delta.status()), file));
+                }
+            }
+            Err(e) => files.push(format!("（无法计算文件差异: {}）", e)),
+        }
+
+        let mut result = format!(
+            "📥 {}/{} 有 {} 个新提交待合并（本地领先 {} 个）\n\n📜 提交列表：\n{}\n\n📂 涉及文件（{} 个）：\n{}",
+            remote_name,
+            branch,
+            behind,
+            ahead,
+            commits.join("\n"),
+            files.len(),
+            files.join("\n")
+        );
+        if ahead > 0 {
+            result.push_str("\n\n⚠️ 本地与远程已分叉，拉取时将产生合并提交，可考虑使用 rebase");
+        }
+        Ok(result)
+    }
+
+    /// 暂存全部变更并汇总
+    #[tool(description = "暂存所有变更（含未跟踪文件），并按新增/修改/删除分组返回暂存区文件列表，便于提交前确认")]
+    async fn git_stage_all(&self, Parameters(param): Parameters<PathParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let _guard = self.lock_repo(&repo_path).await;
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+
+        let mut index = match repo.index() {
+            Ok(i) => i,
+            Err(e) => return Err(format!("❌ 读取暂存区失败: {}", e)),
+        };
+
+        // add_all 处理新增和修改，update_all 处理已删除的跟踪文件
+        let staged = index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .and_then(|_| index.update_all(["*"], None))
+            .and_then(|_| index.write());
+        if let Err(e) = staged {
+            return Err(format!("❌ 暂存失败: {}", e));
+        }
+
+        let head_tree = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+        let diff = match repo.diff_tree_to_index(head_tree.as_ref(), Some(&index), None) {
+            Ok(d) => d,
+            Err(e) => return Err(format!("❌ 计算暂存区差异失败: {}", e)),
+        };
+
+        let mut added = Vec::new();
+        let mut modified = Vec::new();
+        let mut deleted = Vec::new();
+        for delta in diff.deltas() {
+            let file = delta
+                .new_file()
+                .path()
+                .or_else(|| delta.old_file().path())
+                .map(|p| p.display().to_string())
+                .unwrap_or_else(|| "unknown".to_string());
+            match delta.status() {
+                git2::Delta::Added => added.push(file),
+                git2::Delta::Deleted => deleted.push(file),
+                _ => modified.push(file),
+            }
+        }
+
+        let total = added.len() + modified.len() + deleted.len();
+        if total == 0 {
+            return Ok("✅ 工作区干净，没有需要暂存的变更".to_string());
+        }
+
+        let mut result = format!("📥 已暂存 {} 个文件：\n", total);
+        for (icon, label, files) in [("➕", "新增", &added), ("📝", "修改", &modified), ("➖", "删除", &deleted)] {
+            if files.is_empty() {
+                continue;
+            }
+            result.push_str(&format!("\n{} {}（{} 个）：\n", icon, label, files.len()));
+            for f in files {
+                result.push_str(&format!("- {}\n", f));
+            }
+        }
+        result.push_str("\n💡 确认无误后可执行提交，如需排除某些文件请先取消暂存");
+        Ok(result)
+    }
+
+    /// 查看未推送的提交
+    #[tool(description = "列出当前分支尚未推送到上游分支的提交（@{u}..HEAD）")]
+    async fn git_unpushed(&self, Parameters(param): Parameters<PathParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+
+        let head = match repo.head() {
+            Ok(h) => h,
+            Err(e) => return Err(format!("❌ 无法读取 HEAD: {}", e)),
+        };
+        if !head.is_branch() {
+            return Err("❌ 当前处于游离 HEAD，没有对应的上游分支".to_string());
+        }
+        let branch_name = head.shorthand().unwrap_or("HEAD").to_string();
+        let head_oid = match head.target() {
+            Some(oid) => oid,
+            None => return Err("❌ 无法解析 HEAD 指向的提交".to_string()),
+        };
+
+        let upstream = match repo
+            .find_branch(&branch_name, git2::BranchType::Local)
+            .and_then(|b| b.upstream())
+        {
+            Ok(u) => u,
+            Err(_) => {
+                return Ok(format!(
+                    "⚠️ 分支 {} 没有配置上游分支，所有提交都尚未推送\n\n💡 首次推送请执行: git push -u origin {}",
+                    branch_name, branch_name
+                ));
+            }
+        };
+        let upstream_name = upstream.name().ok().flatten().unwrap_or("upstream").to_string();
+        let upstream_oid = match upstream.get().target() {
+            Some(oid) => oid,
+            None => return Err(format!("❌ 无法解析上游分支 {}", upstream_name)),
+        };
+
+        let (ahead, behind) = match repo.graph_ahead_behind(head_oid, upstream_oid) {
+            Ok(v) => v,
+            Err(e) => return Err(format!("❌ 计算分叉失败: {}", e)),
+        };
+
+        if ahead == 0 {
+            return Ok(format!("✅ 没有未推送的提交，{} 与 {} 同步", branch_name, upstream_name));
+        }
+
+        let mut revwalk = match repo.revwalk() {
+            Ok(w) => w,
+            Err(e) => return Err(format!("❌ 遍历提交失败: {}", e)),
+        };
+        if let Err(e) = revwalk.push(head_oid).and_then(|_| revwalk.hide(upstream_oid)) {
+            return Err(format!("❌ 遍历提交失败: {}", e));
+        }
+
+        let commits: Vec<String> = revwalk
+            .flatten()
+            .filter_map(|oid| repo.find_commit(oid).ok())
+            .map(|c| format!("- {} {}", short_id(c.id()), c.summary().unwrap_or("")))
+            .collect();
+
+        let mut result = format!(
+            "📤 {} 有 {} 个提交尚未推送到 {}：\n\n{}",
+            branch_name,
+            ahead,
+            upstream_name,
+            commits.join("\n")
+        );
+        if behind > 0 {
+            result.push_str(&format!(
+                "\n\n⚠️ 上游分支有 {} 个本地没有的提交，推送前需要先拉取",
+                behind
+            ));
+        }
+        Ok(result)
+    }
+
+    /// 查看或设置上游分支
+    #[tool(description = "查看或设置当前分支的上游跟踪分支；不传 remote 和 branch 时仅显示当前上游")]
+    async fn git_set_upstream(&self, Parameters(param): Parameters<SetUpstreamParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+
+        let head = match repo.head() {
+            Ok(h) => h,
+            Err(e) => return Err(format!("❌ 无法读取 HEAD: {}", e)),
+        };
+        if !head.is_branch() {
+            return Err("❌ 当前处于游离 HEAD，无法设置上游分支".to_string());
+        }
+        let branch_name = head.shorthand().unwrap_or("HEAD").to_string();
+
+        let mut branch = match repo.find_branch(&branch_name, git2::BranchType::Local) {
+            Ok(b) => b,
+            Err(e) => return Err(format!("❌ 找不到分支 {}: {}", branch_name, e)),
+        };
+
+        // 显示模式
+        if param.remote.is_none() && param.branch.is_none() {
+            return Ok(match branch.upstream() {
+                Ok(u) => format!(
+                    "🔗 {} 的上游分支: {}",
+                    branch_name,
+                    u.name().ok().flatten().unwrap_or("unknown")
+                ),
+                Err(_) => format!("⚠️ 分支 {} 没有配置上游分支", branch_name),
+            });
+        }
+
+        let remote_name = param.remote.unwrap_or_else(|| "origin".to_string());
+        let remote_branch = param.branch.unwrap_or_else(|| branch_name.clone());
+
+        if let Err(e) = repo.find_remote(&remote_name) {
+            return Err(format!("❌ 找不到远程仓库 {}: {}", remote_name, e));
+        }
+
+        let upstream_name = format!("{}/{}", remote_name, remote_branch);
+        if repo.find_branch(&upstream_name, git2::BranchType::Remote).is_err() {
+            return Err(format!(
+                "❌ 远程分支 {} 不存在\n\n💡 请先 fetch，或首次推送时执行: git push -u {} {}",
+                upstream_name, remote_name, branch_name
+            ));
+        }
+
+        match branch.set_upstream(Some(&upstream_name)) {
+            Ok(_) => Ok(format!("✅ 已将 {} 的上游分支设置为 {}", branch_name, upstream_name)),
+            Err(e) => Err(format!("❌ 设置上游分支失败: {}", e)),
+        }
+    }
+
+    /// 查看文件历史版本内容
+    #[tool(description = "查看文件在指定修订（默认 HEAD）时的内容，无需检出")]
+    async fn git_show_file(&self, Parameters(param): Parameters<ShowFileParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let revision = param.revision.unwrap_or_else(|| "HEAD".to_string());
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+
+        let commit = match resolve_commit(&repo, &revision) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 无法解析提交 {}: {}", revision, e)),
+        };
+
+        let blob = match commit
+            .tree()
+            .and_then(|tree| tree.get_path(std::path::Path::new(&param.file)))
+            .and_then(|entry| entry.to_object(&repo))
+            .and_then(|obj| obj.peel_to_blob())
+        {
+            Ok(b) => b,
+            Err(e) => return Err(format!("❌ 修订 {} 中找不到文件 {}: {}", revision, param.file, e)),
+        };
+
+        if blob.is_binary() {
+            return Ok(format!(
+                "📄 {} @ {}\n\n⚠️ 二进制文件，大小 {} 字节，不显示内容",
+                param.file,
+                short_id(commit.id()),
+                blob.size()
+            ));
+        }
+
+        Ok(format!(
+            "📄 {} @ {}\n\n```\n{}\n```",
+            param.file,
+            short_id(commit.id()),
+            String::from_utf8_lossy(blob.content()).trim_end_matches('\n')
+        ))
+    }
+
+    /// 检查大文件
+    #[tool(description = "扫描即将暂存的新增/修改文件，列出超过大小上限（GIT_MCP_MAX_FILE_SIZE，默认 10MB）的文件")]
+    async fn git_check_large_files(&self, Parameters(param): Parameters<PathParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+
+        let threshold = max_file_size();
+        let large = match find_large_files(&repo, threshold) {
+            Ok(l) => l,
+            Err(e) => return Err(format!("❌ 获取状态失败: {}", e)),
+        };
+
+        if large.is_empty() {
+            return Ok(format!("✅ 没有超过 {} 的待提交文件", format_size(threshold)));
+        }
+
+        let mut result = format!(
+            "⚠️ 发现 {} 个超过 {} 的待提交文件：\n\n",
+            large.len(),
+            format_size(threshold)
+        );
+        result.push_str(&format_large_files(&large));
+        result.push_str("\n\n💡 建议加入 .gitignore 或改用 Git LFS 管理");
+        Ok(result)
+    }
+
+    /// 对比两个分支
+    #[tool(description = "对比两个分支：列出 head 中有而 base 中没有的提交（base..head），以及 head 相对分叉点的文件变更统计")]
+    async fn git_compare_branches(&self, Parameters(param): Parameters<CompareBranchesParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+
+        let base = match resolve_commit(&repo, &param.base) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 无法解析 {}: {}", param.base, e)),
+        };
+        let head = match resolve_commit(&repo, &param.head) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 无法解析 {}: {}", param.head, e)),
+        };
+
+        let mut revwalk = match repo.revwalk() {
+            Ok(w) => w,
+            Err(e) => return Err(format!("❌ 遍历提交失败: {}", e)),
+        };
+        if let Err(e) = revwalk.push(head.id()).and_then(|_| revwalk.hide(base.id())) {
+            return Err(format!("❌ 遍历提交失败: {}", e));
+        }
+        let commits: Vec<String> = revwalk
+            .flatten()
+            .filter_map(|oid| repo.find_commit(oid).ok())
+            .map(|c| format!("- {} {}", short_id(c.id()), c.summary().unwrap_or("")))
+            .collect();
+
+        // 以分叉点为基准，只统计 head 分支自身引入的变更
+        let diff = repo
+            .merge_base(base.id(), head.id())
+            .and_then(|oid| repo.find_commit(oid)?.tree())
+            .and_then(|base_tree| {
+                let head_tree = head.tree()?;
+                repo.diff_tree_to_tree(Some(&base_tree), Some(&head_tree), None)
+            });
+        let diff = match diff {
+            Ok(d) => d,
+            Err(e) => return Err(format!("❌ 计算差异失败: {}", e)),
+        };
+        let stats = match diff.stats() {
+            Ok(s) => s,
+            Err(e) => return Err(format!("❌ 计算差异统计失败: {}", e)),
+        };
+
+        if commits.is_empty() && stats.files_changed() == 0 {
+            return Ok(format!("✅ {} 没有 {} 之外的提交", param.head, param.base));
+        }
+
+        Ok(format!(
+            "🔀 {} 相对 {} 的变更\n\n📜 提交（{} 个）：\n{}\n\n📂 文件变更：{} 个文件，+{} -{}\n{}",
+            param.head,
+            param.base,
+            commits.len(),
+            commits.join("\n"),
+            stats.files_changed(),
+            stats.insertions(),
+            stats.deletions(),
+            diff_stat_lines(&diff).join("\n")
+        ))
+    }
+
+    /// 仓库维护
+    #[tool(description = "执行仓库维护（git gc 垃圾回收与重新打包），并报告 .git 目录维护前后的大小")]
+    async fn git_maintenance(&self, Parameters(param): Parameters<MaintenanceParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+        let git_dir = repo.path().to_path_buf();
+        let before = dir_size(&git_dir);
+
+        // git2 没有提供 gc，交给 git 命令行完成
+        let mut args = vec!["gc", "--quiet"];
+        if param.aggressive.unwrap_or(false) {
+            args.push("--aggressive");
+        }
+
+        let output = run_git(&repo_path, &args);
+
+        match output {
+            Ok(o) if o.status.success() => {
+                let after = dir_size(&git_dir);
+                Ok(format!(
+                    "🧹 仓库维护完成{}\n\n📦 .git 大小: {} → {}（节省 {}）",
+                    if param.aggressive.unwrap_or(false) { "（aggressive）" } else { "" },
+                    format_size(before),
+                    format_size(after),
+                    format_size(before.saturating_sub(after))
+                ))
+            }
+            Ok(o) => Err(format!("❌ git gc 失败: {}", String::from_utf8_lossy(&o.stderr))),
+            Err(e) => Err(format!("❌ 执行 git gc 失败: {}", e)),
+        }
+    }
+
+    /// 预览提交组的差异
+    #[tool(description = "预览指定文件相对 HEAD 的完整差异（已暂存 + 未暂存 + 未跟踪），用于在 smart_commit 前确认每组内容")]
+    async fn preview_group_diff(&self, Parameters(param): Parameters<GroupDiffParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+
+        if param.files.is_empty() {
+            return Err("❌ 参数 files 不能为空".to_string());
+        }
+        let json = wants_json(param.format.as_deref())?;
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+
+        let diff = match group_diff(&repo, &param.files) {
+            Ok(d) => d,
+            Err(e) => return Err(format!("❌ 计算差异失败: {}", e)),
+        };
+
+        if diff.deltas().len() == 0 {
+            return Ok("✅ 指定文件相对 HEAD 没有变更".to_string());
+        }
+        if json {
+            return diff_json_response(&diff);
+        }
+
+        match diff_to_patch_text(&diff) {
+            Ok(text) => Ok(format!(
+                "🔍 {} 个文件的差异：\n\n{}\n\n```diff\n{}```",
+                diff.deltas().len(),
+                diff_stat_lines(&diff).join("\n"),
+                text
+            )),
+            Err(e) => Err(format!("❌ 生成差异失败: {}", e)),
+        }
+    }
+
+    /// 查看合并基准
+    #[tool(description = "查找两个修订的共同祖先（merge base），并显示各自相对它的提交数")]
+    async fn git_merge_base(&self, Parameters(param): Parameters<MergeBaseParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+
+        let a = match resolve_commit(&repo, &param.a) {
+            Ok(c) => c.id(),
+            Err(e) => return Err(format!("❌ 无法解析 {}: {}", param.a, e)),
+        };
+        let b = match resolve_commit(&repo, &param.b) {
+            Ok(c) => c.id(),
+            Err(e) => return Err(format!("❌ 无法解析 {}: {}", param.b, e)),
+        };
+
+        let base = match repo.merge_base(a, b) {
+            Ok(oid) => oid,
+            Err(e) if e.code() == git2::ErrorCode::NotFound => {
+                return Ok(format!("⚠️ {} 和 {} 没有共同祖先", param.a, param.b));
+            }
+            Err(e) => return Err(format!("❌ 查找共同祖先失败: {}", e)),
+        };
+        let subject = repo
+            .find_commit(base)
+            .map(|c| c.summary().unwrap_or("").to_string())
+            .unwrap_or_default();
+
+        let mut result = format!(
+            "🔗 {} 与 {} 的共同祖先: {} {}\n🆔 {}",
+            param.a,
+            param.b,
+            short_id(base),
+            subject,
+            base
+        );
+        if let (Ok((a_ahead, _)), Ok((b_ahead, _))) =
+            (repo.graph_ahead_behind(a, base), repo.graph_ahead_behind(b, base))
+        {
+            result.push_str(&format!(
+                "\n\n📏 {} 自分叉后有 {} 个提交，{} 自分叉后有 {} 个提交",
+                param.a, a_ahead, param.b, b_ahead
+            ));
+        }
+        Ok(result)
+    }
+
+    /// 初始化仓库
+    #[tool(description = "初始化新的 Git 仓库，支持指定初始分支名和裸仓库；目录已是仓库时不做任何修改，位于其他仓库的工作区内时默认拒绝创建嵌套仓库")]
+    async fn git_init(&self, Parameters(param): Parameters<InitParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+
+        if let Ok(repo) = Repository::open(&repo_path) {
+            return Ok(format!("ℹ️ 已是 Git 仓库: {}", repo.path().display()));
+        }
+
+        // 目录可能尚不存在，从最近的已存在上级目录向上查找外层仓库
+        let mut probe = std::path::PathBuf::from(&repo_path);
+        while !probe.exists() {
+            match probe.parent() {
+                Some(parent) if !parent.as_os_str().is_empty() => probe = parent.to_path_buf(),
+                _ => {
+                    probe = std::path::PathBuf::from(".");
+                    break;
+                }
+            }
+        }
+        let enclosing = Repository::discover(&probe)
+            .ok()
+            .and_then(|r| r.workdir().map(|p| p.display().to_string()));
+        let mut nested_notice = String::new();
+        if let Some(outer) = enclosing {
+            if !param.allow_nested.unwrap_or(false) {
+                return Err(format!(
+                    "❌ {} 位于仓库 {} 的工作区内，已拒绝创建嵌套仓库\n💡 如确需嵌套仓库，请传入 allow_nested: true；若要使用外层仓库，直接对其操作即可",
+                    repo_path, outer
+                ));
+            }
+            nested_notice = format!("\n⚠️ 该仓库嵌套在 {} 的工作区内，外层仓库会将其视为未跟踪目录", outer);
+        }
+
+        let bare = param.bare.unwrap_or(false);
+        let mut opts = git2::RepositoryInitOptions::new();
+        opts.bare(bare).mkpath(true);
+        if let Some(branch) = &param.initial_branch {
+            opts.initial_head(branch);
+        }
+
+        match Repository::init_opts(&repo_path, &opts) {
+            Ok(repo) => {
+                let branch = repo
+                    .find_reference("HEAD")
+                    .ok()
+                    .and_then(|h| h.symbolic_target().map(|t| t.trim_start_matches("refs/heads/").to_string()))
+                    .unwrap_or_else(|| "unknown".to_string());
+                Ok(format!(
+                    "🎉 已初始化{}仓库: {}\n🌿 初始分支: {}{}",
+                    if bare { "裸" } else { " Git " },
+                    repo.path().display(),
+                    branch,
+                    nested_notice
+                ))
+            }
+            Err(e) => Err(format!("❌ 初始化仓库失败: {}", e)),
+        }
+    }
+
+    /// 检查提交信息格式
+    #[tool(description = "按 commitlint 风格规则检查提交信息（类型、标题句号、空行、行长、行尾空白），返回 {rule, severity, line, message} 列表")]
+    async fn lint_commit_message(&self, Parameters(param): Parameters<LintMessageParam>) -> Result<String, String> {
+        let config = load_config(&resolve_repo_path(param.path))?;
+
+        let problems = lint_message(&param.message, &config.lint);
+        if problems.is_empty() {
+            return Ok("✅ 提交信息检查通过".to_string());
+        }
+
+        let errors = problems.iter().filter(|p| p.severity == "error").count();
+        let warnings = problems.len() - errors;
+        let json = serde_json::to_string_pretty(&problems).unwrap_or_default();
+
+        Ok(format!(
+            "{} 提交信息检查：{} 个错误，{} 个警告\n\n```json\n{}\n```",
+            if errors > 0 { "❌" } else { "⚠️" },
+            errors,
+            warnings,
+            json
+        ))
+    }
+
+    /// 加入暂存计划
+    #[tool(description = "将文件加入当前仓库的暂存计划（保存在服务内存中），可多次调用逐步组装一次提交，最后用 plan_commit 提交")]
+    async fn plan_add(&self, Parameters(param): Parameters<PlanAddParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        if param.files.is_empty() {
+            return Err("❌ 参数 files 不能为空".to_string());
+        }
+
+        let mut plans = self.plans.lock().unwrap_or_else(|e| e.into_inner());
+        let plan = plans.entry(repo_key(&repo_path)).or_default();
+        let mut added = 0;
+        for file in param.files {
+            if !plan.contains(&file) {
+                plan.push(file);
+                added += 1;
+            }
+        }
+
+        Ok(format!(
+            "📝 已加入 {} 个文件，暂存计划共 {} 个文件：\n\n{}",
+            added,
+            plan.len(),
+            plan.iter().map(|f| format!("- {}", f)).collect::<Vec<_>>().join("\n")
+        ))
+    }
+
+    /// 查看暂存计划
+    #[tool(description = "查看当前仓库暂存计划中的文件列表")]
+    async fn plan_status(&self, Parameters(param): Parameters<PathParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+
+        let plans = self.plans.lock().unwrap_or_else(|e| e.into_inner());
+        match plans.get(&repo_key(&repo_path)) {
+            Some(plan) if !plan.is_empty() => Ok(format!(
+                "📋 暂存计划共 {} 个文件：\n\n{}",
+                plan.len(),
+                plan.iter().map(|f| format!("- {}", f)).collect::<Vec<_>>().join("\n")
+            )),
+            _ => Ok("📋 暂存计划为空，可使用 plan_add 添加文件".to_string()),
+        }
+    }
+
+    /// 提交暂存计划
+    #[tool(description = "只提交暂存计划中的文件（不影响其他已暂存或未暂存的变更），成功后清空计划")]
+    async fn plan_commit(&self, Parameters(param): Parameters<PlanCommitParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let message = require_text("message", &param.message)?;
+        let message = match attribution_trailer()? {
+            Some(trailer) => append_trailer(&message, &trailer),
+            None => message,
+        };
+        let _guard = self.lock_repo(&repo_path).await;
+
+        let key = repo_key(&repo_path);
+        let files = {
+            let plans = self.plans.lock().unwrap_or_else(|e| e.into_inner());
+            plans.get(&key).cloned().unwrap_or_default()
+        };
+        if files.is_empty() {
+            return Err("❌ 暂存计划为空，请先使用 plan_add 添加文件".to_string());
+        }
+
+        // 先 add 以包含未跟踪文件，再用路径限定 commit，只提交计划内的文件
+        let mut add_args = vec!["add".to_string(), "--".to_string()];
+        add_args.extend(files.iter().cloned());
+        match run_git(&repo_path, &add_args) {
+            Ok(o) if !o.status.success() => {
+                return Err(format!("❌ git add 失败: {}", String::from_utf8_lossy(&o.stderr)));
+            }
+            Err(e) => return Err(format!("❌ 执行 git add 失败: {}", e)),
+            _ => {}
+        }
+
+        let mut commit_args = vec!["commit".to_string(), "-m".to_string(), message, "--".to_string()];
+        commit_args.extend(files.iter().cloned());
+        match run_git(&repo_path, &commit_args) {
+            Ok(o) if o.status.success() => {
+                self.plans.lock().unwrap_or_else(|e| e.into_inner()).remove(&key);
+                Ok(format!(
+                    "✅ 已提交暂存计划中的 {} 个文件，计划已清空\n\n{}",
+                    files.len(),
+                    post_commit_hint(&repo_path)
+                ))
+            }
+            Ok(o) => Err(format!("❌ git commit 失败: {}", String::from_utf8_lossy(&o.stderr))),
+            Err(e) => Err(format!("❌ 执行 git commit 失败: {}", e)),
+        }
+    }
+
+    /// 重命名分支
+    #[tool(description = "重命名本地分支（默认当前分支），新分支名已存在时拒绝；不会重命名远程分支")]
+    async fn git_rename_branch(&self, Parameters(param): Parameters<RenameBranchParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let new_name = require_text("new_name", &param.new_name)?;
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+
+        let old_name = match param.old_name {
+            Some(name) => name,
+            None => match repo.head() {
+                Ok(h) if h.is_branch() => h.shorthand().unwrap_or("").to_string(),
+                Ok(_) => return Err("❌ 当前处于游离 HEAD，请指定 old_name".to_string()),
+                Err(e) => return Err(format!("❌ 无法读取 HEAD: {}", e)),
+            },
+        };
+
+        if repo.find_branch(&new_name, git2::BranchType::Local).is_ok() {
+            return Err(format!("❌ 分支 {} 已存在，已取消重命名", new_name));
+        }
+
+        let mut branch = match repo.find_branch(&old_name, git2::BranchType::Local) {
+            Ok(b) => b,
+            Err(e) => return Err(format!("❌ 找不到分支 {}: {}", old_name, e)),
+        };
+        let upstream = branch
+            .upstream()
+            .ok()
+            .and_then(|u| u.name().ok().flatten().map(|n| n.to_string()));
+
+        if let Err(e) = branch.rename(&new_name, false) {
+            return Err(format!("❌ 重命名分支失败: {}", e));
+        }
+
+        let mut result = format!("✅ 已将分支 {} 重命名为 {}", old_name, new_name);
+        if let Some(upstream) = upstream {
+            let (remote, remote_branch) = upstream.split_once('/').unwrap_or(("origin", &upstream));
+            result.push_str(&format!(
+                "\n\n⚠️ 远程分支 {} 未被重命名，如需同步远程请执行：\ngit push {} -u {}\ngit push {} --delete {}",
+                upstream, remote, new_name, remote, remote_branch
+            ));
+        }
+        Ok(result)
+    }
+
+    /// 查看文件逐行作者
+    #[tool(description = "查看文件每一行最后修改的提交（git blame），支持行范围和忽略指定提交（ignore_revs / .git-blame-ignore-revs）")]
+    async fn git_blame(&self, Parameters(param): Parameters<BlameParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let file = require_text("file", &param.file)?;
+
+        let mut args = vec!["blame".to_string()];
+        let ignore_revs = param.ignore_revs.unwrap_or_default();
+        for rev in &ignore_revs {
+            args.push("--ignore-rev".to_string());
+            args.push(rev.clone());
+        }
+
+        let ignore_file = Repository::discover(&repo_path)
+            .ok()
+            .and_then(|r| r.workdir().map(|p| p.join(".git-blame-ignore-revs")))
+            .filter(|p| p.is_file());
+        if let Some(ignore_file) = &ignore_file {
+            args.push("--ignore-revs-file".to_string());
+            args.push(ignore_file.to_string_lossy().to_string());
+        }
+
+        match (param.start_line, param.end_line) {
+            (Some(start), Some(end)) if start > end => {
+                return Err(format!("❌ 行范围无效: start_line {} 大于 end_line {}", start, end));
+            }
+            (Some(start), Some(end)) => args.push(format!("-L{},{}", start, end)),
+            (Some(start), None) => args.push(format!("-L{},", start)),
+            (None, Some(end)) => args.push(format!("-L1,{}", end)),
+            (None, None) => {}
+        }
+
+        if let Some(revision) = &param.revision {
+            args.push(revision.clone());
+        }
+        args.push("--".to_string());
+        args.push(file.clone());
+
+        let output = match run_git(&repo_path, &args) {
+            Ok(o) => o,
+            Err(e) => return Err(format!("❌ 执行 git blame 失败: {}", e)),
+        };
+        if !output.status.success() {
+            return Err(format!("❌ git blame 失败: {}", String::from_utf8_lossy(&output.stderr)));
+        }
+
+        let mut result = format!(
+            "🔍 {} @ {}\n",
+            file,
+            param.revision.as_deref().unwrap_or("HEAD")
+        );
+        if !ignore_revs.is_empty() {
+            result.push_str(&format!("🙈 忽略提交: {}\n", ignore_revs.join(", ")));
+        }
+        if ignore_file.is_some() {
+            result.push_str("🙈 已应用 .git-blame-ignore-revs\n");
+        }
+        result.push_str(&format!("\n```\n{}```", String::from_utf8_lossy(&output.stdout)));
+        Ok(result)
+    }
+
+    /// 统计文件变动热点
+    #[tool(description = "统计 since..HEAD 之间每个文件的累计增删行数，按变动量排序列出热点文件")]
+    async fn git_churn(&self, Parameters(param): Parameters<ChurnParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let top = param.top.unwrap_or(20);
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+
+        let since = match resolve_commit(&repo, &param.since) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 无法解析 {}: {}", param.since, e)),
+        };
+        let head = match resolve_commit(&repo, "HEAD") {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 无法解析 HEAD: {}", e)),
+        };
+
+        let mut revwalk = match repo.revwalk() {
+            Ok(w) => w,
+            Err(e) => return Err(format!("❌ 遍历提交失败: {}", e)),
+        };
+        if let Err(e) = revwalk.push(head.id()).and_then(|_| revwalk.hide(since.id())) {
+            return Err(format!("❌ 遍历提交失败: {}", e));
+        }
+
+        // 每个文件的 (新增行, 删除行, 涉及提交数)
+        let mut churn: HashMap<String, (usize, usize, usize)> = HashMap::new();
+        let mut commit_count = 0;
+        for oid in revwalk.flatten() {
+            let commit = match repo.find_commit(oid) {
+                Ok(c) => c,
+                Err(_) => continue,
+            };
+            let diff = commit.tree().and_then(|tree| {
+                let parent_tree = match commit.parent(0) {
+                    Ok(p) => Some(p.tree()?),
+                    Err(_) => None,
+                };
+                repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)
+            });
+            let diff = match diff {
+                Ok(d) => d,
+                Err(e) => return Err(format!("❌ 计算提交 {} 的差异失败: {}", short_id(oid), e)),
+            };
+            commit_count += 1;
+
+            for (idx, delta) in diff.deltas().enumerate() {
+                let file = match delta.new_file().path().or_else(|| delta.old_file().path()) {
+                    Some(p) => p.display().to_string(),
+                    None => continue,
+                };
+                let (additions, deletions) = match git2::Patch::from_diff(&diff, idx) {
+                    Ok(Some(patch)) => patch.line_stats().map(|(_, a, d)| (a, d)).unwrap_or((0, 0)),
+                    _ => (0, 0),
+                };
+                let entry = churn.entry(file).or_insert((0, 0, 0));
+                entry.0 += additions;
+                entry.1 += deletions;
+                entry.2 += 1;
+            }
+        }
+
+        if churn.is_empty() {
+            return Ok(format!("✅ {}..HEAD 之间没有文件变更", param.since));
+        }
+
+        let mut files: Vec<_> = churn.into_iter().collect();
+        files.sort_by_key(|(file, (a, d, _))| (std::cmp::Reverse(a + d), file.clone()));
+
+        let lines: Vec<String> = files
+            .iter()
+            .take(top)
+            .map(|(file, (a, d, n))| format!("- {} | {} 行（+{} -{}），{} 次提交", file, a + d, a, d, n))
+            .collect();
+
+        Ok(format!(
+            "🔥 {}..HEAD 变动热点（{} 个提交，{} 个文件，显示前 {} 个）：\n\n{}",
+            param.since,
+            commit_count,
+            files.len(),
+            lines.len(),
+            lines.join("\n")
+        ))
+    }
+
+    /// 查看单个文件的本地改动
+    #[tool(description = "对比单个文件在工作区的内容与指定修订（默认 HEAD）中的内容")]
+    async fn git_file_diff(&self, Parameters(param): Parameters<FileDiffParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let file = require_text("file", &param.file)?;
+        let revision = param.revision.unwrap_or_else(|| "HEAD".to_string());
+        let json = wants_json(param.format.as_deref())?;
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+
+        let tree = match resolve_commit(&repo, &revision).and_then(|c| c.tree()) {
+            Ok(t) => t,
+            Err(e) => return Err(format!("❌ 无法解析提交 {}: {}", revision, e)),
+        };
+
+        let mut opts = git2::DiffOptions::new();
+        opts.include_untracked(true)
+            .show_untracked_content(true)
+            .disable_pathspec_match(true)
+            .pathspec(&file);
+
+        let diff = match repo.diff_tree_to_workdir(Some(&tree), Some(&mut opts)) {
+            Ok(d) => d,
+            Err(e) => return Err(format!("❌ 计算差异失败: {}", e)),
+        };
+
+        if diff.deltas().len() == 0 {
+            return Ok(format!("✅ {} 与 {} 中的内容一致", file, revision));
+        }
+        if json {
+            return diff_json_response(&diff);
+        }
+
+        match diff_to_patch_text(&diff) {
+            Ok(text) => Ok(format!(
+                "🔍 {} 相对 {} 的差异：\n\n{}\n\n```diff\n{}```",
+                file,
+                revision,
+                diff_stat_lines(&diff).join("\n"),
+                text
+            )),
+            Err(e) => Err(format!("❌ 生成差异失败: {}", e)),
+        }
+    }
+
+    /// 中止进行中的合并/变基/拣选
+    #[tool(description = "检测仓库当前进行中的操作（merge / rebase / cherry-pick / revert / am），调用对应的 git <操作> --abort 将其中止、恢复到操作前的状态（连续拣选/回退会回到开始前的 HEAD）；会丢弃该操作产生的改动，git 无法中止时退回 git reset --merge，尽量保留无关的未提交改动")]
+    async fn git_abort(&self, Parameters(param): Parameters<PathParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let _guard = self.lock_repo(&repo_path).await;
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+
+        let state = repo.state();
+        let operation = match state {
+            RepositoryState::Clean => return Ok("✅ 当前没有进行中的合并、变基或拣选操作".to_string()),
+            RepositoryState::Merge => "合并（merge）",
+            RepositoryState::Revert | RepositoryState::RevertSequence => "回退（revert）",
+            RepositoryState::CherryPick | RepositoryState::CherryPickSequence => "拣选（cherry-pick）",
+            RepositoryState::Rebase | RepositoryState::RebaseInteractive | RepositoryState::RebaseMerge => "变基（rebase）",
+            RepositoryState::ApplyMailbox | RepositoryState::ApplyMailboxOrRebase => "补丁应用（am）",
+            RepositoryState::Bisect => return Err("❌ 当前处于二分查找（bisect），请使用 git bisect reset 结束".to_string()),
+        };
+
+        let mut fallback = None;
+        match state {
+            RepositoryState::Rebase | RepositoryState::RebaseInteractive | RepositoryState::RebaseMerge => {
+                // git2 只能打开 rebase-merge 形式的变基，其余情况交给 git 命令处理
+                let aborted = repo.open_rebase(None).and_then(|mut rebase| rebase.abort());
+                if aborted.is_err() {
+                    run_abort_command(&repo_path, &["rebase", "--abort"])?;
+                }
+            }
+            RepositoryState::ApplyMailbox | RepositoryState::ApplyMailboxOrRebase => {
+                run_abort_command(&repo_path, &["am", "--abort"])?;
+            }
+            _ => {
+                // 连续拣选/回退时 HEAD 已经移动，交给 git 按 sequencer 记录回到操作前的 HEAD
+                let args: &[&str] = match state {
+                    RepositoryState::Merge => &["merge", "--abort"],
+                    RepositoryState::Revert | RepositoryState::RevertSequence => &["revert", "--abort"],
+                    _ => &["cherry-pick", "--abort"],
+                };
+                if let Err(e) = run_abort_command(&repo_path, args) {
+                    // 兜底：git 不认可当前状态（例如由 git2 发起的操作缺少 sequencer 记录）时，
+                    // 用 reset --merge 回到当前 HEAD，只丢弃该操作改动的文件，再清理状态文件
+                    run_abort_command(&repo_path, &["reset", "--merge"])?;
+                    if let Err(e) = repo.cleanup_state() {
+                        return Err(format!("❌ 清理操作状态失败: {}", e));
+                    }
+                    fallback = Some(e);
+                }
+            }
+        }
+
+        let head = repo
+            .head()
+            .ok()
+            .and_then(|h| h.target())
+            .map(short_id)
+            .unwrap_or_default();
+        let mut result = format!("✅ 已中止进行中的{}，当前 HEAD @ {}", operation, head);
+        if let Some(e) = fallback {
+            result.push_str(&format!(
+                "\n\n⚠️ {}\n已改用 git reset --merge 回到当前 HEAD，已完成的拣选/回退提交不会被撤销",
+                e.trim_end()
+            ));
+        }
+        Ok(result)
+    }
+
+    /// 列出修订中的文件
+    #[tool(description = "列出指定修订（默认 HEAD）中某个目录的条目（模式、类型、名称），可递归，无需检出")]
+    async fn git_ls_tree(&self, Parameters(param): Parameters<LsTreeParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let revision = param.revision.unwrap_or_else(|| "HEAD".to_string());
+        let directory = param
+            .directory
+            .map(|d| d.trim().trim_matches('/').to_string())
+            .filter(|d| !d.is_empty());
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+
+        let commit = match resolve_commit(&repo, &revision) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 无法解析提交 {}: {}", revision, e)),
+        };
+        let root = match commit.tree() {
+            Ok(t) => t,
+            Err(e) => return Err(format!("❌ 读取目录树失败: {}", e)),
+        };
+
+        let tree = match &directory {
+            Some(dir) => match root
+                .get_path(std::path::Path::new(dir))
+                .and_then(|entry| entry.to_object(&repo))
+                .and_then(|obj| obj.peel_to_tree())
+            {
+                Ok(t) => t,
+                Err(e) => return Err(format!("❌ 修订 {} 中找不到目录 {}: {}", revision, dir, e)),
+            },
+            None => root,
+        };
+
+        let prefix = directory.as_ref().map(|d| format!("{}/", d)).unwrap_or_default();
+        let format_entry = |parent: &str, entry: &git2::TreeEntry| {
+            let kind = match entry.kind() {
+                Some(git2::ObjectType::Tree) => "tree",
+                Some(git2::ObjectType::Commit) => "commit",
+                _ => "blob",
+            };
+            format!(
+                "{:06o} {} {}{}{}",
+                entry.filemode(),
+                kind,
+                prefix,
+                parent,
+                entry.name().unwrap_or("")
+            )
+        };
+
+        let mut lines = Vec::new();
+        if param.recursive.unwrap_or(false) {
+            let walked = tree.walk(git2::TreeWalkMode::PreOrder, |parent, entry| {
+                if entry.kind() != Some(git2::ObjectType::Tree) {
+                    lines.push(format_entry(parent, entry));
+                }
+                git2::TreeWalkResult::Ok
+            });
+            if let Err(e) = walked {
+                return Err(format!("❌ 遍历目录树失败: {}", e));
+            }
+        } else {
+            lines.extend(tree.iter().map(|entry| format_entry("", &entry)));
+        }
+
+        if lines.is_empty() {
+            return Ok(format!("📂 {}:{} 为空", short_id(commit.id()), directory.unwrap_or_default()));
+        }
+
+        Ok(format!(
+            "📂 {}:{}（{} 个条目）\n\n{}",
+            short_id(commit.id()),
+            directory.unwrap_or_else(|| "/".to_string()),
+            lines.len(),
+            lines.join("\n")
+        ))
+    }
+
+    /// 查看包含某提交的分支
+    #[tool(description = "列出包含指定提交的所有分支（分支末端是该提交或其后代），用于确认修复是否已合入发布分支")]
+    async fn git_branch_contains(&self, Parameters(param): Parameters<BranchContainsParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let revision = require_text("revision", &param.revision)?;
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+
+        let commit = match resolve_commit(&repo, &revision) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 无法解析提交 {}: {}", revision, e)),
+        };
+
+        let filter = if param.include_remote.unwrap_or(false) {
+            None
+        } else {
+            Some(git2::BranchType::Local)
+        };
+        let branches = match repo.branches(filter) {
+            Ok(b) => b,
+            Err(e) => return Err(format!("❌ 读取分支列表失败: {}", e)),
+        };
+
+        let mut local = Vec::new();
+        let mut remote = Vec::new();
+        for (branch, kind) in branches.flatten() {
+            let (Some(name), Some(tip)) = (branch.name().ok().flatten(), branch.get().target()) else {
+                continue;
+            };
+            if name.ends_with("/HEAD") {
+                continue;
+            }
+            let contains = tip == commit.id() || repo.graph_descendant_of(tip, commit.id()).unwrap_or(false);
+            if contains {
+                match kind {
+                    git2::BranchType::Local => local.push(format!("- {}", name)),
+                    git2::BranchType::Remote => remote.push(format!("- {}", name)),
+                }
+            }
+        }
+
+        let label = format!("{} {}", short_id(commit.id()), commit.summary().unwrap_or(""));
+        if local.is_empty() && remote.is_empty() {
+            return Ok(format!("⚠️ 没有分支包含提交 {}", label));
+        }
+
+        let mut result = format!("🌿 包含提交 {} 的分支：\n", label);
+        if !local.is_empty() {
+            result.push_str(&format!("\n本地分支（{} 个）：\n{}\n", local.len(), local.join("\n")));
+        }
+        if !remote.is_empty() {
+            result.push_str(&format!("\n远程分支（{} 个）：\n{}\n", remote.len(), remote.join("\n")));
+        }
+        Ok(result.trim_end().to_string())
+    }
+
+    /// 清理已合并的分支
+    #[tool(description = "找出已完全合入目标分支（默认当前分支）的本地分支并删除；默认 dry_run=true 只列出候选，不会删除当前分支和目标分支")]
+    async fn git_prune_branches(&self, Parameters(param): Parameters<PruneBranchesParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let dry_run = param.dry_run.unwrap_or(true);
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+
+        let current = repo
+            .head()
+            .ok()
+            .filter(|h| h.is_branch())
+            .and_then(|h| h.shorthand().map(|s| s.to_string()));
+        let target = match param.target.or_else(|| current.clone()) {
+            Some(t) => t,
+            None => return Err("❌ 当前处于游离 HEAD，请指定 target".to_string()),
+        };
+        let target_commit = match resolve_commit(&repo, &target) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 无法解析 {}: {}", target, e)),
+        };
+
+        let branches = match repo.branches(Some(git2::BranchType::Local)) {
+            Ok(b) => b,
+            Err(e) => return Err(format!("❌ 读取分支列表失败: {}", e)),
+        };
+
+        let mut candidates = Vec::new();
+        for (branch, _) in branches.flatten() {
+            let (Some(name), Some(tip)) = (branch.name().ok().flatten(), branch.get().target()) else {
+                continue;
+            };
+            if name == target || Some(name) == current.as_deref() {
+                continue;
+            }
+            let merged = tip == target_commit.id()
+                || repo.graph_descendant_of(target_commit.id(), tip).unwrap_or(false);
+            if merged {
+                candidates.push((name.to_string(), tip));
+            }
+        }
+
+        if candidates.is_empty() {
+            return Ok(format!("✅ 没有已合入 {} 的本地分支需要清理", target));
+        }
+
+        let list = candidates
+            .iter()
+            .map(|(name, tip)| format!("- {} @ {}", name, short_id(*tip)))
+            .collect::<Vec<_>>()
+            .join("\n");
+        if dry_run {
+            return Ok(format!(
+                "🔍 以下 {} 个分支已合入 {}，可以清理：\n\n{}\n\n💡 确认后使用 dry_run=false 执行删除",
+                candidates.len(),
+                target,
+                list
+            ));
+        }
+
+        let mut deleted = Vec::new();
+        let mut failed = Vec::new();
+        for (name, tip) in &candidates {
+            let result = repo
+                .find_branch(name, git2::BranchType::Local)
+                .and_then(|mut b| b.delete());
+            match result {
+                Ok(()) => deleted.push(format!("- {} @ {}", name, short_id(*tip))),
+                Err(e) => failed.push(format!("- {}: {}", name, e)),
+            }
+        }
+
+        let mut result = format!("✅ 已删除 {} 个已合入 {} 的分支：\n\n{}", deleted.len(), target, deleted.join("\n"));
+        if !failed.is_empty() {
+            result.push_str(&format!("\n\n⚠️ 以下分支删除失败：\n{}", failed.join("\n")));
+        }
+        result.push_str("\n\n💡 如需恢复，可使用 git branch <name> <hash> 重新创建");
+        Ok(result)
+    }
+
+    /// 储藏指定文件
+    #[tool(description = "只储藏（git stash push）指定文件的改动（包括未跟踪文件），其余改动保留在工作区")]
+    async fn git_stash_push(&self, Parameters(param): Parameters<StashPushParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+
+        if param.files.is_empty() {
+            return Err("❌ 参数 files 不能为空".to_string());
+        }
+        let _guard = self.lock_repo(&repo_path).await;
+
+        let mut args = vec!["stash".to_string(), "push".to_string(), "--include-untracked".to_string()];
+        if let Some(message) = param.message.as_deref().map(str::trim).filter(|m| !m.is_empty()) {
+            args.push("-m".to_string());
+            args.push(message.to_string());
+        }
+        args.push("--".to_string());
+        args.extend(param.files.iter().cloned());
+
+        let output = match run_git(&repo_path, &args) {
+            Ok(o) => o,
+            Err(e) => return Err(format!("❌ 执行 git stash 失败: {}", e)),
+        };
+        if !output.status.success() {
+            return Err(format!("❌ git stash 失败: {}", String::from_utf8_lossy(&output.stderr)));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if stdout.contains("No local changes to save") {
+            return Ok("⚠️ 指定文件没有改动，未创建储藏".to_string());
+        }
+
+        let files = param.files.iter().map(|f| format!("- {}", f)).collect::<Vec<_>>().join("\n");
+        Ok(format!(
+            "✅ 已储藏 {} 个文件的改动：\n\n{}\n\n💡 恢复请执行: git stash pop",
+            param.files.len(),
+            files
+        ))
+    }
+
+    /// 检查工作区是否干净
+    #[tool(description = "检查工作区和暂存区是否干净（含未跟踪文件），以 JSON 返回 clean 布尔值和变更文件列表，适合在切换分支/拉取/合并前做判断")]
+    async fn git_is_clean(&self, Parameters(param): Parameters<PathParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(true).recurse_untracked_dirs(true);
+
+        let statuses = match repo.statuses(Some(&mut opts)) {
+            Ok(s) => s,
+            Err(e) => return Err(format!("❌ 获取状态失败: {}", e)),
+        };
+
+        let dirty: Vec<String> = statuses
+            .iter()
+            .filter(|entry| entry.status() != git2::Status::CURRENT && !entry.status().is_ignored())
+            .filter_map(|entry| entry.path().map(|p| p.to_string()))
+            .collect();
+        let clean = dirty.is_empty();
+        let json = serde_json::to_string_pretty(&serde_json::json!({
+            "clean": clean,
+            "dirty_files": dirty,
+        }))
+        .unwrap_or_default();
+
+        Ok(format!(
+            "{}\n\n```json\n{}\n```",
+            if clean {
+                "✅ 工作区干净".to_string()
+            } else {
+                format!("⚠️ 工作区有 {} 个文件未提交", dirty.len())
+            },
+            json
+        ))
+    }
+
+    /// 推送分支
+    #[tool(description = "将一个或多个本地分支推送到远程同名分支（默认当前分支推送到 origin），逐个报告每个分支的推送结果；任一分支失败时整体返回错误")]
+    async fn git_push(&self, Parameters(param): Parameters<PushParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let remote_name = param.remote.unwrap_or_else(|| "origin".to_string());
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+
+        let branches = match param.branches.filter(|b| !b.is_empty()) {
+            Some(b) => b,
+            None => match repo.head() {
+                Ok(h) if h.is_branch() => vec![h.shorthand().unwrap_or("").to_string()],
+                Ok(_) => return Err("❌ 当前处于游离 HEAD，请指定 branches".to_string()),
+                Err(e) => return Err(format!("❌ 无法读取 HEAD: {}", e)),
+            },
+        };
+
+        let mut remote = match repo.find_remote(&remote_name) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 找不到远程仓库 {}: {}", remote_name, e)),
+        };
+
+        // 本地不存在的分支直接记为失败，其余分支一次性推送
+        let mut results: Vec<(String, Result<(), String>)> = Vec::new();
+        let mut transport_error = None;
+        let mut refspecs = Vec::new();
+        for branch in &branches {
+            match repo.find_branch(branch, git2::BranchType::Local) {
+                Ok(_) => refspecs.push(format!("refs/heads/{0}:refs/heads/{0}", branch)),
+                Err(e) => results.push((branch.clone(), Err(format!("找不到本地分支: {}", e)))),
+            }
+        }
+
+        if !refspecs.is_empty() {
+            // 远程逐个回报的引用状态：None 表示更新成功，Some 为拒绝原因
+            let reported: std::cell::RefCell<HashMap<String, Option<String>>> = Default::default();
+            let attempted = Default::default();
+            let mut callbacks = credential_callbacks(&attempted);
+            callbacks.push_update_reference(|refname, status| {
+                reported
+                    .borrow_mut()
+                    .insert(refname.to_string(), status.map(|message| message.to_string()));
+                Ok(())
+            });
+            let mut opts = git2::PushOptions::new();
+            opts.remote_callbacks(callbacks);
+
+            let pushed = remote.push(&refspecs, Some(&mut opts));
+            drop(opts);
+            let reported = reported.into_inner();
+            // 连接或认证失败属于整次推送的问题，只报告一次，不归到每个分支上
+            if let Err(e) = &pushed {
+                transport_error = Some(remote_error(e, &attempted));
+            }
+            for refspec in &refspecs {
+                let refname = refspec.split(':').next().unwrap_or_default();
+                let branch = refname.trim_start_matches("refs/heads/").to_string();
+                let outcome = match reported.get(refname) {
+                    Some(None) => Ok(()),
+                    Some(Some(message)) => Err(format!("被远程拒绝: {}", message)),
+                    None if pushed.is_err() => Err("未推送（推送中断）".to_string()),
+                    None => Ok(()),
+                };
+                results.push((branch, outcome));
+            }
+        }
+
+        // 按传入顺序输出
+        results.sort_by_key(|(branch, _)| branches.iter().position(|b| b == branch));
+        let failed = results.iter().filter(|(_, r)| r.is_err()).count();
+        let lines = results
+            .iter()
+            .map(|(branch, outcome)| match outcome {
+                Ok(()) => format!("✅ {} → {}/{}", branch, remote_name, branch),
+                Err(e) => format!("❌ {}: {}", branch, e),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let mut summary = format!(
+            "🚀 推送到 {}：{}/{} 个分支成功\n\n{}",
+            remote_name,
+            results.len() - failed,
+            results.len(),
+            lines
+        );
+        if let Some(e) = transport_error {
+            summary = format!("❌ 推送到 {} 失败（连接或认证错误）: {}\n\n{}", remote_name, e, summary);
+        }
+        if failed > 0 {
+            Err(summary)
+        } else {
+            Ok(summary)
+        }
+    }
+
+    /// 自动递增版本标签
+    #[tool(description = "找到最新的语义化版本标签（如 v1.2.3 或 1.2.3），按 level（major/minor/patch）递增后在 HEAD 上创建附注标签；没有版本标签时从 0.1.0 开始")]
+    async fn git_bump_tag(&self, Parameters(param): Parameters<BumpTagParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        // 未指定前缀时 v 前缀和无前缀的版本标签都参与比较
+        let prefixes = match param.prefix {
+            Some(prefix) => vec![prefix],
+            None => vec!["v".to_string(), String::new()],
+        };
+
+        let level = param.level.trim().to_lowercase();
+        if !["major", "minor", "patch"].contains(&level.as_str()) {
+            return Err(format!("❌ 不支持的 level: {}，可选值: major / minor / patch", param.level));
+        }
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+
+        let tags = match repo.tag_names(None) {
+            Ok(t) => t,
+            Err(e) => return Err(format!("❌ 读取标签失败: {}", e)),
+        };
+        let latest = tags
+            .iter()
+            .flatten()
+            .filter_map(|tag| {
+                let prefix = prefixes.iter().find(|p| parse_semver_tag(tag, p).is_some())?;
+                Some((parse_semver_tag(tag, prefix)?, tag.to_string(), prefix.clone()))
+            })
+            .max();
+        let prefix = match &latest {
+            Some((_, _, prefix)) => prefix.clone(),
+            None => prefixes[0].clone(),
+        };
+
+        let (major, minor, patch) = match &latest {
+            None => (0, 1, 0),
+            Some(((major, minor, patch), _, _)) => match level.as_str() {
+                "major" => (major + 1, 0, 0),
+                "minor" => (*major, minor + 1, 0),
+                _ => (*major, *minor, patch + 1),
+            },
+        };
+        let new_tag = format!("{}{}.{}.{}", prefix, major, minor, patch);
+        if repo.refname_to_id(&format!("refs/tags/{}", new_tag)).is_ok() {
+            return Err(format!("❌ 标签 {} 已存在，已取消创建", new_tag));
+        }
+
+        let head = match repo.head().and_then(|h| h.peel_to_commit()) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 无法读取 HEAD: {}", e)),
+        };
+        let sign = param.sign.unwrap_or(false);
+        if sign {
+            // git2 不支持签名标签，交给 git tag -s（依赖本地 gpg 和 user.signingkey 配置）
+            match run_git(&repo_path, ["tag", "-s", &new_tag, "-m", &new_tag, &head.id().to_string()]) {
+                Ok(o) if o.status.success() => {}
+                Ok(o) => {
+                    return Err(format!(
+                        "❌ 签名标签失败: {}\n💡 请确认已配置 user.signingkey 且本地 gpg 可用",
+                        String::from_utf8_lossy(&o.stderr).trim()
+                    ));
+                }
+                Err(e) => return Err(format!("❌ 执行 git tag 失败: {}", e)),
+            }
+        } else {
+            let signature = match repo.signature() {
+                Ok(s) => s,
+                Err(e) => return Err(format!("❌ 未配置提交者身份（user.name / user.email）: {}", e)),
+            };
+            if let Err(e) = repo.tag(&new_tag, head.as_object(), &signature, &new_tag, false) {
+                return Err(format!("❌ 创建标签 {} 失败: {}", new_tag, e));
+            }
+        }
+
+        let previous = match latest {
+            Some((_, tag, _)) => format!("（上一版本 {}）", tag),
+            None => "（仓库中没有版本标签，从 0.1.0 开始）".to_string(),
+        };
+        Ok(format!(
+            "🏷️ 已在 {} {} 上创建{}标签 {}{}\n\n💡 如需推送标签，请执行: git push origin {}",
+            short_id(head.id()),
+            head.summary().unwrap_or(""),
+            if sign { "签名" } else { "" },
+            new_tag,
+            previous,
+            new_tag
+        ))
+    }
+
+    /// 恢复已删除的文件
+    #[tool(description = "从历史中找到文件最后存在的提交，将其内容写回工作区（可选加入暂存区）；工作区已存在同名文件时拒绝覆盖")]
+    async fn git_restore_deleted(&self, Parameters(param): Parameters<RestoreDeletedParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let file = require_text("file", &param.file)?;
+        let _guard = self.lock_repo(&repo_path).await;
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+        let Some(workdir) = repo.workdir().map(|p| p.to_path_buf()) else {
+            return Err("❌ 裸仓库没有工作区，无法恢复文件".to_string());
+        };
+
+        // 在读写工作区和暂存区之前校验路径，避免把文件写到仓库之外
+        let rel = repo_relative_path(&workdir, &file)?;
+        let file = rel.to_string_lossy().replace('\\', "/");
+
+        let target = workdir.join(&rel);
+        if target.exists() {
+            return Err(format!("❌ 工作区中已存在 {}，已取消恢复", file));
+        }
+
+        let head = match repo.head().and_then(|h| h.peel_to_commit()) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 无法读取 HEAD: {}", e)),
+        };
+        let mut revwalk = match repo.revwalk() {
+            Ok(w) => w,
+            Err(e) => return Err(format!("❌ 遍历提交失败: {}", e)),
+        };
+        if let Err(e) = revwalk
+            .set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)
+            .and_then(|_| revwalk.push(head.id()))
+        {
+            return Err(format!("❌ 遍历提交失败: {}", e));
+        }
+
+        // 从新到旧查找第一个仍包含该文件的提交，即文件最后存在的版本
+        let found = revwalk.flatten().find_map(|oid| {
+            let commit = repo.find_commit(oid).ok()?;
+            let entry = commit.tree().ok()?.get_path(&rel).ok()?;
+            let blob = entry.to_object(&repo).ok()?.peel_to_blob().ok()?;
+            Some((commit, blob))
+        });
+        let Some((commit, blob)) = found else {
+            return Err(format!("❌ 在 HEAD 的历史中找不到文件 {}", file));
+        };
+
+        if let Some(parent) = target.parent() {
+            if let Err(e) = std::fs::create_dir_all(parent) {
+                return Err(format!("❌ 创建目录失败: {}", e));
+            }
+        }
+        if let Err(e) = std::fs::write(&target, blob.content()) {
+            return Err(format!("❌ 写入文件失败: {}", e));
+        }
+
+        let mut result = format!(
+            "✅ 已从 {} {} 恢复 {}（{}）",
+            short_id(commit.id()),
+            commit.summary().unwrap_or(""),
+            file,
+            format_size(blob.size() as u64)
+        );
+        if param.stage.unwrap_or(false) {
+            let staged = repo
+                .index()
+                .and_then(|mut index| index.add_path(&rel).and_then(|_| index.write()));
+            match staged {
+                Ok(()) => result.push_str("，并已加入暂存区"),
+                Err(e) => result.push_str(&format!("\n\n⚠️ 加入暂存区失败: {}", e)),
+            }
+        }
+        Ok(result)
+    }
+
+    /// 查看冲突文件的三方内容
+    #[tool(description = "显示冲突文件的三方内容：共同祖先（base）、当前分支（ours）和合入方（theirs），便于在了解原始版本的前提下解决冲突")]
+    async fn git_conflict_diff(&self, Parameters(param): Parameters<ConflictDiffParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let file = require_text("file", &param.file)?;
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+        let index = match repo.index() {
+            Ok(i) => i,
+            Err(e) => return Err(format!("❌ 读取暂存区失败: {}", e)),
+        };
+        let conflicts = match index.conflicts() {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 读取冲突信息失败: {}", e)),
+        };
+
+        let entry_path = |entry: &Option<git2::IndexEntry>| {
+            entry.as_ref().map(|e| String::from_utf8_lossy(&e.path).to_string())
+        };
+        let conflict = conflicts.flatten().find(|c| {
+            [&c.ancestor, &c.our, &c.their]
+                .iter()
+                .any(|entry| entry_path(entry).as_deref() == Some(file.as_str()))
+        });
+        let Some(conflict) = conflict else {
+            return Ok(format!("✅ {} 没有冲突", file));
+        };
+
+        let section = |title: &str, entry: &Option<git2::IndexEntry>| {
+            let body = match entry {
+                None => "（该版本中不存在此文件）".to_string(),
+                Some(entry) => match repo.find_blob(entry.id) {
+                    Ok(blob) if blob.is_binary() => format!("（二进制文件，{}）", format_size(blob.size() as u64)),
+                    Ok(blob) => format!(
+                        "```\n{}\n```",
+                        String::from_utf8_lossy(blob.content()).trim_end_matches('\n')
+                    ),
+                    Err(e) => format!("（读取内容失败: {}）", e),
+                },
+            };
+            format!("### {}\n\n{}", title, body)
+        };
+
+        Ok(format!(
+            "⚔️ {} 的三方冲突内容\n\n{}\n\n{}\n\n{}\n\n💡 解决后使用 git add {} 标记为已解决",
+            file,
+            section("base（共同祖先）", &conflict.ancestor),
+            section("ours（当前分支）", &conflict.our),
+            section("theirs（合入方）", &conflict.their),
+            file
+        ))
+    }
+
+    /// 按类别列出变更文件
+    #[tool(description = "以 JSON 列出变更文件路径，可按类别过滤：staged（已暂存）/ unstaged（未暂存）/ untracked（未跟踪）/ conflicted（冲突），默认按类别分组返回全部")]
+    async fn git_list_changed(&self, Parameters(param): Parameters<ListChangedParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+
+        const CATEGORIES: [&str; 4] = ["staged", "unstaged", "untracked", "conflicted"];
+        let category = param.category.map(|c| c.trim().to_lowercase()).filter(|c| !c.is_empty());
+        if let Some(c) = &category {
+            if !CATEGORIES.contains(&c.as_str()) {
+                return Err(format!("❌ 不支持的类别 {}，可选值: {}", c, CATEGORIES.join(" / ")));
+            }
+        }
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(true).recurse_untracked_dirs(true);
+        let statuses = match repo.statuses(Some(&mut opts)) {
+            Ok(s) => s,
+            Err(e) => return Err(format!("❌ 获取状态失败: {}", e)),
+        };
+
+        let staged_bits = git2::Status::INDEX_NEW
+            | git2::Status::INDEX_MODIFIED
+            | git2::Status::INDEX_DELETED
+            | git2::Status::INDEX_RENAMED
+            | git2::Status::INDEX_TYPECHANGE;
+        let unstaged_bits = git2::Status::WT_MODIFIED
+            | git2::Status::WT_DELETED
+            | git2::Status::WT_RENAMED
+            | git2::Status::WT_TYPECHANGE;
+
+        // 同一文件可能同时属于已暂存和未暂存；冲突文件只归入 conflicted
+        let mut groups: HashMap<&str, Vec<String>> = CATEGORIES.iter().map(|c| (*c, Vec::new())).collect();
+        for entry in statuses.iter() {
+            let Some(path) = entry.path() else { continue };
+            let status = entry.status();
+            if status.is_conflicted() {
+                groups.entry("conflicted").or_default().push(path.to_string());
+                continue;
+            }
+            if status.intersects(staged_bits) {
+                groups.entry("staged").or_default().push(path.to_string());
+            }
+            if status.intersects(unstaged_bits) {
+                groups.entry("unstaged").or_default().push(path.to_string());
+            }
+            if status.is_wt_new() {
+                groups.entry("untracked").or_default().push(path.to_string());
+            }
+        }
+
+        let (count, json) = match &category {
+            Some(c) => {
+                let files = groups.remove(c.as_str()).unwrap_or_default();
+                (files.len(), serde_json::to_string_pretty(&files))
+            }
+            None => {
+                let grouped: serde_json::Map<String, serde_json::Value> = CATEGORIES
+                    .iter()
+                    .map(|c| (c.to_string(), serde_json::json!(groups.get(c).cloned().unwrap_or_default())))
+                    .collect();
+                // 同时有已暂存和未暂存改动的文件只计一次
+                let files: std::collections::HashSet<&String> = groups.values().flatten().collect();
+                (files.len(), serde_json::to_string_pretty(&grouped))
+            }
+        };
+
+        Ok(format!(
+            "📋 {}共 {} 项：\n\n```json\n{}\n```",
+            category.map(|c| format!("{} 文件", c)).unwrap_or_else(|| "变更文件".to_string()),
+            count,
+            json.unwrap_or_default()
+        ))
+    }
+
+    /// 查看当前提交
+    #[tool(description = "以 JSON 返回 HEAD 的完整哈希、短哈希、所在分支（游离时为 null）和提交标题")]
+    async fn git_head(&self, Parameters(param): Parameters<PathParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+
+        let head = match repo.head() {
+            Ok(h) => h,
+            Err(e) => return Err(format!("❌ 无法读取 HEAD（仓库可能还没有提交）: {}", e)),
+        };
+        let commit = match head.peel_to_commit() {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 无法解析 HEAD 指向的提交: {}", e)),
+        };
+        let branch = if head.is_branch() { head.shorthand() } else { None };
+
+        let json = serde_json::to_string_pretty(&serde_json::json!({
+            "oid": commit.id().to_string(),
+            "short_oid": short_id(commit.id()),
+            "branch": branch,
+            "detached": branch.is_none(),
+            "subject": commit.summary().unwrap_or(""),
+        }))
+        .unwrap_or_default();
+
+        Ok(format!(
+            "📍 HEAD @ {}（{}）\n\n```json\n{}\n```",
+            short_id(commit.id()),
+            branch.unwrap_or("游离 HEAD"),
+            json
+        ))
+    }
+
+    /// 查看储藏内容
+    #[tool(description = "显示指定储藏（默认最新）相对其创建时所在提交的完整差异，包括储藏的未跟踪文件，便于决定是否 pop")]
+    async fn git_stash_diff(&self, Parameters(param): Parameters<StashDiffParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let stash_ref = format!("stash@{{{}}}", param.index.unwrap_or(0));
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+
+        let stash = match resolve_commit(&repo, &stash_ref) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 找不到储藏 {}: {}", stash_ref, e)),
+        };
+        let base = match stash.parent(0) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 无法读取储藏的基准提交: {}", e)),
+        };
+
+        let diff = base
+            .tree()
+            .and_then(|base_tree| repo.diff_tree_to_tree(Some(&base_tree), Some(&stash.tree()?), None));
+        let diff = match diff {
+            Ok(d) => d,
+            Err(e) => return Err(format!("❌ 计算差异失败: {}", e)),
+        };
+        // 使用 -u 储藏时，未跟踪文件保存在第三个父提交中
+        let untracked = match stash.parent(2) {
+            Ok(c) => match c.tree().and_then(|tree| repo.diff_tree_to_tree(None, Some(&tree), None)) {
+                Ok(d) => Some(d),
+                Err(e) => return Err(format!("❌ 计算未跟踪文件差异失败: {}", e)),
+            },
+            Err(_) => None,
+        };
+
+        let mut stats = diff_stat_lines(&diff);
+        let mut text = match diff_to_patch_text(&diff) {
+            Ok(t) => t,
+            Err(e) => return Err(format!("❌ 生成差异失败: {}", e)),
+        };
+        if let Some(untracked) = &untracked {
+            stats.extend(diff_stat_lines(untracked));
+            match diff_to_patch_text(untracked) {
+                Ok(t) => text.push_str(&t),
+                Err(e) => return Err(format!("❌ 生成差异失败: {}", e)),
+            }
+        }
+
+        if stats.is_empty() {
+            return Ok(format!("✅ {} 没有包含任何改动", stash_ref));
+        }
+
+        Ok(format!(
+            "📦 {}: {}\n基于 {} {}\n\n{}\n\n```diff\n{}```",
+            stash_ref,
+            stash.summary().unwrap_or(""),
+            short_id(base.id()),
+            base.summary().unwrap_or(""),
+            stats.join("\n"),
+            text
+        ))
+    }
+
+    /// 追溯某一行的修改历史
+    #[tool(description = "从 HEAD 开始逐级回溯文件某一行的修改历史（类似 git log -L），列出每次修改该行的提交及修改前后的内容，最多 20 条")]
+    async fn git_line_history(&self, Parameters(param): Parameters<LineHistoryParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let file = require_text("file", &param.file)?;
+        if param.line == 0 {
+            return Err("❌ 参数 line 必须从 1 开始".to_string());
+        }
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+
+        let changes = match line_history(&repo, &file, param.line as usize, 20) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 追溯 {}:{} 失败: {}", file, param.line, e)),
+        };
+        if changes.is_empty() {
+            return Err(format!("❌ HEAD 中的 {} 没有第 {} 行", file, param.line));
+        }
+
+        let entries: Vec<String> = changes
+            .iter()
+            .enumerate()
+            .map(|(idx, change)| {
+                let (summary, author) = repo
+                    .find_commit(change.commit)
+                    .map(|c| {
+                        (
+                            c.summary().unwrap_or("").to_string(),
+                            c.author().name().unwrap_or("").to_string(),
+                        )
+                    })
+                    .unwrap_or_default();
+                let (note, before) = match &change.before {
+                    Some(content) => ("", format!("- {}\n", content)),
+                    None => ("，新增该行", String::new()),
+                };
+                format!(
+                    "{}. {} {}（{}{}）\n```diff\n{}+ {}\n```",
+                    idx + 1,
+                    short_id(change.commit),
+                    summary,
+                    author,
+                    note,
+                    before,
+                    change.after
+                )
+            })
+            .collect();
+
+        Ok(format!(
+            "🕰️ {}:{} 的修改历史（{} 次）：\n\n{}",
+            file,
+            param.line,
+            entries.len(),
+            entries.join("\n\n")
+        ))
+    }
+
+    /// 生成分组提交计划
+    #[tool(description = "读取工作区变更，按文件类别和目录启发式分组，为每组生成规范提交信息，返回可直接传给 smart_commit 的参数 JSON（建议审阅并完善描述后再提交）")]
+    async fn plan_commits(&self, Parameters(param): Parameters<PathParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(true).recurse_untracked_dirs(true);
+        let statuses = match repo.statuses(Some(&mut opts)) {
+            Ok(s) => s,
+            Err(e) => return Err(format!("❌ 获取状态失败: {}", e)),
+        };
+
+        // (分组键, 推断的提交类型, 提交组)，提交类型和描述在分组完成后补全
+        let mut groups: Vec<(String, Option<&str>, CommitGroup)> = Vec::new();
+        for entry in statuses.iter() {
+            let (Some(path), Some((_, label))) = (entry.path(), change_label(entry.status())) else {
+                continue;
+            };
+            let (key, commit_type) = classify_change(path);
+            let idx = match groups.iter().position(|g| g.0 == key) {
+                Some(idx) => idx,
+                None => {
+                    let group = CommitGroup {
+                        files: Vec::new(),
+                        commit_type: String::new(),
+                        short_desc: String::new(),
+                        details: Vec::new(),
+                        body: None,
+                    };
+                    groups.push((key, commit_type, group));
+                    groups.len() - 1
+                }
+            };
+            groups[idx].2.files.push(path.to_string());
+            groups[idx].2.details.push(format!("{}: {}", label, path));
+        }
+
+        if groups.is_empty() {
+            return Ok("✅ 工作区干净，没有需要提交的变更".to_string());
+        }
+
+        let mut commits: Vec<CommitGroup> = groups
+            .into_iter()
+            .map(|(key, commit_type, mut group)| {
+                // 普通代码组：包含新增文件视为新功能，否则视为重构
+                let commit_type = commit_type.unwrap_or_else(|| {
+                    if group.details.iter().any(|d| d.starts_with("新增")) { "feat" } else { "refactor" }
+                });
+                let short_desc = match commit_type {
+                    "docs" => "更新文档".to_string(),
+                    "test" => "更新测试".to_string(),
+                    "ci" => "更新 CI 配置".to_string(),
+                    "build" => "更新构建配置".to_string(),
+                    "config" => "更新配置文件".to_string(),
+                    "feat" => format!("新增 {} 相关功能", key),
+                    _ => format!("调整 {} 相关代码", key),
+                };
+                group.commit_type = commit_type.to_string();
+                group.short_desc = short_desc;
+                group
+            })
+            .collect();
+
+        // 与 smart_commit 的约定一致：fix 优先，其次 feat，最后其他
+        const ORDER: [&str; 8] = ["fix", "feat", "refactor", "test", "docs", "build", "ci", "config"];
+        commits.sort_by_key(|c| ORDER.iter().position(|t| *t == c.commit_type).unwrap_or(ORDER.len()));
+
+        let config = load_config(&repo_path)?;
+        let footers = ticket_footer(&config.footer, &repo_path)?
+            .into_iter()
+            .chain(attribution_trailer()?)
+            .collect();
+        let message_opts = MessageOptions {
+            emoji: emoji_enabled(None),
+            footers,
+            details_header: details_header(None)?,
+        };
+        let previews = commits
+            .iter()
+            .enumerate()
+            .map(|(idx, c)| {
+                let message = build_commit_message(&c.commit_type, &c.short_desc, &c.details, None, &message_opts);
+                format!("第{}组（{} 个文件）：\n```\n{}\n```", idx + 1, c.files.len(), message)
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
+        let json = serde_json::to_string_pretty(&serde_json::json!({
+            "commits": commits
+                .iter()
+                .map(|c| serde_json::json!({
+                    "files": c.files,
+                    "commit_type": c.commit_type,
+                    "short_desc": c.short_desc,
+                    "details": c.details,
+                }))
+                .collect::<Vec<_>>(),
+            "path": repo_path,
+        }))
+        .unwrap_or_default();
+
+        Ok(format!(
+            "📋 提交计划（{} 组）：\n\n{}\n\n📦 smart_commit 参数：\n\n```json\n{}\n```\n\n💡 分组和描述由启发式生成，请审阅并完善 short_desc 后再调用 smart_commit",
+            commits.len(),
+            previews,
+            json
+        ))
+    }
+
+    /// 解析修订表达式
+    #[tool(description = "将修订表达式（HEAD~3、main@{yesterday}、标签、部分哈希等）解析为完整的提交哈希，并返回提交标题")]
+    async fn git_rev_parse(&self, Parameters(param): Parameters<RevParseParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let expression = require_text("expression", &param.expression)?;
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+
+        let commit = match resolve_commit(&repo, &expression) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 无法解析 {}: {}", expression, e)),
+        };
+
+        Ok(format!(
+            "🔎 {} → {}\n\n{} {}",
+            expression,
+            commit.id(),
+            short_id(commit.id()),
+            commit.summary().unwrap_or("")
+        ))
+    }
+
+    /// 查看所有分支与上游的同步状态
+    #[tool(description = "以 JSON 列出每个配置了上游的本地分支相对上游的领先/落后提交数（不执行 fetch），并标记上游已被删除（gone）的分支")]
+    async fn git_remote_status(&self, Parameters(param): Parameters<PathParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+        let branches = match repo.branches(Some(git2::BranchType::Local)) {
+            Ok(b) => b,
+            Err(e) => return Err(format!("❌ 读取分支列表失败: {}", e)),
+        };
+
+        let mut entries = Vec::new();
+        let mut without_upstream = 0;
+        for (branch, _) in branches.flatten() {
+            let (Some(name), Some(refname), Some(local_oid)) = (
+                branch.name().ok().flatten(),
+                branch.get().name(),
+                branch.get().target(),
+            ) else {
+                continue;
+            };
+            // 配置了上游但对应的远程跟踪分支不存在，说明上游已被删除
+            let Ok(upstream_ref) = repo.branch_upstream_name(refname) else {
+                without_upstream += 1;
+                continue;
+            };
+            let upstream_ref = upstream_ref.as_str().unwrap_or("").to_string();
+            let upstream = upstream_ref
+                .strip_prefix("refs/remotes/")
+                .unwrap_or(&upstream_ref)
+                .to_string();
+
+            let entry = match repo.refname_to_id(&upstream_ref) {
+                Ok(upstream_oid) => {
+                    let (ahead, behind) = repo.graph_ahead_behind(local_oid, upstream_oid).unwrap_or((0, 0));
+                    serde_json::json!({
+                        "branch": name,
+                        "upstream": upstream,
+                        "ahead": ahead,
+                        "behind": behind,
+                        "gone": false,
+                    })
+                }
+                Err(_) => serde_json::json!({
+                    "branch": name,
+                    "upstream": upstream,
+                    "ahead": null,
+                    "behind": null,
+                    "gone": true,
+                }),
+            };
+            entries.push(entry);
+        }
+
+        if entries.is_empty() {
+            return Ok(format!("⚠️ 没有配置上游的本地分支（共 {} 个本地分支）", without_upstream));
+        }
+
+        let gone = entries.iter().filter(|e| e["gone"] == true).count();
+        let json = serde_json::to_string_pretty(&entries).unwrap_or_default();
+        let mut result = format!(
+            "🔄 {} 个分支的上游同步状态（基于本地已有的远程跟踪分支，未执行 fetch）",
+            entries.len()
+        );
+        if gone > 0 {
+            result.push_str(&format!("，其中 {} 个分支的上游已被删除", gone));
+        }
+        if without_upstream > 0 {
+            result.push_str(&format!("；另有 {} 个分支未配置上游", without_upstream));
+        }
+        result.push_str(&format!("\n\n```json\n{}\n```", json));
+        Ok(result)
+    }
+
+    /// 修改最近一次提交的作者
+    #[tool(description = "改写 HEAD 提交的作者姓名、邮箱或作者时间，保留文件内容和提交信息；HEAD 已推送到远程时拒绝执行")]
+    async fn git_amend_author(&self, Parameters(param): Parameters<AmendAuthorParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let name = param.name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+        let email = param.email.map(|e| e.trim().to_string()).filter(|e| !e.is_empty());
+        let date = match param.date.as_deref().map(str::trim).filter(|d| !d.is_empty()) {
+            Some(raw) => Some(parse_commit_date(raw)?),
+            None => None,
+        };
+        if name.is_none() && email.is_none() && date.is_none() {
+            return Err("❌ 请至少提供 name、email、date 中的一项".to_string());
+        }
+
+        let _guard = self.lock_repo(&repo_path).await;
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+        let head = match repo.head().and_then(|h| h.peel_to_commit()) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 无法读取 HEAD 提交: {}", e)),
+        };
+        if let Some(remote_branch) = pushed_to_remote(&repo, head.id()) {
+            return Err(format!(
+                "❌ 提交 {} 已推送到 {}，改写作者会导致与远程历史分叉，已拒绝执行",
+                short_id(head.id()),
+                remote_branch
+            ));
+        }
+
+        let old_author = head.author();
+        let author = match git2::Signature::new(
+            name.as_deref().unwrap_or(old_author.name().unwrap_or("")),
+            email.as_deref().unwrap_or(old_author.email().unwrap_or("")),
+            &date.unwrap_or_else(|| old_author.when()),
+        ) {
+            Ok(s) => s,
+            Err(e) => return Err(format!("❌ 无效的作者信息: {}", e)),
+        };
+        // 与 git commit --amend 一致，提交者更新为当前身份，取不到时保留原提交者
+        let committer = repo.signature().unwrap_or_else(|_| head.committer().to_owned());
+
+        let new_oid = match head.amend(Some("HEAD"), Some(&author), Some(&committer), None, None, None) {
+            Ok(oid) => oid,
+            Err(e) => return Err(format!("❌ 改写提交失败: {}", e)),
+        };
+
+        Ok(format!(
+            "✅ 已改写提交作者: {} → {}\n\n作者: {} <{}> → {} <{}>\n时间: {} → {}",
+            short_id(head.id()),
+            short_id(new_oid),
+            old_author.name().unwrap_or(""),
+            old_author.email().unwrap_or(""),
+            author.name().unwrap_or(""),
+            author.email().unwrap_or(""),
+            format_time(old_author.when()),
+            format_time(author.when())
+        ))
+    }
+
+    /// 按顶层目录汇总变更
+    #[tool(description = "按顶层目录汇总工作区和暂存区的变更文件数（含新增/修改/删除细分），快速了解改动集中在哪些目录，便于拆分提交")]
+    async fn git_status_by_dir(&self, Parameters(param): Parameters<PathParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(true).recurse_untracked_dirs(true);
+
+        let statuses = match repo.statuses(Some(&mut opts)) {
+            Ok(s) => s,
+            Err(e) => return Err(format!("❌ 获取状态失败: {}", e)),
+        };
+
+        // 目录 -> [新增, 修改, 删除, 其他]
+        let mut dirs: HashMap<String, [usize; 4]> = HashMap::new();
+        for entry in statuses.iter() {
+            let status = entry.status();
+            if status == git2::Status::CURRENT || status.is_ignored() {
+                continue;
+            }
+            let path = entry.path().unwrap_or("unknown");
+            let dir = match path.split_once('/') {
+                Some((top, _)) => format!("{}/", top),
+                None => "./".to_string(),
+            };
+            let slot = match change_label(status).map(|(_, label)| label) {
+                Some("新增") => 0,
+                Some("修改") => 1,
+                Some("删除") => 2,
+                _ => 3,
+            };
+            dirs.entry(dir).or_default()[slot] += 1;
+        }
+
+        if dirs.is_empty() {
+            return Ok("✅ 工作区干净，没有变更".to_string());
+        }
+
+        let mut dirs: Vec<(String, [usize; 4])> = dirs.into_iter().collect();
+        dirs.sort_by(|a, b| {
+            let (total_a, total_b) = (a.1.iter().sum::<usize>(), b.1.iter().sum::<usize>());
+            total_b.cmp(&total_a).then_with(|| a.0.cmp(&b.0))
+        });
+
+        let total: usize = dirs.iter().map(|(_, counts)| counts.iter().sum::<usize>()).sum();
+        let mut result = format!("📂 {} 个变更文件分布在 {} 个顶层目录：\n\n", total, dirs.len());
+        for (dir, counts) in &dirs {
+            let breakdown: Vec<String> = ["新增", "修改", "删除", "其他"]
+                .iter()
+                .zip(counts)
+                .filter(|(_, n)| **n > 0)
+                .map(|(label, n)| format!("{} {}", label, n))
+                .collect();
+            result.push_str(&format!(
+                "- {}: {} 个变更（{}）\n",
+                dir,
+                counts.iter().sum::<usize>(),
+                breakdown.join("，")
+            ));
+        }
+        if dirs.iter().any(|(dir, _)| dir == "./") {
+            result.push_str("\n💡 ./ 表示仓库根目录下的文件");
+        }
+        Ok(result)
+    }
+
+    /// 查看提交的父提交
+    #[tool(description = "列出指定提交的父提交哈希和标题，识别合并提交（多个父提交）和根提交（没有父提交）")]
+    async fn git_parents(&self, Parameters(param): Parameters<ParentsParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let revision = require_text("revision", &param.revision)?;
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+        let commit = match resolve_commit(&repo, &revision) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 无法解析 {}: {}", revision, e)),
+        };
+
+        let header = format!("{} {}", short_id(commit.id()), commit.summary().unwrap_or(""));
+        let parents: Vec<String> = commit
+            .parents()
+            .enumerate()
+            .map(|(i, p)| format!("{}. {} {}", i + 1, p.id(), p.summary().unwrap_or("")))
+            .collect();
+
+        Ok(match parents.len() {
+            0 => format!("🌱 {} 是根提交，没有父提交", header),
+            1 => format!("👆 {} 的父提交：\n\n{}", header, parents[0]),
+            n => format!(
+                "🔀 {} 是合并提交，有 {} 个父提交：\n\n{}\n\n💡 第 1 个父提交是合并时所在的分支，可分别与各父提交对比查看合并引入的改动",
+                header,
+                n,
+                parents.join("\n")
+            ),
+        })
+    }
+
+    /// 管理稀疏检出
+    #[tool(description = "通过 git sparse-checkout 将工作区限定为指定目录（set）、查看当前规则（list）或恢复完整工作区（disable），加快大型仓库中的 status/diff")]
+    async fn git_sparse_checkout(&self, Parameters(param): Parameters<SparseCheckoutParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let action = param.action.trim().to_lowercase();
+
+        let sparse_enabled = || {
+            Repository::open(&repo_path)
+                .and_then(|r| r.config())
+                .and_then(|c| c.get_bool("core.sparseCheckout"))
+                .unwrap_or(false)
+        };
+        let list_patterns = || -> Result<Vec<String>, String> {
+            let output = match run_git(&repo_path, ["sparse-checkout", "list"]) {
+                Ok(o) => o,
+                Err(e) => return Err(format!("❌ 执行 git sparse-checkout 失败: {}", e)),
+            };
+            if !output.status.success() {
+                return Err(format!("❌ 读取稀疏检出规则失败: {}", String::from_utf8_lossy(&output.stderr)));
+            }
+            Ok(String::from_utf8_lossy(&output.stdout).lines().map(|l| l.to_string()).collect())
+        };
+        let format_patterns =
+            |patterns: &[String]| patterns.iter().map(|p| format!("- {}", p)).collect::<Vec<_>>().join("\n");
+
+        match action.as_str() {
+            "list" => {
+                if !sparse_enabled() {
+                    return Ok("⚠️ 未启用稀疏检出，工作区包含全部文件".to_string());
+                }
+                let patterns = list_patterns()?;
+                Ok(format!("🌿 当前稀疏检出规则（{} 条）：\n\n{}", patterns.len(), format_patterns(&patterns)))
+            }
+            "set" => {
+                let patterns: Vec<String> = param
+                    .patterns
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|p| p.trim().to_string())
+                    .filter(|p| !p.is_empty())
+                    .collect();
+                if patterns.is_empty() {
+                    return Err("❌ set 操作需要提供 patterns".to_string());
+                }
+                let _guard = self.lock_repo(&repo_path).await;
+
+                let mut args = vec!["sparse-checkout".to_string(), "set".to_string()];
+                // cone 模式只接受目录，出现通配符时改用非 cone 模式
+                if patterns.iter().any(|p| p.contains(['*', '?', '[', '!'])) {
+                    args.push("--no-cone".to_string());
+                }
+                args.push("--".to_string());
+                args.extend(patterns);
+
+                let output = match run_git(&repo_path, &args) {
+                    Ok(o) => o,
+                    Err(e) => return Err(format!("❌ 执行 git sparse-checkout 失败: {}", e)),
+                };
+                if !output.status.success() {
+                    return Err(format!("❌ 设置稀疏检出失败: {}", String::from_utf8_lossy(&output.stderr)));
+                }
+
+                let active = list_patterns()?;
+                Ok(format!(
+                    "✅ 已启用稀疏检出，当前生效的规则：\n\n{}\n\n💡 恢复完整工作区请使用 action=disable",
+                    format_patterns(&active)
+                ))
+            }
+            "disable" => {
+                if !sparse_enabled() {
+                    return Ok("⚠️ 未启用稀疏检出，无需恢复".to_string());
+                }
+                let _guard = self.lock_repo(&repo_path).await;
+                let output = match run_git(&repo_path, ["sparse-checkout", "disable"]) {
+                    Ok(o) => o,
+                    Err(e) => return Err(format!("❌ 执行 git sparse-checkout 失败: {}", e)),
+                };
+                if !output.status.success() {
+                    return Err(format!("❌ 关闭稀疏检出失败: {}", String::from_utf8_lossy(&output.stderr)));
+                }
+                Ok("✅ 已关闭稀疏检出，工作区已恢复为全部文件".to_string())
+            }
+            other => Err(format!("❌ 不支持的操作 {}，可选 set、list、disable", other)),
+        }
+    }
+
+    /// 列出最近切换过的分支
+    #[tool(description = "根据 HEAD 的 reflog 列出最近切换过的本地分支（去重，按最近访问排序），方便回到之前所在的分支")]
+    async fn git_recent_branches(&self, Parameters(param): Parameters<RecentBranchesParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let count = param.count.unwrap_or(10).max(1) as usize;
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+        let history = match checkout_history(&repo) {
+            Ok(h) => h,
+            Err(e) => return Err(format!("❌ 读取 reflog 失败: {}", e)),
+        };
+        let current = repo
+            .head()
+            .ok()
+            .filter(|h| h.is_branch())
+            .and_then(|h| h.shorthand().map(|s| s.to_string()));
+
+        // 跳过游离 HEAD 的提交哈希和已删除的分支
+        let mut branches: Vec<(String, git2::Time)> = Vec::new();
+        for (target, time) in history {
+            if branches.len() >= count {
+                break;
+            }
+            if branches.iter().any(|(name, _)| *name == target)
+                || repo.find_branch(&target, git2::BranchType::Local).is_err()
+            {
+                continue;
+            }
+            branches.push((target, time));
+        }
+
+        if branches.is_empty() {
+            return Ok("⚠️ reflog 中没有切换分支的记录".to_string());
+        }
+
+        let lines: Vec<String> = branches
+            .iter()
+            .enumerate()
+            .map(|(i, (name, time))| {
+                let marker = if current.as_deref() == Some(name.as_str()) { "（当前）" } else { "" };
+                format!("{}. {}{} — {}", i + 1, name, marker, format_time(*time))
+            })
+            .collect();
+        Ok(format!("🕘 最近切换过的 {} 个分支：\n\n{}", branches.len(), lines.join("\n")))
+    }
+
+    /// 由补丁直接创建提交
+    #[tool(description = "将补丁应用到父提交的文件树（使用临时索引，不修改工作区、暂存区和任何分支），据此创建新提交并返回提交哈希")]
+    async fn git_commit_tree(&self, Parameters(param): Parameters<CommitTreeParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let message = require_text("message", &param.message)?;
+        let message = match attribution_trailer()? {
+            Some(trailer) => append_trailer(&message, &trailer),
+            None => message,
+        };
+        if param.patch.trim().is_empty() {
+            return Err("❌ 参数 patch 不能为空".to_string());
+        }
+        let parent_rev = param.parent.unwrap_or_else(|| "HEAD".to_string());
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+        let parent = match resolve_commit(&repo, &parent_rev) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 无法解析父提交 {}: {}", parent_rev, e)),
+        };
+        let parent_tree = match parent.tree() {
+            Ok(t) => t,
+            Err(e) => return Err(format!("❌ 读取父提交文件树失败: {}", e)),
+        };
+
+        // git2 要求补丁以换行结尾
+        let mut patch = param.patch.clone();
+        if !patch.ends_with('\n') {
+            patch.push('\n');
+        }
+        let diff = match git2::Diff::from_buffer(patch.as_bytes()) {
+            Ok(d) => d,
+            Err(e) => return Err(format!("❌ 无法解析补丁: {}", e)),
+        };
+        let mut index = match repo.apply_to_tree(&parent_tree, &diff, None) {
+            Ok(i) => i,
+            Err(e) => {
+                return Err(format!(
+                    "❌ 补丁无法应用到 {} ({}): {}",
+                    parent_rev,
+                    short_id(parent.id()),
+                    e
+                ))
+            }
+        };
+        let tree = match index.write_tree_to(&repo).and_then(|oid| repo.find_tree(oid)) {
+            Ok(t) => t,
+            Err(e) => return Err(format!("❌ 写入文件树失败: {}", e)),
+        };
+        if tree.id() == parent_tree.id() {
+            return Err("❌ 补丁应用后文件树没有变化，未创建提交".to_string());
+        }
+
+        let signature = match repo.signature() {
+            Ok(s) => s,
+            Err(e) => return Err(format!("❌ 无法获取提交者身份（请配置 user.name / user.email）: {}", e)),
+        };
+        let oid = match repo.commit(None, &signature, &signature, &message, &tree, &[&parent]) {
+            Ok(oid) => oid,
+            Err(e) => return Err(format!("❌ 创建提交失败: {}", e)),
+        };
+
+        Ok(format!(
+            "✅ 已创建提交 {}（父提交 {}，修改 {} 个文件），工作区和分支均未改动\n\n💡 该提交尚未被任何分支引用，可执行 git branch <分支名> {} 保留，或 git cherry-pick {} 应用到当前分支",
+            oid,
+            short_id(parent.id()),
+            diff.deltas().len(),
+            short_id(oid),
+            short_id(oid)
+        ))
+    }
+
+    /// 估算一组文件的提交规模
+    #[tool(description = "统计指定文件作为一组提交时的新增/删除行数和 hunk 数（含未跟踪文件），用于判断提交是否过大需要拆分")]
+    async fn estimate_commit_size(&self, Parameters(param): Parameters<EstimateCommitSizeParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+
+        if param.files.is_empty() {
+            return Err("❌ 参数 files 不能为空".to_string());
+        }
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+        let diff = match group_diff(&repo, &param.files) {
+            Ok(d) => d,
+            Err(e) => return Err(format!("❌ 计算差异失败: {}", e)),
+        };
+
+        if diff.deltas().len() == 0 {
+            return Ok("✅ 指定文件相对 HEAD 没有变更".to_string());
+        }
+
+        let (mut insertions, mut deletions, mut hunks, mut binaries) = (0, 0, 0, 0);
+        let mut lines = Vec::new();
+        for idx in 0..diff.deltas().len() {
+            let Ok(Some(patch)) = git2::Patch::from_diff(&diff, idx) else {
+                continue;
+            };
+            let delta = patch.delta();
+            let file = delta
+                .new_file()
+                .path()
+                .or_else(|| delta.old_file().path())
+                .map(|p| p.display().to_string())
+                .unwrap_or_else(|| "unknown".to_string());
+            if delta.flags().is_binary() {
+                binaries += 1;
+                lines.push(format!("- {} | {}", file, binary_change(&delta)));
+                continue;
+            }
+            let (_, added, removed) = patch.line_stats().unwrap_or((0, 0, 0));
+            insertions += added;
+            deletions += removed;
+            hunks += patch.num_hunks();
+            lines.push(format!("- {} | +{} -{}，{} 个 hunk", file, added, removed, patch.num_hunks()));
+        }
+
+        let mut result = format!(
+            "📏 {} 个文件预计提交规模：+{} -{}，共 {} 行变动，{} 个 hunk",
+            diff.deltas().len(),
+            insertions,
+            deletions,
+            insertions + deletions,
+            hunks
+        );
+        if binaries > 0 {
+            result.push_str(&format!("，另有 {} 个二进制文件", binaries));
+        }
+        result.push_str(&format!("\n\n{}", lines.join("\n")));
+        Ok(result)
+    }
+
+    /// 读写提交注释（git notes）
+    #[tool(description = "查看（show）、添加（add）或删除（remove）提交的 git notes 注释，可在不改写提交的前提下附加评审状态、构建结果等元数据")]
+    async fn git_notes(&self, Parameters(param): Parameters<NotesParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let action = param.action.trim().to_lowercase();
+        let revision = param.revision.unwrap_or_else(|| "HEAD".to_string());
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+        let commit = match resolve_commit(&repo, &revision) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 无法解析 {}: {}", revision, e)),
+        };
+        let target = format!("{} {}", short_id(commit.id()), commit.summary().unwrap_or(""));
+        let existing = repo
+            .find_note(None, commit.id())
+            .ok()
+            .map(|note| note.message().unwrap_or("").trim_end().to_string());
+
+        match action.as_str() {
+            "show" => Ok(match existing {
+                Some(note) => format!("📌 {} 的注释：\n\n{}", target, note),
+                None => format!("⚠️ {} 没有注释", target),
+            }),
+            "add" => {
+                let message = require_text("message", param.message.as_deref().unwrap_or(""))?;
+                let signature = match repo.signature() {
+                    Ok(s) => s,
+                    Err(e) => return Err(format!("❌ 无法获取提交者身份（请配置 user.name / user.email）: {}", e)),
+                };
+                if let Err(e) = repo.note(&signature, &signature, None, commit.id(), &message, true) {
+                    return Err(format!("❌ 添加注释失败: {}", e));
+                }
+                let result = match existing {
+                    Some(old) => format!("✅ 已覆盖 {} 的注释\n\n原注释：\n{}", target, old),
+                    None => format!("✅ 已为 {} 添加注释", target),
+                };
+                Ok(format!(
+                    "{}\n\n💡 注释保存在 refs/notes/commits，默认不会随 git push 推送，需要时执行: git push origin refs/notes/commits",
+                    result
+                ))
+            }
+            "remove" => {
+                if existing.is_none() {
+                    return Ok(format!("⚠️ {} 没有注释，无需删除", target));
+                }
+                let signature = match repo.signature() {
+                    Ok(s) => s,
+                    Err(e) => return Err(format!("❌ 无法获取提交者身份（请配置 user.name / user.email）: {}", e)),
+                };
+                match repo.note_delete(commit.id(), None, &signature, &signature) {
+                    Ok(_) => Ok(format!("✅ 已删除 {} 的注释", target)),
+                    Err(e) => Err(format!("❌ 删除注释失败: {}", e)),
+                }
+            }
+            other => Err(format!("❌ 不支持的操作 {}，可选 show、add、remove", other)),
+        }
+    }
+
+    /// 检查分支是否已变基到基准分支之上
+    #[tool(description = "检查 branch 与 base 的共同祖先是否就是 base 的最新提交（即 branch 已基于最新的 base），以 JSON 返回结果、落后 base 的提交数和分支上的合并提交数，适合作为合并前的检查")]
+    async fn git_is_rebased_on(&self, Parameters(param): Parameters<IsRebasedOnParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let branch = require_text("branch", &param.branch)?;
+        let base = require_text("base", &param.base)?;
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+        let branch_oid = match resolve_commit(&repo, &branch) {
+            Ok(c) => c.id(),
+            Err(e) => return Err(format!("❌ 无法解析 {}: {}", branch, e)),
+        };
+        let base_oid = match resolve_commit(&repo, &base) {
+            Ok(c) => c.id(),
+            Err(e) => return Err(format!("❌ 无法解析 {}: {}", base, e)),
+        };
+
+        let merge_base = match repo.merge_base(branch_oid, base_oid) {
+            Ok(oid) => oid,
+            Err(e) if e.code() == git2::ErrorCode::NotFound => {
+                return Ok(format!("⚠️ {} 和 {} 没有共同祖先", branch, base));
+            }
+            Err(e) => return Err(format!("❌ 查找共同祖先失败: {}", e)),
+        };
+        let (ahead, behind) = match repo.graph_ahead_behind(branch_oid, base_oid) {
+            Ok(v) => v,
+            Err(e) => return Err(format!("❌ 计算分叉失败: {}", e)),
+        };
+
+        // base..branch 中的合并提交说明分支历史不是线性的
+        let mut revwalk = match repo.revwalk() {
+            Ok(w) => w,
+            Err(e) => return Err(format!("❌ 遍历提交失败: {}", e)),
+        };
+        if let Err(e) = revwalk.push(branch_oid).and_then(|_| revwalk.hide(base_oid)) {
+            return Err(format!("❌ 遍历提交失败: {}", e));
+        }
+        let merge_commits = revwalk
+            .flatten()
+            .filter_map(|oid| repo.find_commit(oid).ok())
+            .filter(|c| c.parent_count() > 1)
+            .count();
+
+        let rebased = merge_base == base_oid;
+        let json = serde_json::to_string_pretty(&serde_json::json!({
+            "rebased": rebased,
+            "merge_base": merge_base.to_string(),
+            "base_tip": base_oid.to_string(),
+            "ahead": ahead,
+            "behind": behind,
+            "merge_commits": merge_commits,
+        }))
+        .unwrap_or_default();
+
+        let mut header = if rebased {
+            format!("✅ {} 已基于 {} 的最新提交，领先 {} 个提交", branch, base, ahead)
+        } else {
+            format!(
+                "⚠️ {} 落后 {} {} 个提交，需要先变基: git rebase {}",
+                branch, base, behind, base
+            )
+        };
+        if merge_commits > 0 {
+            header.push_str(&format!("\n⚠️ 分支上有 {} 个合并提交，历史不是线性的", merge_commits));
+        }
+        Ok(format!("{}\n\n```json\n{}\n```", header, json))
+    }
+
+    /// 生成远程仓库的网页链接
+    #[tool(description = "将 origin 远程地址转换为 GitHub / GitLab / Bitbucket 的网页链接，可指向指定提交或指定提交下的文件，仅返回 URL 不打开浏览器")]
+    async fn git_web_url(&self, Parameters(param): Parameters<WebUrlParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let file = param.file.map(|f| f.trim().trim_start_matches("./").to_string()).filter(|f| !f.is_empty());
+        let revision = param.revision.map(|r| r.trim().to_string()).filter(|r| !r.is_empty());
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+        let remote = match repo.find_remote("origin") {
+            Ok(r) => r,
+            Err(_) => return Err("❌ 仓库没有配置名为 origin 的远程".to_string()),
+        };
+        let remote_url = remote.url().unwrap_or("").to_string();
+        let (base, host) = match web_base_url(&remote_url) {
+            Some(v) => v,
+            None => return Err(format!("❌ 无法从远程地址 {} 推断网页地址", remote_url)),
+        };
+
+        if revision.is_none() && file.is_none() {
+            return Ok(format!("🌐 {}", base));
+        }
+        if host == WebHost::Unknown {
+            return Ok(format!(
+                "🌐 {}\n\n⚠️ 无法识别托管平台，只能给出仓库主页，文件和提交链接格式未知",
+                base
+            ));
+        }
+
+        let rev = revision.unwrap_or_else(|| "HEAD".to_string());
+        let commit = match resolve_commit(&repo, &rev) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 无法解析 {}: {}", rev, e)),
+        };
+        let hash = commit.id().to_string();
+
+        let url = match (&file, host) {
+            (Some(file), WebHost::GitHub) => format!("{}/blob/{}/{}", base, hash, file),
+            (Some(file), WebHost::GitLab) => format!("{}/-/blob/{}/{}", base, hash, file),
+            (Some(file), _) => format!("{}/src/{}/{}", base, hash, file),
+            (None, WebHost::GitHub) => format!("{}/commit/{}", base, hash),
+            (None, WebHost::GitLab) => format!("{}/-/commit/{}", base, hash),
+            (None, _) => format!("{}/commits/{}", base, hash),
+        };
+
+        let mut result = format!("🌐 {}", url);
+        if let Some(file) = &file {
+            let exists = commit.tree().map(|t| t.get_path(std::path::Path::new(file)).is_ok()).unwrap_or(false);
+            if !exists {
+                result.push_str(&format!("\n\n⚠️ 提交 {} 中不存在文件 {}", short_id(commit.id()), file));
+            }
+        }
+        if pushed_to_remote(&repo, commit.id()).is_none() {
+            result.push_str(&format!(
+                "\n\n⚠️ 提交 {} 尚未推送到远程，推送前链接无法访问",
+                short_id(commit.id())
+            ));
+        }
+        Ok(result)
+    }
+
+    /// 从其他分支取来指定文件
+    #[tool(description = "将指定文件在其他分支上的版本写入当前工作区和暂存区（相当于 git restore --source=<branch> --staged --worktree），不需要完整合并；目标文件有未提交改动时拒绝执行")]
+    async fn git_checkout_from(&self, Parameters(param): Parameters<CheckoutFromParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let branch = require_text("branch", &param.branch)?;
+        let files: Vec<String> = param
+            .files
+            .unwrap_or_default()
+            .into_iter()
+            .map(|f| f.trim().to_string())
+            .filter(|f| !f.is_empty())
+            .collect();
+        let _guard = self.lock_repo(&repo_path).await;
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+        let source = match resolve_commit(&repo, &branch).and_then(|c| c.tree()) {
+            Ok(t) => t,
+            Err(e) => return Err(format!("❌ 无法解析 {}: {}", branch, e)),
+        };
+        let head_tree = repo.head().ok().and_then(|h| h.peel_to_tree().ok());
+
+        let mut opts = git2::DiffOptions::new();
+        for file in &files {
+            opts.pathspec(file);
+        }
+        let diff = match repo.diff_tree_to_tree(head_tree.as_ref(), Some(&source), Some(&mut opts)) {
+            Ok(d) => d,
+            Err(e) => return Err(format!("❌ 计算差异失败: {}", e)),
+        };
+        if diff.deltas().len() == 0 {
+            return Ok(format!("✅ 指定文件在当前分支与 {} 之间没有差异", branch));
+        }
+
+        // 覆盖有未提交改动的文件会丢失这些改动
+        let changed: Vec<String> = diff
+            .deltas()
+            .filter_map(|d| d.new_file().path().or_else(|| d.old_file().path()))
+            .map(|p| p.display().to_string())
+            .collect();
+        let mut status_opts = StatusOptions::new();
+        status_opts.include_untracked(true);
+        for file in &changed {
+            status_opts.pathspec(file);
+        }
+        status_opts.disable_pathspec_match(true);
+        let dirty: Vec<String> = match repo.statuses(Some(&mut status_opts)) {
+            Ok(statuses) => statuses
+                .iter()
+                .filter(|e| e.status() != git2::Status::CURRENT && !e.status().is_ignored())
+                .filter_map(|e| e.path().map(|p| format!("- {}", p)))
+                .collect(),
+            Err(e) => return Err(format!("❌ 获取状态失败: {}", e)),
+        };
+        if !dirty.is_empty() {
+            return Err(format!(
+                "❌ 以下文件有未提交的改动，取来 {} 的版本会覆盖它们，已取消：\n\n{}\n\n💡 请先提交或储藏（git_stash_push）这些改动",
+                branch,
+                dirty.join("\n")
+            ));
+        }
+
+        let mut args = vec![
+            "restore".to_string(),
+            format!("--source={}", branch),
+            "--staged".to_string(),
+            "--worktree".to_string(),
+            "--".to_string(),
+        ];
+        args.extend(changed);
+        let output = match run_git(&repo_path, &args) {
+            Ok(o) => o,
+            Err(e) => return Err(format!("❌ 执行 git restore 失败: {}", e)),
+        };
+        if !output.status.success() {
+            return Err(format!("❌ git restore 失败: {}", String::from_utf8_lossy(&output.stderr)));
+        }
+
+        Ok(format!(
+            "✅ 已从 {} 取来 {} 个文件的版本并加入暂存区：\n\n{}\n\n💡 确认无误后可调用 git_commit 提交",
+            branch,
+            diff.deltas().len(),
+            diff_stat_lines(&diff).join("\n")
+        ))
+    }
+
+    /// 查看远程仓库的默认分支
+    #[tool(description = "确定远程仓库的默认分支（main 还是 master 等）：优先读取本地的 refs/remotes/<remote>/HEAD，没有时连接远程查询，并说明结果来源")]
+    async fn git_default_branch(&self, Parameters(param): Parameters<DefaultBranchParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let remote_name = param.remote.unwrap_or_else(|| "origin".to_string());
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+        let mut remote = match repo.find_remote(&remote_name) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 找不到远程仓库 {}: {}", remote_name, e)),
+        };
+
+        // clone 时会记录远程 HEAD，不需要联网
+        let head_ref = format!("refs/remotes/{}/HEAD", remote_name);
+        let local = repo
+            .find_reference(&head_ref)
+            .ok()
+            .and_then(|r| r.symbolic_target().map(|t| t.to_string()));
+        if let Some(target) = local {
+            let prefix = format!("refs/remotes/{}/", remote_name);
+            let branch = target.strip_prefix(&prefix).unwrap_or(&target);
+            return Ok(format!("🌿 {} 的默认分支: {}（来源：本地 {}）", remote_name, branch, head_ref));
+        }
+
+        let attempted = Default::default();
+        let connected = remote
+            .connect_auth(git2::Direction::Fetch, Some(credential_callbacks(&attempted)), None)
+            .and_then(|connection| {
+                connection
+                    .default_branch()
+                    .map(|buf| buf.as_str().unwrap_or("").to_string())
+            });
+        let target = match connected {
+            Ok(t) if !t.is_empty() => t,
+            Ok(_) => return Err(format!("❌ 远程仓库 {} 没有公布默认分支", remote_name)),
+            Err(e) => return Err(format!("❌ 查询远程默认分支失败: {}", remote_error(&e, &attempted))),
+        };
+        let branch = target.strip_prefix("refs/heads/").unwrap_or(&target);
+        Ok(format!(
+            "🌿 {} 的默认分支: {}（来源：远程查询）\n\n💡 执行 git remote set-head {} -a 可记录到本地，之后无需联网",
+            remote_name, branch, remote_name
+        ))
+    }
+
+    /// 预览提交组的提交信息
+    #[tool(description = "按 smart_commit 的规则（emoji、类型、正文、工单号脚注、署名 trailer、.gitmcp.toml 规范）渲染单个提交组的完整提交信息，不暂存也不提交任何文件")]
+    async fn preview_commit_message(&self, Parameters(param): Parameters<PreviewCommitMessageParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let group = param.group;
+        let short_desc = require_text("short_desc", &group.short_desc)?;
+
+        let config = load_config(&repo_path)?;
+        let violations = check_commit_policy(
+            &config.policy,
+            &group.commit_type,
+            &short_desc,
+            &group.details,
+            group.body.as_deref(),
+        );
+        let footers = ticket_footer(&config.footer, &repo_path)?
+            .into_iter()
+            .chain(attribution_trailer()?)
+            .collect();
+        let opts = MessageOptions {
+            emoji: emoji_enabled(param.emoji),
+            footers,
+            details_header: details_header(param.language.as_deref())?,
+        };
+
+        let message = build_commit_message(&group.commit_type, &short_desc, &group.details, group.body.as_deref(), &opts);
+        let mut result = format!("📝 提交信息预览（未暂存、未提交）：\n\n```\n{}\n```", message);
+        if !violations.is_empty() {
+            result.push_str(&format!(
+                "\n\n⚠️ 不符合提交规范（.gitmcp.toml），smart_commit 会拒绝该组：\n\n{}",
+                violations.iter().map(|v| format!("- {}", v)).collect::<Vec<_>>().join("\n")
+            ));
+        }
+        Ok(result)
+    }
+
+    /// 统计仓库体积
+    #[tool(description = "统计已跟踪文件数量和总大小、.git 目录大小以及最大的 10 个已跟踪文件，帮助判断是否需要维护或迁移到 Git LFS")]
+    async fn git_repo_size(&self, Parameters(param): Parameters<PathParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+        let Some(workdir) = repo.workdir().map(|p| p.to_path_buf()) else {
+            return Err("❌ 裸仓库没有工作区，无法统计已跟踪文件".to_string());
+        };
+        let index = match repo.index() {
+            Ok(i) => i,
+            Err(e) => return Err(format!("❌ 读取暂存区失败: {}", e)),
+        };
+
+        // 以暂存区为准列出已跟踪文件，子模块（gitlink）不计入
+        let mut files = Vec::new();
+        let mut missing = 0;
+        for entry in index.iter() {
+            if entry.mode == 0o160000 {
+                continue;
+            }
+            let path = String::from_utf8_lossy(&entry.path).to_string();
+            match std::fs::symlink_metadata(workdir.join(&path)) {
+                Ok(meta) => files.push((path, meta.len())),
+                Err(_) => missing += 1,
+            }
+        }
+        if files.is_empty() && missing == 0 {
+            return Ok("⚠️ 仓库中没有已跟踪的文件".to_string());
+        }
+
+        let count = files.len();
+        let total: u64 = files.iter().map(|(_, size)| size).sum();
+        let git_dir_size = dir_size(repo.path());
+        files.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+        files.truncate(10);
+
+        let mut result = format!(
+            "📦 仓库体积统计：\n\n- 已跟踪文件: {} 个，共 {}\n- .git 目录: {}\n\n📈 最大的 {} 个已跟踪文件：\n{}",
+            count,
+            format_size(total),
+            format_size(git_dir_size),
+            files.len(),
+            format_large_files(&files)
+        );
+        if missing > 0 {
+            result.push_str(&format!("\n\n⚠️ 另有 {} 个已跟踪文件不在工作区（已删除或被稀疏检出排除），未计入大小", missing));
+        }
+        Ok(result)
+    }
+
+    /// 列出两个标签之间的变更
+    #[tool(description = "校验标签存在后，列出 from_tag..to_tag（默认 HEAD）之间的提交并按提交类型分组，用于撰写发布说明；合并提交不计入")]
+    async fn git_changes_between_tags(&self, Parameters(param): Parameters<ChangesBetweenTagsParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let from_tag = require_text("from_tag", &param.from_tag)?;
+        let to_tag = param.to_tag.map(|t| t.trim().to_string()).filter(|t| !t.is_empty());
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+        let tag_commit = |tag: &str| match repo
+            .find_reference(&format!("refs/tags/{}", tag))
+            .and_then(|r| r.peel_to_commit())
+        {
+            Ok(c) => Ok(c.id()),
+            Err(_) => Err(format!("❌ 标签 {} 不存在", tag)),
+        };
+        let from = tag_commit(&from_tag)?;
+        let (to, to_label) = match &to_tag {
+            Some(tag) => (tag_commit(tag)?, tag.clone()),
+            None => match repo.head().and_then(|h| h.peel_to_commit()) {
+                Ok(c) => (c.id(), "HEAD".to_string()),
+                Err(e) => return Err(format!("❌ 无法读取 HEAD: {}", e)),
+            },
+        };
+
+        let mut revwalk = match repo.revwalk() {
+            Ok(w) => w,
+            Err(e) => return Err(format!("❌ 遍历提交失败: {}", e)),
+        };
+        if let Err(e) = revwalk.push(to).and_then(|_| revwalk.hide(from)) {
+            return Err(format!("❌ 遍历提交失败: {}", e));
+        }
+        let commits: Vec<git2::Commit> = revwalk
+            .flatten()
+            .filter_map(|oid| repo.find_commit(oid).ok())
+            .filter(|c| c.parent_count() <= 1)
+            .collect();
+
+        if commits.is_empty() {
+            let hint = if repo.graph_descendant_of(from, to).unwrap_or(false) {
+                format!("\n\n💡 {} 比 {} 更新，是否把两个标签写反了？", from_tag, to_label)
+            } else {
+                String::new()
+            };
+            return Ok(format!("⚠️ {}..{} 之间没有提交{}", from_tag, to_label, hint));
+        }
+
+        Ok(format!(
+            "📋 {}..{} 共 {} 个提交：\n\n{}",
+            from_tag,
+            to_label,
+            commits.len(),
+            changelog_sections(&commits)
+        ))
+    }
+
+    /// 检查能否快进合并
+    #[tool(description = "分析将 branch 合入当前分支的结果：up_to_date（已包含）、fast_forward（可快进）、normal（需要合并提交）或 conflict（会冲突，通过内存中试合并判断），以 JSON 返回，不修改工作区")]
+    async fn git_can_ff(&self, Parameters(param): Parameters<CanFfParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let branch = require_text("branch", &param.branch)?;
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+        let theirs = match resolve_commit(&repo, &branch) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 无法解析 {}: {}", branch, e)),
+        };
+        let annotated = match repo.find_annotated_commit(theirs.id()) {
+            Ok(a) => a,
+            Err(e) => return Err(format!("❌ 无法解析 {}: {}", branch, e)),
+        };
+        let (analysis, _) = match repo.merge_analysis(&[&annotated]) {
+            Ok(v) => v,
+            Err(e) => return Err(format!("❌ 合并分析失败: {}", e)),
+        };
+
+        let mut conflicts = Vec::new();
+        let (result, explanation) = if analysis.is_up_to_date() {
+            ("up_to_date", format!("✅ 当前分支已包含 {} 的全部提交，无需合并", branch))
+        } else if analysis.is_unborn() || analysis.is_fast_forward() {
+            ("fast_forward", format!("✅ 可以快进合并 {}，可使用 --ff-only", branch))
+        } else {
+            // 在内存中试合并，判断真正合并时是否会冲突
+            let ours = match repo.head().and_then(|h| h.peel_to_commit()) {
+                Ok(c) => c,
+                Err(e) => return Err(format!("❌ 无法读取 HEAD: {}", e)),
+            };
+            let index = match repo.merge_commits(&ours, &theirs, None) {
+                Ok(i) => i,
+                Err(e) => return Err(format!("❌ 试合并失败: {}", e)),
+            };
+            if index.has_conflicts() {
+                conflicts = merge_conflicts(&index).into_iter().map(|(path, _)| path).collect();
+                (
+                    "conflict",
+                    format!("⚠️ 无法快进，合并 {} 会在 {} 个文件上产生冲突", branch, conflicts.len()),
+                )
+            } else {
+                ("normal", format!("⚠️ 无法快进，合并 {} 需要创建合并提交（不会冲突）", branch))
+            }
+        };
+
+        let json = serde_json::to_string_pretty(&serde_json::json!({
+            "analysis": result,
+            "fast_forward": result == "fast_forward",
+            "conflicts": conflicts,
+        }))
+        .unwrap_or_default();
+        Ok(format!("{}\n\n```json\n{}\n```", explanation, json))
+    }
+
+    /// 储藏改动后执行操作并自动恢复
+    #[tool(description = "先储藏工作区改动（含未跟踪文件），执行 checkout / pull / merge，然后自动恢复储藏并报告恢复时的冲突，避免遗留储藏；操作失败时会先撤销操作再恢复")]
+    async fn git_with_stash(&self, Parameters(param): Parameters<WithStashParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let operation = param.operation.trim().to_lowercase();
+        let branch = param.branch.map(|b| b.trim().to_string()).filter(|b| !b.is_empty());
+
+        let op_args: Vec<String> = match (operation.as_str(), &branch) {
+            ("checkout", Some(b)) => vec!["checkout".into(), b.clone()],
+            ("merge", Some(b)) => vec!["merge".into(), "--no-edit".into(), b.clone()],
+            ("pull", None) => vec!["pull".into(), "--no-edit".into()],
+            ("pull", Some(b)) => {
+                let remote = param
+                    .remote
+                    .map(|r| r.trim().to_string())
+                    .filter(|r| !r.is_empty())
+                    .or_else(|| {
+                        let repo = Repository::open(&repo_path).ok()?;
+                        let head = repo.head().ok()?;
+                        let remote = repo.branch_upstream_remote(head.name()?).ok()?;
+                        remote.as_str().map(|r| r.to_string())
+                    })
+                    .unwrap_or_else(|| "origin".to_string());
+                vec!["pull".into(), "--no-edit".into(), remote, b.clone()]
+            }
+            ("checkout" | "merge", None) => return Err(format!("❌ {} 操作需要提供 branch", operation)),
+            (other, _) => return Err(format!("❌ 不支持的操作 {}，可选 checkout、pull、merge", other)),
+        };
+        let _guard = self.lock_repo(&repo_path).await;
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+        if repo.state() != RepositoryState::Clean {
+            return Err("❌ 仓库有进行中的合并、变基等操作，请先完成或调用 git_abort".to_string());
+        }
+
+        let run = |args: &[String]| match run_git(&repo_path, args) {
+            Ok(o) if o.status.success() => Ok(String::from_utf8_lossy(&o.stdout).trim().to_string()),
+            Ok(o) => Err(format!(
+                "{}{}",
+                String::from_utf8_lossy(&o.stdout),
+                String::from_utf8_lossy(&o.stderr)
+            )
+            .trim()
+            .to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+
+        let stash_args: Vec<String> = vec![
+            "stash".into(),
+            "push".into(),
+            "--include-untracked".into(),
+            "-m".into(),
+            format!("git-mcp: git_with_stash before {}", operation),
+        ];
+        // 比较储藏前后的 refs/stash 判断是否真正创建了储藏，不依赖 git 输出的语言
+        let before = stash_top(&repo_path);
+        if let Err(e) = run(&stash_args) {
+            return Err(format!("❌ 储藏改动失败，未执行 {}: {}", operation, e));
+        }
+        let stashed = stash_top(&repo_path).filter(|oid| Some(*oid) != before);
+
+        let op_result = run(&op_args);
+        let mut report = match &op_result {
+            Ok(_) => format!("✅ 已执行 git {}", op_args.join(" ")),
+            Err(e) => {
+                // 合并失败会留下冲突状态，先撤销再恢复储藏
+                let state = Repository::open(&repo_path).map(|r| r.state()).ok();
+                let undo = if state == Some(RepositoryState::Merge) {
+                    match run_abort_command(&repo_path, &["merge", "--abort"]) {
+                        Ok(()) => "，已撤销该操作".to_string(),
+                        Err(abort) => format!("，撤销失败: {}", abort),
+                    }
+                } else {
+                    String::new()
+                };
+                format!("❌ git {} 失败{}：\n{}", op_args.join(" "), undo, e)
+            }
+        };
+
+        if let Some(stash_oid) = stashed {
+            // 只恢复本次创建的储藏，按提交查找其当前位置
+            let Some(stash) = stash_ref(&repo_path, stash_oid) else {
+                report.push_str(&format!(
+                    "\n\n⚠️ 找不到本次创建的储藏 {}，未自动恢复\n\n💡 请执行 git stash list 确认后手动恢复",
+                    short_id(stash_oid)
+                ));
+                return match op_result {
+                    Ok(_) => Ok(report),
+                    Err(_) => Err(report),
+                };
+            };
+            match run(&["stash".to_string(), "pop".to_string(), stash.clone()]) {
+                Ok(_) => report.push_str("\n\n📦 已恢复储藏的改动"),
+                Err(e) => {
+                    let conflicts = run(&[
+                        "diff".to_string(),
+                        "--name-only".to_string(),
+                        "--diff-filter=U".to_string(),
+                    ])
+                    .unwrap_or_default();
+                    if conflicts.is_empty() {
+                        report.push_str(&format!(
+                            "\n\n⚠️ 恢复储藏失败，储藏仍保留在 {}：\n{}\n\n💡 处理后执行 git stash pop {} 恢复",
+                            stash, e, stash
+                        ));
+                    } else {
+                        let files = conflicts.lines().map(|f| format!("- {}", f)).collect::<Vec<_>>().join("\n");
+                        report.push_str(&format!(
+                            "\n\n⚠️ 恢复储藏时出现冲突（已写入冲突标记），储藏仍保留在 {}：\n{}\n\n💡 解决冲突后执行 git stash drop {} 删除该储藏",
+                            stash, files, stash
+                        ));
+                    }
+                }
+            }
+        } else {
+            report.push_str("\n\n💡 工作区没有改动，未创建储藏");
+        }
+
+        match op_result {
+            Ok(_) => Ok(report),
+            Err(_) => Err(report),
+        }
+    }
+
+    /// 以结构化数据返回提交图
+    #[tool(description = "以 JSON 返回 HEAD 和所有本地分支的提交图：每个提交的哈希、父提交、指向它的引用（分支/标签/HEAD）以及泳道信息，便于客户端绘制提交图")]
+    async fn git_graph(&self, Parameters(param): Parameters<GraphParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let count = param.count.unwrap_or(50).max(1) as usize;
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+
+        let mut revwalk = match repo.revwalk() {
+            Ok(w) => w,
+            Err(e) => return Err(format!("❌ 遍历提交失败: {}", e)),
+        };
+        let walk_setup = revwalk
+            .set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::TIME)
+            .and_then(|_| revwalk.push_glob("refs/heads/*"));
+        if let Err(e) = walk_setup {
+            return Err(format!("❌ 遍历提交失败: {}", e));
+        }
+        // 游离 HEAD 不在任何分支上，单独加入
+        let head_oid = repo.head().ok().and_then(|h| h.target());
+        if let Some(oid) = head_oid {
+            let _ = revwalk.push(oid);
+        }
+
+        // 提交 -> 指向它的引用名
+        let mut decorations: HashMap<git2::Oid, Vec<String>> = HashMap::new();
+        if let Some(oid) = head_oid {
+            decorations.entry(oid).or_default().push("HEAD".to_string());
+        }
+        if let Ok(refs) = repo.references() {
+            for reference in refs.flatten() {
+                let (Some(name), Ok(commit)) = (reference.shorthand(), reference.peel_to_commit()) else {
+                    continue;
+                };
+                if reference.is_branch() || reference.is_tag() || reference.is_remote() {
+                    let label = if reference.is_tag() { format!("tag: {}", name) } else { name.to_string() };
+                    decorations.entry(commit.id()).or_default().push(label);
+                }
+            }
+        }
+
+        let commits: Vec<git2::Commit> = revwalk
+            .flatten()
+            .take(count)
+            .filter_map(|oid| repo.find_commit(oid).ok())
+            .collect();
+        if commits.is_empty() {
+            return Ok("⚠️ 仓库还没有任何提交".to_string());
+        }
+
+        let topology: Vec<(git2::Oid, Vec<git2::Oid>)> =
+            commits.iter().map(|c| (c.id(), c.parent_ids().collect())).collect();
+        let lanes = assign_lanes(&topology);
+        let nodes: Vec<serde_json::Value> = commits
+            .iter()
+            .zip(&lanes)
+            .map(|(commit, (lane, parent_lanes))| {
+                serde_json::json!({
+                    "hash": commit.id().to_string(),
+                    "short": short_id(commit.id()),
+                    "subject": commit.summary().unwrap_or(""),
+                    "parents": commit.parent_ids().map(|p| p.to_string()).collect::<Vec<_>>(),
+                    "refs": decorations.remove(&commit.id()).unwrap_or_default(),
+                    "lane": lane,
+                    "parent_lanes": parent_lanes,
+                })
+            })
+            .collect();
+
+        let width = lanes
+            .iter()
+            .flat_map(|(lane, parents)| std::iter::once(lane).chain(parents))
+            .max()
+            .map_or(1, |max| max + 1);
+        let json = serde_json::to_string_pretty(&nodes).unwrap_or_default();
+        Ok(format!(
+            "🌳 提交图：{} 个提交，{} 条泳道（按拓扑顺序，最新在前）\n\n```json\n{}\n```",
+            nodes.len(),
+            width,
+            json
+        ))
+    }
+
+    /// 查看或设置提交身份
+    #[tool(description = "同时提供 name 和 email 时写入仓库本地配置的 user.name / user.email；否则返回当前生效的提交身份及其来源（仓库本地或全局配置），解决未配置身份导致提交失败的问题")]
+    async fn git_identity(&self, Parameters(param): Parameters<IdentityParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let name = param.name.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+        let email = param.email.map(|e| e.trim().to_string()).filter(|e| !e.is_empty());
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+        let config = match repo.config() {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 读取配置失败: {}", e)),
+        };
+
+        match (name, email) {
+            (Some(name), Some(email)) => {
+                let mut local = match config.open_level(git2::ConfigLevel::Local) {
+                    Ok(c) => c,
+                    Err(e) => return Err(format!("❌ 打开仓库本地配置失败: {}", e)),
+                };
+                let written = local
+                    .set_str("user.name", &name)
+                    .and_then(|_| local.set_str("user.email", &email));
+                match written {
+                    Ok(()) => Ok(format!("✅ 已为当前仓库设置提交身份: {} <{}>", name, email)),
+                    Err(e) => Err(format!("❌ 写入配置失败: {}", e)),
+                }
+            }
+            (None, None) => {
+                // 记录每项配置来自哪一层，便于判断是否被全局配置覆盖
+                let lookup = |key: &str| {
+                    let entry = config.get_entry(key).ok()?;
+                    let value = entry.value()?.to_string();
+                    let level = match entry.level() {
+                        git2::ConfigLevel::Local | git2::ConfigLevel::Worktree => "仓库本地",
+                        git2::ConfigLevel::Global | git2::ConfigLevel::XDG => "全局",
+                        git2::ConfigLevel::System | git2::ConfigLevel::ProgramData => "系统",
+                        _ => "其他",
+                    };
+                    Some((value, level))
+                };
+                let describe = |key: &str| match lookup(key) {
+                    Some((value, level)) => format!("- {}: {}（来自{}配置）", key, value, level),
+                    None => format!("- {}: 未设置", key),
+                };
+                let lines = format!("{}\n{}", describe("user.name"), describe("user.email"));
+                if lookup("user.name").is_some() && lookup("user.email").is_some() {
+                    Ok(format!("👤 当前生效的提交身份：\n\n{}", lines))
+                } else {
+                    Ok(format!(
+                        "⚠️ 提交身份不完整，提交将会失败：\n\n{}\n\n💡 传入 name 和 email 即可为当前仓库设置身份",
+                        lines
+                    ))
+                }
+            }
+            _ => Err("❌ 设置身份时 name 和 email 需要同时提供".to_string()),
+        }
+    }
+
+    /// 将单个文件恢复为指定修订中的版本
+    #[tool(description = "将单个文件恢复为指定修订（默认 HEAD）中的版本，相当于 git restore --source=<revision> [--staged]：默认改写工作区文件，staged 为 true 时只改写暂存区；修订中不存在该文件时会将其删除")]
+    async fn git_restore_file(&self, Parameters(param): Parameters<RestoreFileParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let file = require_text("file", &param.file)?;
+        let revision = param.revision.unwrap_or_else(|| "HEAD".to_string());
+        let staged = param.staged.unwrap_or(false);
+        let _guard = self.lock_repo(&repo_path).await;
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+        let Some(workdir) = repo.workdir().map(|p| p.to_path_buf()) else {
+            return Err("❌ 裸仓库没有工作区，无法恢复文件".to_string());
+        };
+        // 在读写工作区和暂存区之前校验路径，避免改动或删除仓库之外的文件
+        let rel = repo_relative_path(&workdir, &file)?;
+        let file = rel.to_string_lossy().replace('\\', "/");
+        let rel = rel.as_path();
+        let commit = match resolve_commit(&repo, &revision) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 无法解析 {}: {}", revision, e)),
+        };
+        let mut index = match repo.index() {
+            Ok(i) => i,
+            Err(e) => return Err(format!("❌ 读取暂存区失败: {}", e)),
+        };
+
+        let source = match commit.tree().map(|tree| tree.get_path(rel)) {
+            Ok(Ok(entry)) => {
+                let blob = match entry.to_object(&repo).and_then(|o| o.peel_to_blob()) {
+                    Ok(b) => b,
+                    Err(_) => return Err(format!("❌ {} 在 {} 中不是普通文件，无法恢复", file, revision)),
+                };
+                Some((entry.filemode() as u32, blob))
+            }
+            Ok(Err(_)) => None,
+            Err(e) => return Err(format!("❌ 读取 {} 的文件树失败: {}", revision, e)),
+        };
+        let target = workdir.join(rel);
+        let in_index = index.get_path(rel, 0).is_some();
+        if source.is_none() && !(if staged { in_index } else { target.exists() }) {
+            return Err(format!("❌ {} 在 {} 中不存在，工作区和暂存区也没有该文件", file, revision));
+        }
+
+        let from = format!("{} {}", short_id(commit.id()), commit.summary().unwrap_or(""));
+        let result = if staged {
+            let written = match &source {
+                Some((mode, blob)) => {
+                    let entry = git2::IndexEntry {
+                        ctime: git2::IndexTime::new(0, 0),
+                        mtime: git2::IndexTime::new(0, 0),
+                        dev: 0,
+                        ino: 0,
+                        mode: *mode,
+                        uid: 0,
+                        gid: 0,
+                        file_size: blob.size() as u32,
+                        id: blob.id(),
+                        flags: 0,
+                        flags_extended: 0,
+                        path: file.as_bytes().to_vec(),
+                    };
+                    index.add(&entry)
+                }
+                None => index.remove_path(rel),
+            };
+            if let Err(e) = written.and_then(|_| index.write()) {
+                return Err(format!("❌ 更新暂存区失败: {}", e));
+            }
+            match source {
+                Some(_) => format!("✅ 已将暂存区中的 {} 恢复为 {} 的版本，工作区未改动", file, from),
+                None => format!("✅ {} 在 {} 中不存在，已将其移出暂存区，工作区未改动", file, from),
+            }
+        } else {
+            match source {
+                Some((_, blob)) => {
+                    if let Some(parent) = target.parent() {
+                        if let Err(e) = std::fs::create_dir_all(parent) {
+                            return Err(format!("❌ 创建目录失败: {}", e));
+                        }
+                    }
+                    if let Err(e) = std::fs::write(&target, blob.content()) {
+                        return Err(format!("❌ 写入文件失败: {}", e));
+                    }
+                    format!("✅ 已将工作区的 {} 恢复为 {} 的版本，暂存区未改动", file, from)
+                }
+                None => {
+                    if let Err(e) = std::fs::remove_file(&target) {
+                        return Err(format!("❌ 删除文件失败: {}", e));
+                    }
+                    format!("✅ {} 在 {} 中不存在，已从工作区删除，暂存区未改动", file, from)
+                }
+            }
+        };
+        Ok(result)
+    }
+
+    /// 查看影响提交行为的配置
+    #[tool(description = "以 JSON 返回影响提交行为的生效配置：user.name、user.email、commit.gpgsign、commit.template、core.hooksPath，以及 pre-commit / commit-msg 钩子是否存在，便于提交前发现身份缺失、签名或钩子等问题")]
+    async fn git_commit_env(&self, Parameters(param): Parameters<PathParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+        let config = match repo.config() {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 读取配置失败: {}", e)),
+        };
+        let value = |key: &str| config.get_string(key).ok();
+
+        let name = value("user.name");
+        let email = value("user.email");
+        let gpgsign = config.get_bool("commit.gpgsign").unwrap_or(false);
+        let hooks = hooks_dir(&repo);
+        let pre_commit = hooks.join("pre-commit").is_file();
+        let commit_msg = hooks.join("commit-msg").is_file();
+
+        let env = serde_json::json!({
+            "user.name": name,
+            "user.email": email,
+            "commit.gpgsign": gpgsign,
+            "commit.template": value("commit.template"),
+            "core.hooksPath": value("core.hooksPath"),
+            "hooks_dir": hooks.display().to_string(),
+            "pre_commit_hook": pre_commit,
+            "commit_msg_hook": commit_msg,
+        });
+
+        let mut notes = Vec::new();
+        if name.is_none() || email.is_none() {
+            notes.push("⚠️ 提交身份不完整，提交将会失败，可使用 git_identity 设置".to_string());
+        }
+        if gpgsign {
+            notes.push("🔏 已开启 commit.gpgsign，提交时需要可用的签名密钥".to_string());
+        }
+        if pre_commit || commit_msg {
+            notes.push("🪝 提交时会执行钩子，可通过 no_verify 跳过".to_string());
+        }
+        let json = serde_json::to_string_pretty(&env).unwrap_or_default();
+        let mut result = format!("⚙️ 提交相关配置：\n\n```json\n{}\n```", json);
+        if !notes.is_empty() {
+            result.push_str(&format!("\n\n{}", notes.join("\n")));
+        }
+        Ok(result)
+    }
+
+    /// 统计当前分支的提交数
+    #[tool(description = "统计 HEAD 可达的提交数量，可用修订或日期限定起点，适合生成构建号或跟踪进度，比拉取完整日志再计数更高效")]
+    async fn git_commit_count(&self, Parameters(param): Parameters<CommitCountParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let since = param.since.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+        let head = match repo.head().and_then(|h| h.peel_to_commit()) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 无法读取 HEAD: {}", e)),
+        };
+        let mut revwalk = match repo.revwalk() {
+            Ok(w) => w,
+            Err(e) => return Err(format!("❌ 遍历提交失败: {}", e)),
+        };
+        if let Err(e) = revwalk.push(head.id()) {
+            return Err(format!("❌ 遍历提交失败: {}", e));
+        }
+
+        // 先按修订解析，失败再按日期解析
+        let (count, range) = match since {
+            None => (revwalk.count(), "HEAD".to_string()),
+            Some(since) => match resolve_commit(&repo, &since) {
+                Ok(base) => {
+                    if let Err(e) = revwalk.hide(base.id()) {
+                        return Err(format!("❌ 遍历提交失败: {}", e));
+                    }
+                    (revwalk.count(), format!("{}..HEAD", since))
+                }
+                Err(_) => {
+                    let date = parse_commit_date(&since).map_err(|_| {
+                        format!("❌ 无法将 {} 解析为修订或日期（YYYY-MM-DD[ HH:MM[:SS]][ +08:00]）", since)
+                    })?;
+                    let count = revwalk
+                        .flatten()
+                        .filter_map(|oid| repo.find_commit(oid).ok())
+                        .filter(|c| c.time().seconds() >= date.seconds())
+                        .count();
+                    (count, format!("HEAD 自 {} 以来", format_time(date)))
+                }
+            },
+        };
+
+        Ok(format!("🔢 {}\n\n统计范围：{}（HEAD = {}）", count, range, short_id(head.id())))
+    }
+
+    /// 拉取远程后检查各分支同步状态
+    #[tool(description = "先 fetch 远程（只更新远程跟踪分支，受 GIT_MCP_COMMAND_TIMEOUT 超时限制），再以 JSON 报告每个跟踪该远程的本地分支是 up_to_date、ahead、behind 还是 diverged")]
+    async fn git_sync_check(&self, Parameters(param): Parameters<SyncCheckParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let remote_name = param.remote.unwrap_or_else(|| "origin".to_string());
+
+        // fetch 在阻塞线程中执行，超时后直接返回；进度回调同时取消仍在进行的传输
+        let timeout = command_timeout();
+        let deadline = std::time::Instant::now() + timeout;
+        let (fetch_path, fetch_remote) = (repo_path.clone(), remote_name.clone());
+        let fetch = tokio::task::spawn_blocking(move || -> Result<(), String> {
+            let repo = Repository::open(&fetch_path).map_err(|e| format!("❌ 无法打开 Git 仓库: {}", e))?;
+            let mut remote = repo
+                .find_remote(&fetch_remote)
+                .map_err(|e| format!("❌ 找不到远程仓库 {}: {}", fetch_remote, e))?;
+            let attempted = Default::default();
+            let mut callbacks = credential_callbacks(&attempted);
+            callbacks.transfer_progress(move |_| std::time::Instant::now() < deadline);
+            let mut fetch_opts = git2::FetchOptions::new();
+            fetch_opts.remote_callbacks(callbacks);
+            remote
+                .fetch::<&str>(&[], Some(&mut fetch_opts), None)
+                .map_err(|e| format!("❌ fetch 失败: {}", remote_error(&e, &attempted)))
+        });
+        match tokio::time::timeout(timeout, fetch).await {
+            Ok(Ok(Ok(()))) => {}
+            Ok(Ok(Err(e))) => return Err(e),
+            Ok(Err(e)) => return Err(format!("❌ fetch 失败: {}", e)),
+            Err(_) => {
+                return Err(format!(
+                    "❌ fetch {} 超过 {} 秒未完成，已放弃（可通过 GIT_MCP_COMMAND_TIMEOUT 调整）",
+                    remote_name,
+                    timeout.as_secs()
+                ))
+            }
+        }
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+        let branches = match repo.branches(Some(git2::BranchType::Local)) {
+            Ok(b) => b,
+            Err(e) => return Err(format!("❌ 读取分支列表失败: {}", e)),
+        };
+
+        let prefix = format!("refs/remotes/{}/", remote_name);
+        let mut entries = Vec::new();
+        for (branch, _) in branches.flatten() {
+            let (Some(name), Some(refname), Some(local_oid)) = (
+                branch.name().ok().flatten(),
+                branch.get().name(),
+                branch.get().target(),
+            ) else {
+                continue;
+            };
+            let Some(upstream_ref) = repo
+                .branch_upstream_name(refname)
+                .ok()
+                .and_then(|u| u.as_str().map(|s| s.to_string()))
+                .filter(|u| u.starts_with(&prefix))
+            else {
+                continue;
+            };
+            let upstream = upstream_ref.trim_start_matches("refs/remotes/").to_string();
+
+            let entry = match repo.refname_to_id(&upstream_ref) {
+                Ok(upstream_oid) => {
+                    let (ahead, behind) = repo.graph_ahead_behind(local_oid, upstream_oid).unwrap_or((0, 0));
+                    let state = match (ahead, behind) {
+                        (0, 0) => "up_to_date",
+                        (_, 0) => "ahead",
+                        (0, _) => "behind",
+                        _ => "diverged",
+                    };
+                    serde_json::json!({
+                        "branch": name,
+                        "upstream": upstream,
+                        "state": state,
+                        "ahead": ahead,
+                        "behind": behind,
+                    })
+                }
+                // fetch 后远程跟踪分支仍不存在，说明远程已删除该分支
+                Err(_) => serde_json::json!({
+                    "branch": name,
+                    "upstream": upstream,
+                    "state": "gone",
+                    "ahead": null,
+                    "behind": null,
+                }),
+            };
+            entries.push(entry);
+        }
+
+        if entries.is_empty() {
+            return Ok(format!("⚠️ 已 fetch {}，但没有跟踪该远程的本地分支", remote_name));
+        }
+
+        let in_sync = entries.iter().filter(|e| e["state"] == "up_to_date").count();
+        let json = serde_json::to_string_pretty(&entries).unwrap_or_default();
+        let header = if in_sync == entries.len() {
+            format!("✅ 已 fetch {}，{} 个跟踪分支全部同步", remote_name, entries.len())
+        } else {
+            format!(
+                "⚠️ 已 fetch {}，{} 个跟踪分支中有 {} 个未同步",
+                remote_name,
+                entries.len(),
+                entries.len() - in_sync
+            )
+        };
+        Ok(format!("{}\n\n```json\n{}\n```", header, json))
+    }
+
+    /// 查看和编辑 .gitignore
+    #[tool(description = "管理仓库根目录的 .gitignore：list 列出现有规则，add 追加规则（已存在时跳过），check 检查某个路径是否被忽略规则命中（综合 .gitignore、.git/info/exclude 和全局配置）")]
+    async fn git_ignore(&self, Parameters(param): Parameters<IgnoreParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let action = param.action.trim().to_lowercase();
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+        let Some(workdir) = repo.workdir().map(|p| p.to_path_buf()) else {
+            return Err("❌ 裸仓库没有工作区，无法管理 .gitignore".to_string());
+        };
+        let ignore_file = workdir.join(".gitignore");
+        // 追加规则是先读后写，读取前就持有仓库锁，避免并发追加时互相覆盖
+        let _guard = match action.as_str() {
+            "add" => Some(self.lock_repo(&repo_path).await),
+            _ => None,
+        };
+        let content = std::fs::read_to_string(&ignore_file).unwrap_or_default();
+        let rules: Vec<&str> = content
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect();
+
+        match action.as_str() {
+            "list" => {
+                if rules.is_empty() {
+                    return Ok("⚠️ 仓库根目录的 .gitignore 不存在或没有规则".to_string());
+                }
+                let list = rules.iter().map(|rule| format!("  {}", rule)).collect::<Vec<_>>().join("\n");
+                Ok(format!("📄 .gitignore 共 {} 条规则：\n\n{}", rules.len(), list))
+            }
+            "add" => {
+                let pattern = require_text("pattern", param.pattern.as_deref().unwrap_or(""))?;
+                if rules.contains(&pattern.as_str()) {
+                    return Ok(format!("⚠️ .gitignore 中已存在规则 {}，无需重复添加", pattern));
+                }
+                let mut updated = content.clone();
+                if !updated.is_empty() && !updated.ends_with('\n') {
+                    updated.push('\n');
+                }
+                updated.push_str(&pattern);
+                updated.push('\n');
+                if let Err(e) = std::fs::write(&ignore_file, updated) {
+                    return Err(format!("❌ 写入 .gitignore 失败: {}", e));
+                }
+                Ok(format!("✅ 已将 {} 追加到 .gitignore\n\n💡 已被跟踪的文件不受忽略规则影响，如需停止跟踪请执行: git rm --cached <文件>", pattern))
+            }
+            "check" => {
+                let target = require_text("pattern", param.pattern.as_deref().unwrap_or(""))?;
+                let ignored = match repo.is_path_ignored(&target) {
+                    Ok(ignored) => ignored,
+                    Err(e) => return Err(format!("❌ 检查忽略规则失败: {}", e)),
+                };
+                let tracked = repo
+                    .index()
+                    .ok()
+                    .and_then(|index| index.get_path(std::path::Path::new(&target), 0))
+                    .is_some();
+                Ok(match (ignored, tracked) {
+                    (true, true) => format!("⚠️ {} 命中忽略规则，但已被 Git 跟踪，其改动仍会出现在状态中", target),
+                    (true, false) => format!("🙈 {} 被忽略规则命中，不会出现在 git status 中", target),
+                    (false, true) => format!("✅ {} 未被忽略，且已被 Git 跟踪", target),
+                    (false, false) => format!("✅ {} 未被忽略", target),
+                })
+            }
+            other => Err(format!("❌ 不支持的操作 {}，可选 list、add、check", other)),
+        }
+    }
+
+    /// 按提交信息查找提交
+    #[tool(description = "用正则表达式搜索 HEAD 可达提交的标题和正文，以 JSON 返回匹配提交的完整哈希、短哈希、标题、作者和时间，结果可直接用于 git revert 等后续操作")]
+    async fn git_find_commits(&self, Parameters(param): Parameters<FindCommitsParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let pattern = require_text("pattern", &param.pattern)?;
+        let limit = param.count.unwrap_or(20).max(1) as usize;
+        let re = match regex::Regex::new(&pattern) {
+            Ok(re) => re,
+            Err(e) => return Err(format!("❌ 正则表达式 {} 无效: {}", pattern, e)),
+        };
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+        let head = match repo.head().and_then(|h| h.peel_to_commit()) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 无法读取 HEAD: {}", e)),
+        };
+        let mut revwalk = match repo.revwalk() {
+            Ok(w) => w,
+            Err(e) => return Err(format!("❌ 遍历提交失败: {}", e)),
+        };
+        if let Err(e) = revwalk.push(head.id()) {
+            return Err(format!("❌ 遍历提交失败: {}", e));
+        }
+
+        let mut scanned = 0;
+        let mut matches = Vec::new();
+        for oid in revwalk.flatten() {
+            let Ok(commit) = repo.find_commit(oid) else {
+                continue;
+            };
+            scanned += 1;
+            if !re.is_match(commit.message().unwrap_or("")) {
+                continue;
+            }
+            matches.push(serde_json::json!({
+                "hash": oid.to_string(),
+                "short": short_id(oid),
+                "subject": commit.summary().unwrap_or(""),
+                "author": commit.author().name().unwrap_or(""),
+                "date": format_time(commit.time()),
+                "merge": commit.parent_count() > 1,
+            }));
+            if matches.len() >= limit {
+                break;
+            }
+        }
+
+        if matches.is_empty() {
+            return Ok(format!("⚠️ 在 {} 个提交中没有找到匹配 {} 的提交", scanned, pattern));
+        }
+        let json = serde_json::to_string_pretty(&matches).unwrap_or_default();
+        let mut result = format!(
+            "🔍 找到 {} 个匹配 {} 的提交（已扫描 {} 个）：\n\n```json\n{}\n```",
+            matches.len(),
+            pattern,
+            scanned,
+            json
+        );
+        if matches.iter().any(|m| m["merge"] == true) {
+            result.push_str("\n\n💡 合并提交回退时需要指定主线，例如: git revert -m 1 <hash>");
+        }
+        Ok(result)
+    }
+
+    /// 判断一个提交是否是另一个提交的祖先
+    #[tool(description = "判断 commit 是否是 of 的祖先（相同提交也视为祖先，与 git merge-base --is-ancestor 一致），以 JSON 返回布尔结果，可用于判断提交是否已合并、本地分支是否落后")]
+    async fn git_is_ancestor(&self, Parameters(param): Parameters<IsAncestorParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let commit = require_text("commit", &param.commit)?;
+        let of = require_text("of", &param.of)?;
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+        let ancestor = match resolve_commit(&repo, &commit) {
+            Ok(c) => c.id(),
+            Err(e) => return Err(format!("❌ 无法解析 {}: {}", commit, e)),
+        };
+        let descendant = match resolve_commit(&repo, &of) {
+            Ok(c) => c.id(),
+            Err(e) => return Err(format!("❌ 无法解析 {}: {}", of, e)),
+        };
+        let is_ancestor = ancestor == descendant
+            || match repo.graph_descendant_of(descendant, ancestor) {
+                Ok(v) => v,
+                Err(e) => return Err(format!("❌ 比较提交失败: {}", e)),
+            };
+
+        let json = serde_json::to_string_pretty(&serde_json::json!({
+            "ancestor": is_ancestor,
+            "commit": ancestor.to_string(),
+            "of": descendant.to_string(),
+        }))
+        .unwrap_or_default();
+        let header = if is_ancestor {
+            format!("✅ {}（{}）是 {}（{}）的祖先", commit, short_id(ancestor), of, short_id(descendant))
+        } else {
+            format!("⚠️ {}（{}）不是 {}（{}）的祖先", commit, short_id(ancestor), of, short_id(descendant))
+        };
+        Ok(format!("{}\n\n```json\n{}\n```", header, json))
+    }
+
+    /// 将最近的多个提交合并为一个
+    #[tool(description = "将当前分支最近 count 个提交合并为一个新提交（相当于 git reset --soft HEAD~count 后重新提交），暂存区中未提交的改动保持不变；遇到根提交、合并提交或已推送到远程的提交时拒绝执行")]
+    async fn git_squash(&self, Parameters(param): Parameters<SquashParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        if param.count < 2 {
+            return Err("❌ count 至少为 2，才能将多个提交合并为一个".to_string());
+        }
+        let _guard = self.lock_repo(&repo_path).await;
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+        if repo.state() != RepositoryState::Clean {
+            return Err("❌ 仓库有进行中的合并、变基等操作，请先完成或调用 git_abort".to_string());
+        }
+        let head = match repo.head().and_then(|h| h.peel_to_commit()) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 无法读取 HEAD 提交: {}", e)),
+        };
+
+        // 从 HEAD 沿第一父提交向前收集，途中遇到根提交或合并提交即拒绝
+        let mut squashed = Vec::new();
+        let mut current = head.clone();
+        for _ in 0..param.count {
+            if current.parent_count() > 1 {
+                return Err(format!(
+                    "❌ 提交 {} {} 是合并提交，不能跨合并提交压缩",
+                    short_id(current.id()),
+                    current.summary().unwrap_or("")
+                ));
+            }
+            let parent = match current.parent(0) {
+                Ok(p) => p,
+                Err(_) => {
+                    return Err(format!(
+                        "❌ 当前分支只有 {} 个提交，无法越过根提交合并 {} 个",
+                        squashed.len() + 1,
+                        param.count
+                    ));
+                }
+            };
+            squashed.push(current);
+            current = parent;
+        }
+        let base = current;
+        let oldest = &squashed[squashed.len() - 1];
+        if let Some(remote_branch) = pushed_to_remote(&repo, oldest.id()) {
+            return Err(format!(
+                "❌ 提交 {} 已推送到 {}，压缩会改写已推送的历史，已拒绝执行",
+                short_id(oldest.id()),
+                remote_branch
+            ));
+        }
+
+        // 按从旧到新的顺序列出被合并的标题
+        let subjects: Vec<String> = squashed
+            .iter()
+            .rev()
+            .map(|c| c.summary().unwrap_or("").to_string())
+            .collect();
+        let message = match param.message.as_deref().map(str::trim).filter(|m| !m.is_empty()) {
+            Some(message) => message.to_string(),
+            None => {
+                let rest = subjects[1..].iter().map(|s| format!("- {}", s)).collect::<Vec<_>>().join("\n");
+                format!("{}\n\n{}", subjects[0], rest)
+            }
+        };
+        let message = match attribution_trailer()? {
+            Some(trailer) => append_trailer(&message, &trailer),
+            None => message,
+        };
+
+        let signature = match repo.signature() {
+            Ok(s) => s,
+            Err(e) => return Err(format!("❌ 无法获取提交者身份（请配置 user.name / user.email）: {}", e)),
+        };
+        let tree = match head.tree() {
+            Ok(t) => t,
+            Err(e) => return Err(format!("❌ 读取 HEAD 文件树失败: {}", e)),
+        };
+        let new_oid = match repo.commit(None, &signature, &signature, &message, &tree, &[&base]) {
+            Ok(oid) => oid,
+            Err(e) => return Err(format!("❌ 创建提交失败: {}", e)),
+        };
+        let reflog = format!("git-mcp: squash {} commits", param.count);
+        let moved = match repo.head() {
+            Ok(mut head_ref) if head_ref.is_branch() => head_ref.set_target(new_oid, &reflog).map(|_| ()),
+            _ => repo.set_head_detached(new_oid),
+        };
+        if let Err(e) = moved {
+            return Err(format!("❌ 更新 HEAD 失败: {}", e));
+        }
+
+        let list = subjects.iter().map(|s| format!("  - {}", s)).collect::<Vec<_>>().join("\n");
+        Ok(format!(
+            "✅ 已将 {} 个提交合并为 {}（基于 {}）\n\n被合并的提交：\n{}\n\n💡 原 HEAD 为 {}，如需撤销可执行: git reset --soft {}",
+            param.count,
+            short_id(new_oid),
+            short_id(base.id()),
+            list,
+            short_id(head.id()),
+            short_id(head.id())
+        ))
+    }
+
+    /// 列出标签及其说明和日期
+    #[tool(description = "列出仓库中的标签（可按通配符过滤）：附注标签显示创建者、时间和标签说明，轻量标签显示所指提交的时间和标题；版本号标签按版本从新到旧排在前面，其余按时间从新到旧排列")]
+    async fn git_tag_list(&self, Parameters(param): Parameters<TagListParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let pattern = param.pattern.map(|p| p.trim().to_string()).filter(|p| !p.is_empty());
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+        let names = match repo.tag_names(pattern.as_deref()) {
+            Ok(t) => t,
+            Err(e) => return Err(format!("❌ 读取标签失败: {}", e)),
+        };
+
+        // (版本号, 时间, 展示文本)
+        let mut tags = Vec::new();
+        for name in names.iter().flatten() {
+            let Ok(object) = repo.revparse_single(&format!("refs/tags/{}", name)) else {
+                continue;
+            };
+            let commit = object.peel_to_commit().ok();
+            let target = match &commit {
+                Some(c) => format!("{} {}", short_id(c.id()), c.summary().unwrap_or("")),
+                None => format!("{}（非提交对象）", short_id(object.id())),
+            };
+            let version = parse_semver_tag(name, "v").or_else(|| parse_semver_tag(name, ""));
+            let (time, text) = match object.as_tag() {
+                Some(tag) => {
+                    let tagger = tag.tagger();
+                    let time = tagger.as_ref().map(|t| t.when()).or_else(|| commit.as_ref().map(|c| c.time()));
+                    let who = tagger
+                        .as_ref()
+                        .map(|t| format!("{} <{}>", t.name().unwrap_or(""), t.email().unwrap_or("")))
+                        .unwrap_or_else(|| "未知".to_string());
+                    let message = tag.message().unwrap_or("").trim();
+                    let mut text = format!(
+                        "🏷️ {}（附注）\n   时间: {}\n   创建者: {}\n   指向: {}",
+                        name,
+                        time.map(format_time).unwrap_or_default(),
+                        who,
+                        target
+                    );
+                    if !message.is_empty() && message != name {
+                        let indented = message
+                            .lines()
+                            .map(|line| if line.trim().is_empty() { String::new() } else { format!("         {}", line) })
+                            .collect::<Vec<_>>()
+                            .join("\n");
+                        text.push_str(&format!("\n   说明: {}", indented.trim_start()));
+                    }
+                    (time, text)
+                }
+                None => {
+                    let time = commit.as_ref().map(|c| c.time());
+                    let text = format!(
+                        "🔖 {}（轻量）\n   时间: {}\n   指向: {}",
+                        name,
+                        time.map(format_time).unwrap_or_default(),
+                        target
+                    );
+                    (time, text)
+                }
+            };
+            tags.push((version, time.map(|t| t.seconds()).unwrap_or(0), text));
+        }
+
+        if tags.is_empty() {
+            return Ok(match pattern {
+                Some(pattern) => format!("⚠️ 没有匹配 {} 的标签", pattern),
+                None => "⚠️ 仓库中没有标签".to_string(),
+            });
+        }
+        tags.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.cmp(&a.1)));
+        let list = tags.into_iter().map(|(_, _, text)| text).collect::<Vec<_>>();
+        Ok(format!("🏷️ 共 {} 个标签：\n\n{}", list.len(), list.join("\n\n")))
+    }
+
+    /// 找出应当加入 .gitignore 的未跟踪文件
+    #[tool(description = "扫描未跟踪文件，找出 node_modules、target、dist、__pycache__、*.log、.DS_Store 等常见的依赖、构建产物和临时文件，按分类给出建议加入 .gitignore 的规则，避免误提交")]
+    async fn suggest_gitignore(&self, Parameters(param): Parameters<PathParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+
+        // 不展开未跟踪目录，node_modules 等目录整体作为一项返回，避免逐个遍历其中的文件
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(true).recurse_untracked_dirs(false);
+        let statuses = match repo.statuses(Some(&mut opts)) {
+            Ok(s) => s,
+            Err(e) => return Err(format!("❌ 获取状态失败: {}", e)),
+        };
+
+        // 规则 -> 命中的未跟踪路径
+        let mut hits: HashMap<&str, Vec<String>> = HashMap::new();
+        let mut pending: Vec<String> = statuses
+            .iter()
+            .filter(|e| e.status().is_wt_new())
+            .filter_map(|e| e.path().map(|p| p.to_string()))
+            .collect();
+        let workdir = repo.workdir().map(|p| p.to_path_buf()).unwrap_or_default();
+        while let Some(path) = pending.pop() {
+            if let Some((_, rule)) = ignore_suggestion(&path) {
+                hits.entry(rule).or_default().push(path);
+                continue;
+            }
+            // 未命中的未跟踪目录继续向下查找，例如 web/node_modules/
+            if path.ends_with('/') {
+                if let Ok(entries) = std::fs::read_dir(workdir.join(&path)) {
+                    for entry in entries.flatten() {
+                        let name = entry.file_name().to_string_lossy().to_string();
+                        let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+                        pending.push(format!("{}{}{}", path, name, if is_dir { "/" } else { "" }));
+                    }
+                }
+            }
+        }
+
+        if hits.is_empty() {
+            return Ok("✅ 未跟踪文件中没有发现常见的应忽略文件".to_string());
+        }
+
+        let mut sections = Vec::new();
+        let mut categories: Vec<&str> = IGNORE_SUGGESTIONS.iter().map(|(category, _)| *category).collect();
+        categories.dedup();
+        for category in categories {
+            let lines: Vec<String> = IGNORE_SUGGESTIONS
+                .iter()
+                .filter(|(c, _)| *c == category)
+                .filter_map(|(_, rule)| {
+                    let paths = hits.get_mut(rule)?;
+                    paths.sort();
+                    let examples = paths.iter().take(3).cloned().collect::<Vec<_>>().join(", ");
+                    let more = if paths.len() > 3 { format!(" 等 {} 项", paths.len()) } else { String::new() };
+                    Some(format!("  {}  ← {}{}", rule, examples, more))
+                })
+                .collect();
+            if !lines.is_empty() {
+                sections.push(format!("【{}】\n{}", category, lines.join("\n")));
+            }
+        }
+        Ok(format!(
+            "🧹 发现 {} 条建议加入 .gitignore 的规则：\n\n{}\n\n💡 可调用 git_ignore（action: add）逐条添加",
+            hits.len(),
+            sections.join("\n\n")
+        ))
+    }
+
+    /// 试合并并预览冲突文件
+    #[tool(description = "在内存中将 branch 试合并到当前分支，以 JSON 返回会冲突的文件（含冲突类型）、会被改动的文件，以及与合并涉及文件重叠的未提交改动；完全只读，不修改工作区、暂存区和任何引用")]
+    async fn git_merge_preview(&self, Parameters(param): Parameters<MergePreviewParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let branch = require_text("branch", &param.branch)?;
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+        let ours = match repo.head().and_then(|h| h.peel_to_commit()) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 无法读取 HEAD: {}", e)),
+        };
+        let theirs = match resolve_commit(&repo, &branch) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 无法解析 {}: {}", branch, e)),
+        };
+        let merge_base = match repo.merge_base(ours.id(), theirs.id()) {
+            Ok(oid) => oid,
+            Err(_) => return Err(format!("❌ 当前分支与 {} 没有共同祖先，无法预览合并", branch)),
+        };
+        if merge_base == theirs.id() {
+            return Ok(format!("✅ 当前分支已包含 {} 的全部提交，无需合并", branch));
+        }
+
+        let index = match repo.merge_commits(&ours, &theirs, None) {
+            Ok(i) => i,
+            Err(e) => return Err(format!("❌ 试合并失败: {}", e)),
+        };
+        let conflicts = merge_conflicts(&index);
+        let ours_tree = match ours.tree() {
+            Ok(t) => t,
+            Err(e) => return Err(format!("❌ 读取 HEAD 文件树失败: {}", e)),
+        };
+        let changed: Vec<String> = match repo.diff_tree_to_index(Some(&ours_tree), Some(&index), None) {
+            Ok(diff) => diff
+                .deltas()
+                .filter(|d| d.status() != git2::Delta::Conflicted)
+                .filter_map(|d| d.new_file().path().or_else(|| d.old_file().path()))
+                .map(|p| p.display().to_string())
+                .filter(|p| !conflicts.iter().any(|(c, _)| c == p))
+                .collect(),
+            Err(e) => return Err(format!("❌ 计算合并差异失败: {}", e)),
+        };
+
+        // 与合并涉及文件重叠的本地改动会让真正的合并直接失败
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(true).recurse_untracked_dirs(true);
+        let dirty: Vec<String> = match repo.statuses(Some(&mut opts)) {
+            Ok(statuses) => statuses
+                .iter()
+                .filter(|e| !e.status().is_ignored())
+                .filter_map(|e| e.path().map(|p| p.to_string()))
+                .filter(|p| changed.contains(p) || conflicts.iter().any(|(c, _)| c == p))
+                .collect(),
+            Err(e) => return Err(format!("❌ 获取状态失败: {}", e)),
+        };
+
+        let fast_forward = merge_base == ours.id();
+        let json = serde_json::to_string_pretty(&serde_json::json!({
+            "branch": branch,
+            "merge_base": merge_base.to_string(),
+            "fast_forward": fast_forward,
+            "conflicts": conflicts
+                .iter()
+                .map(|(path, kind)| serde_json::json!({ "path": path, "kind": kind }))
+                .collect::<Vec<_>>(),
+            "changed_files": changed,
+            "dirty_overlap": dirty,
+        }))
+        .unwrap_or_default();
+
+        let mut header = if conflicts.is_empty() {
+            format!(
+                "✅ 合并 {} 不会产生冲突，将改动 {} 个文件{}",
+                branch,
+                changed.len(),
+                if fast_forward { "（可快进）" } else { "" }
+            )
+        } else {
+            format!("⚠️ 合并 {} 会在 {} 个文件上产生冲突", branch, conflicts.len())
+        };
+        if !dirty.is_empty() {
+            header.push_str(&format!(
+                "\n⚠️ {} 个文件有未提交的改动且会被合并修改，合并前请先提交或储藏",
+                dirty.len()
+            ));
+        }
+        Ok(format!("{}\n\n```json\n{}\n```", header, json))
+    }
+
+    /// 把遗漏的文件补进最近一次提交
+    #[tool(description = "将指定文件补充进 HEAD 提交并保留原提交信息（git commit --amend --no-edit），只包含列出的文件，暂存区中的其他改动保持不变；HEAD 是合并提交或已推送到远程时拒绝执行")]
+    async fn git_add_to_last_commit(&self, Parameters(param): Parameters<AddToLastCommitParam>) -> Result<String, String> {
+        let repo_path = resolve_repo_path(param.path);
+        let files: Vec<String> = param
+            .files
+            .iter()
+            .map(|f| f.trim().to_string())
+            .filter(|f| !f.is_empty())
+            .collect();
+        if files.is_empty() {
+            return Err("❌ 参数 files 不能为空".to_string());
+        }
+        let _guard = self.lock_repo(&repo_path).await;
+
+        let repo = match Repository::open(&repo_path) {
+            Ok(r) => r,
+            Err(e) => return Err(format!("❌ 无法打开 Git 仓库: {}", e)),
+        };
+        if repo.state() != RepositoryState::Clean {
+            return Err("❌ 仓库有进行中的合并、变基等操作，请先完成或调用 git_abort".to_string());
+        }
+        let head = match repo.head().and_then(|h| h.peel_to_commit()) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 无法读取 HEAD 提交: {}", e)),
+        };
+        if head.parent_count() > 1 {
+            return Err(format!("❌ HEAD {} 是合并提交，不支持补充文件", short_id(head.id())));
+        }
+        if let Some(remote_branch) = pushed_to_remote(&repo, head.id()) {
+            return Err(format!(
+                "❌ 提交 {} 已推送到 {}，修改它会导致与远程历史分叉，已拒绝执行",
+                short_id(head.id()),
+                remote_branch
+            ));
+        }
+
+        let changed: Vec<String> = match group_diff(&repo, &files) {
+            Ok(diff) => diff
+                .deltas()
+                .filter_map(|d| d.new_file().path().or_else(|| d.old_file().path()))
+                .map(|p| p.display().to_string())
+                .collect(),
+            Err(e) => return Err(format!("❌ 计算差异失败: {}", e)),
+        };
+        if changed.is_empty() {
+            return Ok(format!("⚠️ 指定文件与 HEAD {} 中的内容一致，无需补充", short_id(head.id())));
+        }
+
+        let mut add_args = vec!["add".to_string(), "--".to_string()];
+        add_args.extend(files.iter().cloned());
+        match run_git(&repo_path, &add_args) {
+            Ok(o) if o.status.success() => {}
+            Ok(o) => return Err(format!("❌ git add 失败: {}", String::from_utf8_lossy(&o.stderr))),
+            Err(e) => return Err(format!("❌ 执行 git add 失败: {}", e)),
+        }
+
+        // --only 使提交只包含 HEAD 加上列出的文件，不会带上暂存区中的其他改动
+        let mut commit_args: Vec<String> =
+            ["commit", "--amend", "--no-edit", "--only", "--"].iter().map(|s| s.to_string()).collect();
+        commit_args.extend(files.iter().cloned());
+        match run_git(&repo_path, &commit_args) {
+            Ok(o) if o.status.success() => {}
+            Ok(o) => {
+                return Err(format!(
+                    "❌ git commit --amend 失败: {}{}",
+                    String::from_utf8_lossy(&o.stdout),
+                    String::from_utf8_lossy(&o.stderr)
+                ));
+            }
+            Err(e) => return Err(format!("❌ 执行 git commit --amend 失败: {}", e)),
+        }
+
+        let new_head = match repo.head().and_then(|h| h.peel_to_commit()) {
+            Ok(c) => c,
+            Err(e) => return Err(format!("❌ 无法读取新的 HEAD 提交: {}", e)),
+        };
+        let list = changed.iter().map(|f| format!("  - {}", f)).collect::<Vec<_>>().join("\n");
+        Ok(format!(
+            "✅ 已将 {} 个文件补充进提交: {} → {} {}\n\n{}",
+            changed.len(),
+            short_id(head.id()),
+            short_id(new_head.id()),
+            new_head.summary().unwrap_or(""),
+            list
+        ))
+    }
+}
+
+#[tool_handler]
+impl ServerHandler for GitMcpServer {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            protocol_version: ProtocolVersion::V_2025_03_26,
+            instructions: Some("Git MCP Server - 提供 Git 操作工具，支持查看状态、生成规范提交信息、执行提交等功能".to_string()),
+            capabilities: ServerCapabilities::builder().enable_tools().build(),
+            ..Default::default()
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // stdout 用于 MCP 协议通信，提示只能写到 stderr
+    if !git_available() {
+        eprintln!("⚠️ 系统未安装 git 或不在 PATH 中，依赖 git 命令行的工具将不可用，基于 libgit2 的工具不受影响");
+    }
+    let server = GitMcpServer::new().serve(stdio()).await?;
+    server.waiting().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// 测试用的临时仓库，离开作用域时删除目录
+    struct TestRepo {
+        dir: std::path::PathBuf,
+    }
+
+    impl TestRepo {
+        fn new() -> Self {
+            static COUNTER: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+            let id = COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let dir = std::env::temp_dir().join(format!("git-mcp-test-{}-{}", std::process::id(), id));
+            let _ = std::fs::remove_dir_all(&dir);
+
+            let mut opts = git2::RepositoryInitOptions::new();
+            opts.initial_head("main").mkpath(true);
+            let repo = Repository::init_opts(&dir, &opts).unwrap();
+            let mut config = repo.config().unwrap();
+            config.set_str("user.name", "Tester").unwrap();
+            config.set_str("user.email", "tester@example.com").unwrap();
+            config.set_bool("commit.gpgsign", false).unwrap();
+            Self { dir }
+        }
+
+        fn path(&self) -> String {
+            self.dir.display().to_string()
+        }
+
+        fn write(&self, file: &str, content: &str) {
+            let target = self.dir.join(file);
+            std::fs::create_dir_all(target.parent().unwrap()).unwrap();
+            std::fs::write(target, content).unwrap();
+        }
+
+        fn git(&self, args: &[&str]) -> String {
+            let output = Command::new("git").args(args).current_dir(&self.dir).output().unwrap();
+            assert!(output.status.success(), "git {:?}: {}", args, String::from_utf8_lossy(&output.stderr));
+            String::from_utf8_lossy(&output.stdout).trim().to_string()
+        }
+
+        fn commit(&self, file: &str, content: &str, message: &str) -> git2::Oid {
+            self.write(file, content);
+            self.git(&["add", "--", file]);
+            self.git(&["commit", "-q", "-m", message]);
+            self.head()
+        }
+
+        fn head(&self) -> git2::Oid {
+            Repository::open(&self.dir).unwrap().head().unwrap().target().unwrap()
+        }
+
+        fn repo(&self) -> Repository {
+            Repository::open(&self.dir).unwrap()
+        }
+
+        /// 以 upstream 为 origin 创建仓库，当前分支跟踪 origin/main
+        fn clone_of(upstream: &TestRepo) -> Self {
+            let repo = Self::new();
+            repo.git(&["remote", "add", "origin", &upstream.path()]);
+            repo.git(&["fetch", "-q", "origin"]);
+            repo.git(&["reset", "-q", "--hard", "origin/main"]);
+            repo.git(&["branch", "-q", "--set-upstream-to=origin/main"]);
+            repo
+        }
+
+        fn read(&self, file: &str) -> String {
+            std::fs::read_to_string(self.dir.join(file)).unwrap()
+        }
+
+        /// 执行预期可能失败的 git 命令，返回是否成功
+        fn try_git(&self, args: &[&str]) -> bool {
+            Command::new("git").args(args).current_dir(&self.dir).output().unwrap().status.success()
+        }
+    }
+
+    impl Drop for TestRepo {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    /// 断言工具调用成功或失败，返回输出文本
+    trait ToolOutput {
+        fn expect_success(self) -> String;
+        fn expect_failure(self) -> String;
+    }
+
+    impl ToolOutput for Result<String, String> {
+        fn expect_success(self) -> String {
+            self.unwrap_or_else(|e| panic!("{}", e))
+        }
+
+        fn expect_failure(self) -> String {
+            match self {
+                Ok(output) => panic!("预期失败: {}", output),
+                Err(e) => e,
+            }
+        }
+    }
+
+    /// 按 MCP 客户端传入的 JSON 构造工具参数
+    fn params<T: serde::de::DeserializeOwned>(value: serde_json::Value) -> Parameters<T> {
+        Parameters(serde_json::from_value(value).unwrap())
+    }
+
+    /// 按 JSON 描述以默认格式选项执行一个提交组
+    fn run_group(repo: &TestRepo, group: serde_json::Value, no_verify: bool) -> Result<String, String> {
+        let group: CommitGroup = serde_json::from_value(group).unwrap();
+        let opts = MessageOptions { emoji: true, footers: Vec::new(), details_header: "详细描述：" };
+        commit_group(&repo.path(), 0, &group, &opts, no_verify)
+    }
+
+    fn oid(n: u8) -> git2::Oid {
+        git2::Oid::from_bytes(&[n; 20]).unwrap()
+    }
+
+    #[test]
+    fn extracts_verified_signers() {
+        let gpg = "[GNUPG:] NEWSIG\n[GNUPG:] GOODSIG 1234567890ABCDEF Tester <tester@example.com>\n[GNUPG:] TRUST_ULTIMATE";
+        assert_eq!(verify_signer(gpg).as_deref(), Some("Tester <tester@example.com>"));
+        let ssh = "Good \"git\" signature for tester@example.com with ED25519 key SHA256:abc";
+        assert_eq!(verify_signer(ssh).as_deref(), Some("tester@example.com"));
+        assert_eq!(verify_signer("[GNUPG:] BADSIG 1234567890ABCDEF Tester <tester@example.com>"), None);
+    }
+
+    #[tokio::test]
+    async fn verify_commit_reports_unsigned_commits() {
+        let repo = TestRepo::new();
+        let oid = repo.commit("a.txt", "1", "feat: 初始");
+        let server = GitMcpServer::new();
+
+        let output = server
+            .git_verify_commit(params(json!({ "revision": "HEAD", "path": repo.path() })))
+            .await
+            .expect_success();
+        assert!(output.starts_with("🔓 未签名"), "{}", output);
+        assert!(output.contains(&short_id(oid)), "{}", output);
+
+        server
+            .git_verify_commit(params(json!({ "revision": "missing", "path": repo.path() })))
+            .await
+            .expect_failure();
+    }
+
+    #[tokio::test]
+    async fn commit_dry_run_leaves_repository_untouched() {
+        let repo = TestRepo::new();
+        let base = repo.commit("a.txt", "1", "feat: 初始");
+        repo.write("a.txt", "2");
+        repo.write("b.txt", "new");
+        let server = GitMcpServer::new();
+
+        let output = server
+            .git_commit(params(json!({ "message": "fix: 修复", "dry_run": true, "path": repo.path() })))
+            .await
+            .expect_success();
+        assert!(output.contains("a.txt") && output.contains("b.txt"), "{}", output);
+        assert!(output.contains("fix: 修复"), "{}", output);
+        assert_eq!(repo.head(), base);
+        assert_eq!(repo.git(&["diff", "--cached", "--name-only"]), "");
+    }
+
+    #[tokio::test]
+    async fn commit_creates_empty_commits_only_when_allowed() {
+        let repo = TestRepo::new();
+        let base = repo.commit("a.txt", "1", "feat: 初始");
+        let server = GitMcpServer::new();
+
+        server
+            .git_commit(params(json!({ "message": "chore: 触发构建", "path": repo.path() })))
+            .await
+            .expect_failure();
+        assert_eq!(repo.head(), base);
+
+        let output = server
+            .git_commit(params(json!({ "message": "chore: 触发构建", "allow_empty": true, "path": repo.path() })))
+            .await
+            .expect_success();
+        assert!(output.contains("空提交"), "{}", output);
+        let git = repo.repo();
+        let head = git.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.parent_id(0).unwrap(), base);
+        assert_eq!(head.tree_id(), git.find_commit(base).unwrap().tree_id());
+    }
+
+    #[tokio::test]
+    async fn archive_exports_revision_and_checks_output_dir() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        let server = GitMcpServer::new();
+
+        let output = server
+            .git_archive(params(json!({ "output": "export.bin", "format": " ZIP ", "path": repo.path() })))
+            .await
+            .expect_success();
+        assert!(output.contains("zip"), "{}", output);
+        let data = std::fs::read(repo.dir.join("export.bin")).unwrap();
+        assert!(data.starts_with(b"PK"));
+
+        let output = server
+            .git_archive(params(json!({ "output": "missing/export.tar", "path": repo.path() })))
+            .await
+            .expect_failure();
+        assert!(output.contains("输出目录不存在"), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn pull_preview_lists_incoming_commits_without_merging() {
+        let upstream = TestRepo::new();
+        upstream.commit("a.txt", "1", "feat: 初始");
+        let local = TestRepo::clone_of(&upstream);
+        let head = local.head();
+        upstream.commit("b.txt", "2", "feat: 远程新功能");
+        let server = GitMcpServer::new();
+
+        let output = server
+            .git_pull_preview(params(json!({ "path": local.path() })))
+            .await
+            .expect_success();
+        assert!(output.contains("feat: 远程新功能") && output.contains("b.txt"), "{}", output);
+        assert_eq!(local.head(), head);
+        assert!(!local.dir.join("b.txt").exists());
+    }
+
+    #[test]
+    fn resolves_repo_path_from_param_or_env() {
+        // 其他测试都显式传入 path，不会读取该环境变量
+        std::env::set_var("GIT_MCP_DEFAULT_REPO", "/srv/default-repo");
+        assert_eq!(resolve_repo_path(None), "/srv/default-repo");
+        assert_eq!(resolve_repo_path(Some("/srv/explicit".to_string())), "/srv/explicit");
+        std::env::set_var("GIT_MCP_DEFAULT_REPO", "");
+        assert_eq!(resolve_repo_path(None), ".");
+        std::env::remove_var("GIT_MCP_DEFAULT_REPO");
+        assert_eq!(resolve_repo_path(None), ".");
+    }
+
+    #[tokio::test]
+    async fn stage_all_stages_new_modified_and_deleted_files() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        repo.commit("c.txt", "3", "feat: 第二");
+        repo.write("a.txt", "2");
+        repo.write("b.txt", "new");
+        std::fs::remove_file(repo.dir.join("c.txt")).unwrap();
+        let server = GitMcpServer::new();
+
+        let output = server
+            .git_stage_all(params(json!({ "path": repo.path() })))
+            .await
+            .expect_success();
+        assert!(output.contains("已暂存 3 个文件"), "{}", output);
+        assert_eq!(repo.git(&["diff", "--cached", "--name-status"]), "M\ta.txt\nA\tb.txt\nD\tc.txt");
+    }
+
+    #[tokio::test]
+    async fn rejects_blank_required_fields() {
+        assert_eq!(require_text("message", "  fix: 修复  ").unwrap(), "fix: 修复");
+        assert!(require_text("message", " \n\t").is_err());
+
+        let repo = TestRepo::new();
+        let base = repo.commit("a.txt", "1", "feat: 初始");
+        repo.write("a.txt", "2");
+        let server = GitMcpServer::new();
+
+        let output = server
+            .git_commit(params(json!({ "message": "   ", "path": repo.path() })))
+            .await
+            .expect_failure();
+        assert!(output.contains("message"), "{}", output);
+        assert_eq!(repo.head(), base);
+    }
+
+    #[tokio::test]
+    async fn unpushed_lists_commits_ahead_of_upstream() {
+        let upstream = TestRepo::new();
+        upstream.commit("a.txt", "1", "feat: 初始");
+        let local = TestRepo::clone_of(&upstream);
+        let server = GitMcpServer::new();
+        let unpushed = || server.git_unpushed(params(json!({ "path": local.path() })));
+
+        let output = unpushed().await.expect_success();
+        assert!(output.contains("没有未推送的提交"), "{}", output);
+
+        local.commit("b.txt", "2", "feat: 本地一");
+        local.commit("c.txt", "3", "fix: 本地二");
+        let output = unpushed().await.expect_success();
+        assert!(output.contains("2 个提交尚未推送"), "{}", output);
+        assert!(output.contains("feat: 本地一") && output.contains("fix: 本地二"), "{}", output);
+    }
+
+    #[test]
+    fn commit_group_commits_only_its_files() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        repo.write("a.txt", "2");
+        repo.write("b.txt", "new");
+        repo.git(&["add", "b.txt"]);
+
+        let group = json!({ "files": ["a.txt"], "commit_type": "fix", "short_desc": "修复 a", "details": ["细节"] });
+        let line = run_group(&repo, group, false).unwrap();
+        assert!(line.contains("第1组"), "{}", line);
+        assert_eq!(repo.git(&["show", "--name-only", "--format=", "HEAD"]), "a.txt");
+        assert!(repo.git(&["log", "-1", "--format=%s"]).ends_with("fix: 修复 a"));
+
+        let group = json!({ "files": ["missing.txt"], "commit_type": "fix", "short_desc": "不存在", "details": [] });
+        assert!(run_group(&repo, group, false).is_err());
+    }
+
+    #[tokio::test]
+    async fn set_upstream_shows_and_changes_tracking_branch() {
+        let upstream = TestRepo::new();
+        upstream.commit("a.txt", "1", "feat: 初始");
+        upstream.git(&["branch", "dev"]);
+        let local = TestRepo::clone_of(&upstream);
+        let server = GitMcpServer::new();
+
+        let output = server
+            .git_set_upstream(params(json!({ "path": local.path() })))
+            .await
+            .expect_success();
+        assert!(output.contains("origin/main"), "{}", output);
+
+        server
+            .git_set_upstream(params(json!({ "branch": "dev", "path": local.path() })))
+            .await
+            .expect_success();
+        assert_eq!(local.git(&["rev-parse", "--abbrev-ref", "@{u}"]), "origin/dev");
+
+        server
+            .git_set_upstream(params(json!({ "branch": "missing", "path": local.path() })))
+            .await
+            .expect_failure();
+        assert_eq!(local.git(&["rev-parse", "--abbrev-ref", "@{u}"]), "origin/dev");
+    }
+
+    #[tokio::test]
+    async fn show_file_reads_content_at_revision() {
+        let repo = TestRepo::new();
+        repo.commit("src/a.txt", "第一版\n", "feat: 初始");
+        repo.commit("src/a.txt", "第二版\n", "fix: 修改");
+        repo.write("src/a.txt", "未提交\n");
+        let server = GitMcpServer::new();
+        let show = |revision: &str| {
+            server.git_show_file(params(json!({ "file": "src/a.txt", "revision": revision, "path": repo.path() })))
+        };
+
+        let output = show("HEAD~1").await.expect_success();
+        assert!(output.contains("第一版") && !output.contains("第二版"), "{}", output);
+        let output = show("HEAD").await.expect_success();
+        assert!(output.contains("第二版") && !output.contains("未提交"), "{}", output);
+
+        server
+            .git_show_file(params(json!({ "file": "missing.txt", "path": repo.path() })))
+            .await
+            .expect_failure();
+    }
+
+    #[tokio::test]
+    async fn commit_message_follows_repo_policy() {
+        let repo = TestRepo::new();
+        repo.write(".gitmcp.toml", "[policy]\nsubject_max_length = 10\nrequire_body_for_types = [\"feat\"]\n");
+        let server = GitMcpServer::new();
+        let generate = |commit_type: &str, short_desc: &str, details: &[&str]| {
+            server.generate_commit_message(params(json!({
+                "commit_type": commit_type,
+                "short_desc": short_desc,
+                "details": details,
+                "path": repo.path(),
+            })))
+        };
+
+        let output = generate("feat", "一个非常非常长的简短描述文字", &[]).await.expect_failure();
+        assert!(output.contains("超过上限 10"), "{}", output);
+        assert!(output.contains("feat 类型的提交必须填写详细描述"), "{}", output);
+        generate("feat", "添加登录", &["支持短信验证码"]).await.expect_success();
+        generate("fix", "修复崩溃", &[]).await.expect_success();
+    }
+
+    #[test]
+    fn finds_pending_files_over_size_limit() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.0 MB");
+
+        let repo = TestRepo::new();
+        repo.commit("committed.bin", &"x".repeat(4096), "feat: 初始");
+        repo.write("big.bin", &"x".repeat(2048));
+        repo.write("bigger.bin", &"x".repeat(3000));
+        repo.write("small.txt", "small");
+
+        let large = find_large_files(&repo.repo(), 1024).unwrap();
+        assert_eq!(large, [("bigger.bin".to_string(), 3000), ("big.bin".to_string(), 2048)]);
+    }
+
+    #[test]
+    fn smart_commit_example_matches_schema() {
+        let example: SmartCommitParam = serde_json::from_value(smart_commit_example()).unwrap();
+        assert_eq!(example.commits.len(), 2);
+
+        let schema = serde_json::to_string(&schemars::schema_for!(SmartCommitParam)).unwrap();
+        assert!(schema.contains("修复空输入时解析崩溃"), "{}", schema);
+        let schema = serde_json::to_string(&schemars::schema_for!(CommitGroup)).unwrap();
+        assert!(schema.contains("添加用户登录功能"), "{}", schema);
+    }
+
+    #[tokio::test]
+    async fn compare_branches_lists_commits_and_files() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        repo.git(&["checkout", "-q", "-b", "feature"]);
+        repo.commit("b.txt", "1\n2\n", "feat: 新功能");
+        let server = GitMcpServer::new();
+        let compare = |base: &str, head: &str| {
+            server.git_compare_branches(params(json!({ "base": base, "head": head, "path": repo.path() })))
+        };
+
+        let output = compare("main", "feature").await.expect_success();
+        assert!(output.contains("feat: 新功能"), "{}", output);
+        assert!(output.contains("b.txt | +2 -0"), "{}", output);
+
+        let output = compare("feature", "main").await.expect_success();
+        assert!(output.contains("没有 feature 之外的提交"), "{}", output);
+
+        compare("main", "missing").await.expect_failure();
+    }
+
+    #[tokio::test]
+    async fn maintenance_packs_loose_objects() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        repo.commit("b.txt", "2", "feat: 第二");
+        assert!(dir_size(&repo.dir.join(".git")) > 0);
+        let server = GitMcpServer::new();
+
+        let output = server
+            .git_maintenance(params(json!({ "path": repo.path() })))
+            .await
+            .expect_success();
+        assert!(output.contains("仓库维护完成"), "{}", output);
+        assert!(repo.git(&["count-objects", "-v"]).contains("count: 0"));
+    }
+
+    #[test]
+    fn formats_subject_with_or_without_emoji() {
+        let fix = COMMIT_TYPES.iter().find(|t| t.name == "fix").unwrap();
+        assert_eq!(format_subject(fix, "修复崩溃", false), "fix: 修复崩溃");
+        assert_eq!(format_subject(fix, "修复崩溃", true), format!("{} fix: 修复崩溃", fix.emoji));
+        assert!(!emoji_enabled(Some(false)));
+        assert!(emoji_enabled(Some(true)));
+    }
+
+    #[tokio::test]
+    async fn group_diff_shows_only_listed_files() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "旧内容\n", "feat: 初始");
+        repo.write("a.txt", "新内容\n");
+        repo.write("b.txt", "未跟踪\n");
+        repo.write("c.txt", "不在本组\n");
+        let server = GitMcpServer::new();
+
+        let output = server
+            .preview_group_diff(params(json!({ "files": ["a.txt", "b.txt"], "path": repo.path() })))
+            .await
+            .expect_success();
+        assert!(output.contains("-旧内容") && output.contains("+新内容"), "{}", output);
+        assert!(output.contains("+未跟踪"), "{}", output);
+        assert!(!output.contains("c.txt"), "{}", output);
+
+        let output = server
+            .preview_group_diff(params(json!({ "files": [], "path": repo.path() })))
+            .await
+            .expect_failure();
+        assert!(output.contains("files"), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn warns_about_detached_head() {
+        let repo = TestRepo::new();
+        let base = repo.commit("a.txt", "1", "feat: 初始");
+        assert_eq!(detached_head(&repo.path()), None);
+        repo.git(&["checkout", "-q", "--detach"]);
+        assert_eq!(detached_head(&repo.path()), Some(short_id(base)));
+        let server = GitMcpServer::new();
+
+        let output = server
+            .git_branch(params(json!({ "path": repo.path() })))
+            .await
+            .expect_success();
+        assert!(output.contains("游离 HEAD"), "{}", output);
+
+        repo.write("a.txt", "2");
+        let output = server
+            .git_commit(params(json!({ "message": "fix: 修复", "path": repo.path() })))
+            .await
+            .expect_success();
+        assert!(output.contains("游离 HEAD"), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn commit_message_appends_ticket_footer_from_branch() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        repo.write(".gitmcp.toml", "[footer]\nticket_pattern = \"[A-Z]+-[0-9]+\"\n");
+        let server = GitMcpServer::new();
+        let generate = || {
+            server.generate_commit_message(params(json!({
+                "commit_type": "feat",
+                "short_desc": "添加登录",
+                "details": ["支持短信验证码"],
+                "path": repo.path(),
+            })))
+        };
+
+        let output = generate().await.expect_success();
+        assert!(!output.contains("Refs:"), "{}", output);
+
+        repo.git(&["checkout", "-q", "-b", "feature/ABC-123-login"]);
+        let output = generate().await.expect_success();
+        assert!(output.contains("Refs: ABC-123"), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn merge_base_finds_common_ancestor() {
+        let repo = TestRepo::new();
+        let base = repo.commit("a.txt", "1", "feat: 初始");
+        repo.git(&["checkout", "-q", "-b", "feature"]);
+        repo.commit("b.txt", "2", "feat: 分支");
+        repo.git(&["checkout", "-q", "main"]);
+        repo.commit("c.txt", "3", "fix: 主线");
+        repo.commit("d.txt", "4", "fix: 主线二");
+        let server = GitMcpServer::new();
+
+        let output = server
+            .git_merge_base(params(json!({ "a": "main", "b": "feature", "path": repo.path() })))
+            .await
+            .expect_success();
+        assert!(output.contains(&base.to_string()), "{}", output);
+        assert!(output.contains("main 自分叉后有 2 个提交，feature 自分叉后有 1 个提交"), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn commit_auto_pushes_to_upstream() {
+        let upstream = TestRepo::new();
+        upstream.commit("a.txt", "1", "feat: 初始");
+        upstream.git(&["config", "receive.denyCurrentBranch", "updateInstead"]);
+        let local = TestRepo::clone_of(&upstream);
+        local.write("b.txt", "2");
+        let server = GitMcpServer::new();
+
+        let output = server
+            .git_commit(params(json!({ "message": "feat: 新功能", "auto_push": true, "path": local.path() })))
+            .await
+            .expect_success();
+        assert!(output.contains("已推送到上游分支"), "{}", output);
+        assert_eq!(upstream.head(), local.head());
+    }
+
+    #[tokio::test]
+    async fn commit_message_suggests_configured_template() {
+        let repo = TestRepo::new();
+        repo.write(".gitmessage", "# 为什么修改：\n\n# 影响范围：\n");
+        repo.git(&["config", "commit.template", ".gitmessage"]);
+        assert_eq!(
+            commit_template(&repo.path()),
+            Some((".gitmessage".to_string(), "# 为什么修改：\n\n# 影响范围：".to_string()))
+        );
+        let server = GitMcpServer::new();
+        let generate = |details: &[&str]| {
+            server.generate_commit_message(params(json!({
+                "commit_type": "fix",
+                "short_desc": "修复崩溃",
+                "details": details,
+                "path": repo.path(),
+            })))
+        };
+
+        let output = generate(&[]).await.expect_success();
+        assert!(output.contains("# 影响范围："), "{}", output);
+        let output = generate(&["修复空指针"]).await.expect_success();
+        assert!(!output.contains("# 影响范围："), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn log_filters_commits_by_path() {
+        let repo = TestRepo::new();
+        repo.commit("src/a.rs", "1", "feat: 源码");
+        repo.commit("docs/readme.md", "1", "docs: 文档");
+        repo.commit("src/b.rs", "2", "fix: 源码修复");
+        let server = GitMcpServer::new();
+
+        let output = server
+            .git_log(params(json!({ "paths": ["src/"], "path": repo.path() })))
+            .await
+            .expect_success();
+        assert!(output.contains("feat: 源码") && output.contains("fix: 源码修复"), "{}", output);
+        assert!(!output.contains("docs: 文档"), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn init_is_idempotent_and_refuses_nested_repositories() {
+        let outer = TestRepo::new();
+        let server = GitMcpServer::new();
+        let init = |path: std::path::PathBuf, allow_nested: bool| {
+            server.git_init(params(json!({
+                "initial_branch": "trunk",
+                "allow_nested": allow_nested,
+                "path": path.display().to_string(),
+            })))
+        };
+
+        let output = init(outer.dir.clone(), false).await.expect_success();
+        assert!(output.contains("已是 Git 仓库"), "{}", output);
+
+        let nested = outer.dir.join("vendor/lib");
+        let output = init(nested.clone(), false).await.expect_failure();
+        assert!(output.contains("拒绝创建嵌套仓库"), "{}", output);
+        assert!(!nested.exists());
+
+        let output = init(nested.clone(), true).await.expect_success();
+        assert!(output.contains("trunk") && output.contains("嵌套"), "{}", output);
+        let repo = Repository::open(&nested).unwrap();
+        assert_eq!(repo.workdir().unwrap().canonicalize().unwrap(), nested.canonicalize().unwrap());
+    }
+
+    #[test]
+    fn lints_commit_messages() {
+        let lint = LintConfig::default();
+        let rules = |message: &str| lint_message(message, &lint).iter().map(|p| p.rule).collect::<Vec<_>>();
+
+        assert!(rules("feat: 添加登录").is_empty());
+        assert!(rules("✨ feat(api)!: 添加登录\n\n- 细节").is_empty());
+        assert_eq!(rules("添加登录"), ["type-enum"]);
+        assert_eq!(rules("feature: 添加登录"), ["type-enum"]);
+        assert_eq!(rules("fix: 修复崩溃。"), ["subject-full-stop"]);
+        assert_eq!(rules("fix: 修复崩溃\n正文"), ["body-leading-blank"]);
+        assert_eq!(rules("fix: 修复崩溃 "), ["trailing-whitespace"]);
+        assert_eq!(rules(&format!("fix: 修复崩溃\n\n{}", "x".repeat(73))), ["body-max-line-length"]);
+
+        let mut relaxed = LintConfig::default();
+        relaxed.rules.insert("type-enum".to_string(), "off".to_string());
+        relaxed.rules.insert("subject-full-stop".to_string(), "warning".to_string());
+        let problems = lint_message("随便写写.", &relaxed);
+        assert_eq!(problems.len(), 1);
+        assert_eq!((problems[0].rule, problems[0].severity.as_str()), ("subject-full-stop", "warning"));
+    }
+
+    #[tokio::test]
+    async fn plan_commit_commits_only_planned_files() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        repo.write("a.txt", "2");
+        repo.write("b.txt", "新文件");
+        repo.write("c.txt", "另一项改动");
+        repo.git(&["add", "c.txt"]);
+        let server = GitMcpServer::new();
+
+        server
+            .plan_add(params(json!({ "files": ["a.txt"], "path": repo.path() })))
+            .await
+            .expect_success();
+        let output = server
+            .plan_add(params(json!({ "files": ["b.txt", "a.txt"], "path": repo.path() })))
+            .await
+            .expect_success();
+        assert!(output.contains("已加入 1 个文件，暂存计划共 2 个文件"), "{}", output);
+
+        server
+            .plan_commit(params(json!({ "message": "feat: 计划提交", "path": repo.path() })))
+            .await
+            .expect_success();
+        assert_eq!(repo.git(&["show", "--name-only", "--format=", "HEAD"]), "a.txt\nb.txt");
+        assert_eq!(repo.git(&["diff", "--cached", "--name-only"]), "c.txt");
+
+        let output = server
+            .plan_status(params(json!({ "path": repo.path() })))
+            .await
+            .expect_success();
+        assert!(output.contains("暂存计划为空"), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn rename_branch_renames_current_branch() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        repo.git(&["branch", "taken"]);
+        let server = GitMcpServer::new();
+        let rename = |new_name: &str| server.git_rename_branch(params(json!({ "new_name": new_name, "path": repo.path() })));
+
+        let output = rename("taken").await.expect_failure();
+        assert!(output.contains("已存在"), "{}", output);
+
+        rename("trunk").await.expect_success();
+        assert_eq!(repo.git(&["symbolic-ref", "--short", "HEAD"]), "trunk");
+        assert_eq!(repo.git(&["branch", "--list", "main"]), "");
+    }
+
+    #[tokio::test]
+    async fn tools_report_failures_as_errors() {
+        let dir = std::env::temp_dir().join(format!("git-mcp-not-a-repo-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let server = GitMcpServer::new();
+
+        let result = server.git_status(params(json!({ "path": dir.display().to_string() }))).await;
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(result.unwrap_err().starts_with("❌ 无法打开 Git 仓库"));
+
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        assert!(server.git_status(params(json!({ "path": repo.path() }))).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn blame_skips_ignored_revisions() {
+        let repo = TestRepo::new();
+        let original = repo.commit("a.txt", "one\ntwo\n", "feat: 初始");
+        let reformat = repo.commit("a.txt", "one\nTWO\n", "style: 格式化");
+        let server = GitMcpServer::new();
+        let blame = |value: serde_json::Value| server.git_blame(params(value));
+
+        let output = blame(json!({ "file": "a.txt", "path": repo.path() })).await.expect_success();
+        assert!(output.contains(&reformat.to_string()[..7]), "{}", output);
+
+        let output = blame(json!({ "file": "a.txt", "ignore_revs": [reformat.to_string()], "path": repo.path() }))
+            .await
+            .expect_success();
+        assert!(output.contains(&format!("忽略提交: {}", reformat)), "{}", output);
+        let lines = output.split("```").nth(1).unwrap();
+        assert!(!lines.contains(&reformat.to_string()[..7]), "{}", output);
+        assert!(lines.contains(&original.to_string()[..7]), "{}", output);
+
+        let output = blame(json!({ "file": "a.txt", "start_line": 2, "end_line": 1, "path": repo.path() }))
+            .await
+            .expect_failure();
+        assert!(output.contains("行范围无效"), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn churn_ranks_files_by_changed_lines() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1\n", "feat: 初始");
+        repo.commit("a.txt", "1\n2\n3\n", "feat: a");
+        repo.commit("b.txt", "x\n", "feat: b");
+        let server = GitMcpServer::new();
+
+        let output = server
+            .git_churn(params(json!({ "since": "HEAD~2", "path": repo.path() })))
+            .await
+            .expect_success();
+        assert!(output.contains("2 个提交，2 个文件"), "{}", output);
+        let a = output.find("- a.txt | 2 行（+2 -0），1 次提交").expect(&output);
+        let b = output.find("- b.txt | 1 行（+1 -0），1 次提交").expect(&output);
+        assert!(a < b, "{}", output);
+
+        let output = server
+            .git_churn(params(json!({ "since": "HEAD", "path": repo.path() })))
+            .await
+            .expect_success();
+        assert!(output.contains("没有文件变更"), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn repo_lock_serializes_writers_of_the_same_repository() {
+        let repo = TestRepo::new();
+        let other = TestRepo::new();
+        let server = GitMcpServer::new();
+        let wait = std::time::Duration::from_millis(50);
+
+        let guard = server.lock_repo(&repo.path()).await;
+        let nested = repo.dir.join("src").display().to_string();
+        std::fs::create_dir_all(&nested).unwrap();
+        assert!(tokio::time::timeout(wait, server.lock_repo(&nested)).await.is_err());
+        assert!(tokio::time::timeout(wait, server.lock_repo(&other.path())).await.is_ok());
+
+        drop(guard);
+        assert!(tokio::time::timeout(wait, server.lock_repo(&repo.path())).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn file_diff_compares_worktree_with_revision() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1\n", "feat: 初始");
+        repo.commit("a.txt", "2\n", "fix: 修改");
+        repo.write("b.txt", "other\n");
+        let server = GitMcpServer::new();
+        let diff = |revision: &str| {
+            server.git_file_diff(params(json!({ "file": "a.txt", "revision": revision, "path": repo.path() })))
+        };
+
+        let output = diff("HEAD").await.expect_success();
+        assert!(output.contains("内容一致"), "{}", output);
+
+        let output = diff("HEAD~1").await.expect_success();
+        assert!(output.contains("-1\n+2"), "{}", output);
+        assert!(!output.contains("b.txt"), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn commit_message_details_header_follows_language() {
+        let repo = TestRepo::new();
+        let server = GitMcpServer::new();
+        let generate = |language: &str| {
+            server.generate_commit_message(params(json!({
+                "commit_type": "feat",
+                "short_desc": "添加登录",
+                "details": ["支持短信验证码"],
+                "language": language,
+                "path": repo.path(),
+            })))
+        };
+
+        assert!(generate("zh").await.expect_success().contains("详细描述：\n"));
+        let output = generate(" EN ").await.expect_success();
+        assert!(output.contains("Details:\n") && !output.contains("详细描述"), "{}", output);
+        assert!(generate("fr").await.expect_failure().contains("不支持的语言 fr"));
+    }
+
+    #[tokio::test]
+    async fn abort_merge_keeps_unrelated_changes() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "base", "feat: 初始");
+        repo.commit("notes.txt", "notes", "docs: 笔记");
+        repo.git(&["checkout", "-q", "-b", "side"]);
+        repo.commit("a.txt", "theirs", "feat: 对方");
+        repo.git(&["checkout", "-q", "main"]);
+        let head = repo.commit("a.txt", "ours", "feat: 本方");
+        repo.write("notes.txt", "未提交的笔记");
+
+        assert!(!repo.try_git(&["merge", "side"]));
+        assert_eq!(repo.repo().state(), RepositoryState::Merge);
+
+        let server = GitMcpServer::new();
+        server.git_abort(params(json!({ "path": repo.path() }))).await.expect_success();
+        assert_eq!(repo.repo().state(), RepositoryState::Clean);
+        assert_eq!(repo.head(), head);
+        assert_eq!(repo.read("a.txt"), "ours");
+        assert_eq!(repo.read("notes.txt"), "未提交的笔记");
+
+        let output = server.git_abort(params(json!({ "path": repo.path() }))).await.expect_success();
+        assert!(output.contains("当前没有进行中"), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn abort_cherry_pick_sequence_restores_original_head() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "base", "feat: 初始");
+        repo.git(&["checkout", "-q", "-b", "side"]);
+        repo.commit("b.txt", "new", "feat: 可拣选");
+        repo.commit("a.txt", "theirs", "feat: 会冲突");
+        repo.git(&["checkout", "-q", "main"]);
+        let head = repo.commit("a.txt", "ours", "feat: 本方");
+
+        assert!(!repo.try_git(&["cherry-pick", "side~1", "side"]));
+        assert_ne!(repo.head(), head, "第一个拣选应已提交");
+
+        let server = GitMcpServer::new();
+        server.git_abort(params(json!({ "path": repo.path() }))).await.expect_success();
+        assert_eq!(repo.repo().state(), RepositoryState::Clean);
+        assert_eq!(repo.head(), head);
+        assert!(!repo.dir.join("b.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn ls_tree_lists_entries_at_revision() {
+        let repo = TestRepo::new();
+        repo.commit("src/main.rs", "fn main() {}", "feat: 初始");
+        repo.commit("src/lib/mod.rs", "", "feat: 模块");
+        let server = GitMcpServer::new();
+        let ls = |value: serde_json::Value| server.git_ls_tree(params(value));
+
+        let output = ls(json!({ "path": repo.path() })).await.expect_success();
+        assert!(output.contains("040000 tree src"), "{}", output);
+
+        let output = ls(json!({ "directory": "src/", "recursive": true, "path": repo.path() })).await.expect_success();
+        assert!(output.contains("2 个条目"), "{}", output);
+        assert!(output.contains("100644 blob src/lib/mod.rs"), "{}", output);
+        assert!(output.contains("100644 blob src/main.rs"), "{}", output);
+
+        let output = ls(json!({ "directory": "src/lib", "revision": "HEAD~1", "path": repo.path() })).await.expect_failure();
+        assert!(output.contains("找不到目录 src/lib"), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn branch_contains_lists_descendant_branches() {
+        let repo = TestRepo::new();
+        repo.commit("base.txt", "0", "feat: 初始");
+        let fix = repo.commit("a.txt", "1", "fix: 修复");
+        repo.git(&["branch", "release"]);
+        repo.commit("b.txt", "2", "feat: 新功能");
+        repo.git(&["checkout", "-q", "-b", "old", "HEAD~2"]);
+        let server = GitMcpServer::new();
+        let contains = |revision: String| server.git_branch_contains(params(json!({ "revision": revision, "path": repo.path() })));
+
+        let output = contains(fix.to_string()).await.expect_success();
+        assert!(output.contains("本地分支（2 个）"), "{}", output);
+        assert!(output.contains("- main") && output.contains("- release"), "{}", output);
+        assert!(!output.contains("- old"), "{}", output);
+
+        let output = contains("main".to_string()).await.expect_success();
+        assert!(output.contains("本地分支（1 个）：\n- main"), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn prune_branches_deletes_only_merged_branches() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        repo.git(&["branch", "merged"]);
+        repo.git(&["checkout", "-q", "-b", "wip"]);
+        repo.commit("b.txt", "2", "feat: 未合并");
+        repo.git(&["checkout", "-q", "main"]);
+        repo.commit("c.txt", "3", "feat: 主线");
+        let server = GitMcpServer::new();
+        let prune = |dry_run: bool| server.git_prune_branches(params(json!({ "dry_run": dry_run, "path": repo.path() })));
+
+        let output = prune(true).await.expect_success();
+        assert!(output.contains("以下 1 个分支已合入 main"), "{}", output);
+        assert!(output.contains("- merged") && !output.contains("wip"), "{}", output);
+        assert!(repo.git(&["branch", "--list", "merged"]).contains("merged"));
+
+        prune(false).await.expect_success();
+        assert_eq!(repo.git(&["branch", "--list", "merged"]), "");
+        assert!(repo.git(&["branch", "--list", "wip"]).contains("wip"));
+    }
+
+    #[tokio::test]
+    async fn log_shows_commit_bodies_when_requested() {
+        let repo = TestRepo::new();
+        repo.write("a.txt", "1");
+        repo.git(&["add", "a.txt"]);
+        repo.git(&["commit", "-q", "-m", "feat: 初始", "-m", "第一段正文"]);
+        let server = GitMcpServer::new();
+        let log = |show_body: bool| server.git_log(params(json!({ "show_body": show_body, "path": repo.path() })));
+
+        let output = log(false).await.expect_success();
+        assert!(output.contains("feat: 初始") && !output.contains("第一段正文"), "{}", output);
+
+        let output = log(true).await.expect_success();
+        assert!(output.contains("feat: 初始\n    第一段正文"), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn stash_push_stashes_only_listed_files() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        repo.write("a.txt", "2");
+        repo.write("new.txt", "未跟踪");
+        repo.write("keep.txt", "保留");
+        let server = GitMcpServer::new();
+        let stash = |files: &[&str]| {
+            server.git_stash_push(params(json!({ "files": files, "message": "临时", "path": repo.path() })))
+        };
+
+        let output = stash(&["a.txt", "new.txt"]).await.expect_success();
+        assert!(output.contains("已储藏 2 个文件"), "{}", output);
+        assert_eq!(repo.read("a.txt"), "1");
+        assert!(!repo.dir.join("new.txt").exists());
+        assert_eq!(repo.read("keep.txt"), "保留");
+        assert!(repo.git(&["stash", "list"]).contains("临时"));
+
+        stash(&[]).await.expect_failure();
+    }
+
+    #[test]
+    fn appends_trailers() {
+        let trailer = "Co-authored-by: Bot <bot@example.com>";
+        let cases = [
+            ("feat: x", format!("feat: x\n\n{}", trailer)),
+            ("feat: x\n\n正文\n", format!("feat: x\n\n正文\n\n{}", trailer)),
+            ("feat: x\n\nRefs: ABC-1", format!("feat: x\n\nRefs: ABC-1\n{}", trailer)),
+            ("feat: x\n\nnote: not a trailer block\n还有一行", format!("feat: x\n\nnote: not a trailer block\n还有一行\n\n{}", trailer)),
+            ("Refs: ABC-1", format!("Refs: ABC-1\n\n{}", trailer)),
+        ];
+        for (message, expected) in cases {
+            assert_eq!(append_trailer(message, trailer), expected, "{:?}", message);
+        }
+
+        let existing = format!("feat: x\n\n{}", trailer);
+        assert_eq!(append_trailer(&existing, trailer), existing);
+    }
+
+    #[tokio::test]
+    async fn is_clean_reports_dirty_files() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        let server = GitMcpServer::new();
+        let check = || server.git_is_clean(params(json!({ "path": repo.path() })));
+
+        let output = check().await.expect_success();
+        assert!(output.contains("\"clean\": true"), "{}", output);
+
+        repo.write("a.txt", "2");
+        repo.write("new.txt", "未跟踪");
+        let output = check().await.expect_success();
+        assert!(output.contains("有 2 个文件未提交") && output.contains("\"clean\": false"), "{}", output);
+        assert!(output.contains("\"new.txt\""), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn push_reports_each_branch_and_transport_failures_once() {
+        // 本地传输只支持推送到裸仓库
+        let hub = TestRepo::new();
+        hub.git(&["init", "-q", "--bare", "origin.git"]);
+        let origin = hub.dir.join("origin.git");
+        let local = TestRepo::new();
+        local.commit("a.txt", "1", "feat: 初始");
+        local.git(&["remote", "add", "origin", &origin.display().to_string()]);
+        local.git(&["checkout", "-q", "-b", "feature"]);
+        let tip = local.commit("b.txt", "2", "feat: 新功能");
+        let server = GitMcpServer::new();
+        let push = |remote: &str| {
+            server.git_push(params(json!({ "remote": remote, "branches": ["feature", "missing"], "path": local.path() })))
+        };
+
+        let output = push("origin").await.expect_failure();
+        assert!(output.contains("1/2 个分支成功"), "{}", output);
+        assert!(output.contains("✅ feature → origin/feature"), "{}", output);
+        assert!(output.contains("❌ missing: 找不到本地分支"), "{}", output);
+        assert_eq!(Repository::open(&origin).unwrap().refname_to_id("refs/heads/feature").unwrap(), tip);
+
+        let missing = local.dir.join("no-such-remote");
+        local.git(&["remote", "add", "broken", &missing.display().to_string()]);
+        let output = push("broken").await.expect_failure();
+        assert_eq!(output.matches("连接或认证错误").count(), 1, "{}", output);
+        assert!(output.contains("❌ feature: 未推送（推送中断）"), "{}", output);
+    }
+
+    #[test]
+    fn parses_semver_tags() {
+        let cases = [
+            ("v1.2.3", "v", Some((1, 2, 3))),
+            ("1.2.3", "", Some((1, 2, 3))),
+            ("release-10.0.1", "release-", Some((10, 0, 1))),
+            ("v1.2.3", "", None),
+            ("1.2.3", "v", None),
+            ("v1.2", "v", None),
+            ("v1.2.3.4", "v", None),
+            ("v1.2.3-rc.1", "v", None),
+            ("vx.2.3", "v", None),
+        ];
+        for (tag, prefix, expected) in cases {
+            assert_eq!(parse_semver_tag(tag, prefix), expected, "{} / {:?}", tag, prefix);
+        }
+    }
+
+    #[tokio::test]
+    async fn bump_tag_increments_latest_version() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        let server = GitMcpServer::new();
+        let bump = |level: &str| server.git_bump_tag(params(json!({ "level": level, "path": repo.path() })));
+
+        let output = bump("patch").await.expect_success();
+        assert!(output.contains("创建标签 v0.1.0"), "{}", output);
+
+        repo.git(&["tag", "v1.2.3"]);
+        repo.git(&["tag", "v1.10.0-rc.1"]);
+        repo.commit("a.txt", "2", "feat: 新功能");
+        let output = bump(" Minor ").await.expect_success();
+        assert!(output.contains("创建标签 v1.3.0（上一版本 v1.2.3）"), "{}", output);
+        assert_eq!(repo.git(&["rev-parse", "v1.3.0^{commit}"]), repo.head().to_string());
+
+        repo.git(&["tag", "2.0.0"]);
+        let output = bump("major").await.expect_success();
+        assert!(output.contains("创建标签 3.0.0（上一版本 2.0.0）"), "{}", output);
+
+        assert!(bump("huge").await.expect_failure().contains("不支持的 level"));
+    }
+
+    #[tokio::test]
+    async fn file_diff_returns_hunks_as_json() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1\n2\n", "feat: 初始");
+        repo.write("a.txt", "1\n3\n");
+        let server = GitMcpServer::new();
+        let diff = |format: &str| server.git_file_diff(params(json!({ "file": "a.txt", "format": format, "path": repo.path() })));
+
+        let output = diff("JSON").await.expect_success();
+        let json = output.split("```json\n").nth(1).and_then(|rest| rest.split("\n```").next()).unwrap();
+        let files: serde_json::Value = serde_json::from_str(json).unwrap();
+        let file = &files[0];
+        assert_eq!(file["old_path"], "a.txt");
+        assert_eq!(file["status"], "修改");
+        let hunk = &file["hunks"][0];
+        assert_eq!((hunk["old_start"].as_u64(), hunk["new_lines"].as_u64()), (Some(1), Some(2)));
+        let lines: Vec<String> = hunk["lines"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|l| format!("{}{}", l["origin"].as_str().unwrap(), l["content"].as_str().unwrap()))
+            .collect();
+        assert_eq!(lines, [" 1", "-2", "+3"]);
+
+        assert!(diff("yaml").await.expect_failure().contains("不支持的输出格式 yaml"));
+    }
+
+    #[tokio::test]
+    async fn restore_deleted_recovers_last_version_inside_repo() {
+        let repo = TestRepo::new();
+        repo.commit("docs/guide.md", "v1", "docs: 指南");
+        repo.commit("docs/guide.md", "v2", "docs: 更新指南");
+        repo.git(&["rm", "-q", "docs/guide.md"]);
+        repo.git(&["commit", "-q", "-m", "chore: 删除指南"]);
+        let server = GitMcpServer::new();
+        let restore = |file: &str| {
+            server.git_restore_deleted(params(json!({ "file": file, "stage": true, "path": repo.path() })))
+        };
+
+        let output = restore("docs/guide.md").await.expect_success();
+        assert!(output.contains("docs: 更新指南") && output.contains("并已加入暂存区"), "{}", output);
+        assert_eq!(repo.read("docs/guide.md"), "v2");
+        assert_eq!(repo.git(&["diff", "--cached", "--name-only"]), "docs/guide.md");
+
+        assert!(restore("docs/guide.md").await.expect_failure().contains("已存在"));
+        assert!(restore("never.txt").await.expect_failure().contains("找不到文件"));
+
+        let output = restore("../escaped.txt").await.expect_failure();
+        assert!(output.contains("仓库内的相对路径"), "{}", output);
+        assert!(!repo.dir.parent().unwrap().join("escaped.txt").exists());
+    }
+
+    #[test]
+    fn parses_sizes_with_units() {
+        let cases = [
+            ("10M", Some(10 * 1024 * 1024)),
+            (" 512kb ", Some(512 * 1024)),
+            ("2G", Some(2 * 1024 * 1024 * 1024)),
+            ("4096", Some(4096)),
+            ("", None),
+            ("M", None),
+            ("1.5M", None),
+            ("99999999999999G", None),
+        ];
+        for (raw, expected) in cases {
+            assert_eq!(parse_size(raw), expected, "{:?}", raw);
+        }
+    }
+
+    #[test]
+    fn run_git_captures_output() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+
+        let output = run_git(&repo.path(), ["log", "-1", "--format=%s"]).unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "feat: 初始");
+
+        let output = run_git(&repo.path(), ["rev-parse", "no-such-branch"]).unwrap();
+        assert!(!output.status.success());
+    }
+
+    #[tokio::test]
+    async fn conflict_diff_shows_three_way_versions() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "base", "feat: 初始");
+        repo.git(&["checkout", "-q", "-b", "side"]);
+        repo.commit("a.txt", "theirs", "feat: 对方");
+        repo.git(&["checkout", "-q", "main"]);
+        repo.commit("a.txt", "ours", "feat: 本方");
+        let server = GitMcpServer::new();
+        let conflict = || server.git_conflict_diff(params(json!({ "file": "a.txt", "path": repo.path() })));
+
+        assert!(conflict().await.expect_success().contains("没有冲突"));
+
+        assert!(!repo.try_git(&["merge", "side"]));
+        let output = conflict().await.expect_success();
+        let base = output.find("```\nbase\n```").expect(&output);
+        let ours = output.find("```\nours\n```").expect(&output);
+        let theirs = output.find("```\ntheirs\n```").expect(&output);
+        assert!(base < ours && ours < theirs, "{}", output);
+    }
+
+    #[tokio::test]
+    async fn list_changed_groups_files_and_counts_each_once() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        repo.write("a.txt", "2");
+        repo.git(&["add", "a.txt"]);
+        repo.write("a.txt", "3");
+        repo.write("new.txt", "未跟踪");
+        let server = GitMcpServer::new();
+        let list = |category: &str| server.git_list_changed(params(json!({ "category": category, "path": repo.path() })));
+
+        let output = list("").await.expect_success();
+        assert!(output.contains("变更文件共 2 项"), "{}", output);
+        assert!(output.contains("\"staged\": [\n    \"a.txt\"\n  ]"), "{}", output);
+        assert!(output.contains("\"unstaged\": [\n    \"a.txt\"\n  ]"), "{}", output);
+
+        let output = list("Untracked").await.expect_success();
+        assert!(output.contains("untracked 文件共 1 项") && output.contains("new.txt"), "{}", output);
+        assert!(list("ignored").await.expect_failure().contains("不支持的类别 ignored"));
+    }
+
+    #[tokio::test]
+    async fn bump_tag_reports_signing_failures() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        repo.git(&["config", "user.signingkey", "tester@example.com"]);
+        repo.git(&["config", "gpg.program", "false"]);
+        let server = GitMcpServer::new();
+
+        let output = server
+            .git_bump_tag(params(json!({ "level": "patch", "sign": true, "path": repo.path() })))
+            .await
+            .expect_failure();
+        assert!(output.contains("签名标签失败"), "{}", output);
+        assert_eq!(repo.git(&["tag", "--list"]), "");
+    }
+
+    #[tokio::test]
+    async fn head_reports_branch_and_hashes() {
+        let repo = TestRepo::new();
+        let head = repo.commit("a.txt", "1", "feat: 初始");
+        let server = GitMcpServer::new();
+        let show = || server.git_head(params(json!({ "path": repo.path() })));
+
+        let output = show().await.expect_success();
+        assert!(output.contains(&format!("\"oid\": \"{}\"", head)), "{}", output);
+        assert!(output.contains("\"branch\": \"main\"") && output.contains("\"subject\": \"feat: 初始\""), "{}", output);
+
+        repo.git(&["checkout", "-q", "--detach"]);
+        let output = show().await.expect_success();
+        assert!(output.contains("游离 HEAD") && output.contains("\"detached\": true"), "{}", output);
+    }
+
+    #[test]
+    fn explains_authentication_failures() {
+        let attempted = std::cell::RefCell::new(Vec::new());
+        let other = git2::Error::new(git2::ErrorCode::NotFound, git2::ErrorClass::Reference, "no such ref");
+        assert_eq!(remote_error(&other, &attempted), "no such ref");
+
+        let auth = git2::Error::new(git2::ErrorCode::Auth, git2::ErrorClass::Http, "denied");
+        assert!(remote_error(&auth, &attempted).contains("未尝试任何认证方式"));
+
+        attempted.borrow_mut().extend(["ssh-agent".to_string(), "GIT_MCP_TOKEN".to_string()]);
+        assert_eq!(remote_error(&auth, &attempted), "denied\n💡 已尝试的认证方式均失败: ssh-agent、GIT_MCP_TOKEN");
+    }
+
+    #[tokio::test]
+    async fn stash_diff_includes_untracked_files() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1\n", "feat: 初始");
+        repo.write("a.txt", "2\n");
+        repo.write("new.txt", "未跟踪\n");
+        repo.git(&["stash", "push", "-q", "-u", "-m", "半成品"]);
+        let server = GitMcpServer::new();
+        let diff = |index: u32| server.git_stash_diff(params(json!({ "index": index, "path": repo.path() })));
+
+        let output = diff(0).await.expect_success();
+        assert!(output.contains("📦 stash@{0}: On main: 半成品"), "{}", output);
+        assert!(output.contains("-1\n+2") && output.contains("+未跟踪"), "{}", output);
+        assert!(diff(1).await.expect_failure().contains("找不到储藏 stash@{1}"));
+    }
+
+    #[tokio::test]
+    async fn line_history_follows_a_line_back_to_its_origin() {
+        let repo = TestRepo::new();
+        let added = repo.commit("a.txt", "head\nvalue = 1\ntail\n", "feat: 初始");
+        repo.commit("b.txt", "无关", "docs: 无关提交");
+        let changed = repo.commit("a.txt", "head\nvalue = 2\ntail\n", "fix: 修改取值");
+        let server = GitMcpServer::new();
+        let history = |line: u32| server.git_line_history(params(json!({ "file": "a.txt", "line": line, "path": repo.path() })));
+
+        let output = history(2).await.expect_success();
+        assert!(output.contains("a.txt:2 的修改历史（2 次）"), "{}", output);
+        let first = output.find(&format!("1. {} fix: 修改取值", &changed.to_string()[..7])).expect(&output);
+        let second = output.find(&format!("2. {} feat: 初始（Tester，新增该行）", &added.to_string()[..7])).expect(&output);
+        assert!(first < second, "{}", output);
+        assert!(output.contains("- value = 1\n+ value = 2"), "{}", output);
+
+        assert!(history(0).await.expect_failure().contains("必须从 1 开始"));
+        assert!(history(9).await.expect_failure().contains("没有第 9 行"));
+    }
+
+    #[test]
+    fn reads_commit_group_limit_from_env() {
+        assert_eq!(max_commit_groups(), 50);
+        std::env::set_var("GIT_MCP_MAX_COMMIT_GROUPS", " 3 ");
+        assert_eq!(max_commit_groups(), 3);
+        std::env::set_var("GIT_MCP_MAX_COMMIT_GROUPS", "0");
+        assert_eq!(max_commit_groups(), 50);
+        std::env::remove_var("GIT_MCP_MAX_COMMIT_GROUPS");
+    }
+
+    #[tokio::test]
+    async fn plan_commits_groups_changes_by_category() {
+        let repo = TestRepo::new();
+        repo.commit("src/lib.rs", "", "feat: 初始");
+        repo.write("src/new.rs", "pub fn f() {}");
+        repo.write("docs/guide.md", "指南");
+        repo.write("Cargo.toml", "[package]");
+        repo.write(".github/workflows/ci.yml", "on: push");
+        let server = GitMcpServer::new();
+
+        let output = server.plan_commits(params(json!({ "path": repo.path() }))).await.expect_success();
+        assert!(output.contains("提交计划（4 组）"), "{}", output);
+        let json = output.split("```json\n").nth(1).and_then(|rest| rest.split("\n```").next()).unwrap();
+        let plan: SmartCommitParam = serde_json::from_str(json).unwrap();
+        let groups: Vec<(&str, &str)> = plan.commits.iter().map(|c| (c.commit_type.as_str(), c.files[0].as_str())).collect();
+        assert_eq!(
+            groups,
+            [("feat", "src/new.rs"), ("docs", "docs/guide.md"), ("build", "Cargo.toml"), ("ci", ".github/workflows/ci.yml")]
+        );
+    }
+
+    #[tokio::test]
+    async fn status_labels_symlinks_and_submodules() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        std::os::unix::fs::symlink("a.txt", repo.dir.join("link")).unwrap();
+        Command::new("git").args(["init", "-q", "sub"]).current_dir(&repo.dir).output().unwrap();
+        let sub = repo.dir.join("sub");
+        Command::new("git")
+            .args(["-c", "user.name=Tester", "-c", "user.email=tester@example.com", "commit", "-q", "--allow-empty", "-m", "init"])
+            .current_dir(&sub)
+            .output()
+            .unwrap();
+        repo.git(&["add", "link", "sub"]);
+        let server = GitMcpServer::new();
+
+        let output = server.git_status(params(json!({ "path": repo.path() }))).await.expect_success();
+        assert!(output.contains("link（符号链接）"), "{}", output);
+        let sub_head = Repository::open(&sub).unwrap().head().unwrap().target().unwrap();
+        assert!(output.contains(&format!("🔗 新增子模块 sub @ {}", &sub_head.to_string()[..7])), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn rev_parse_resolves_expressions_to_commits() {
+        let repo = TestRepo::new();
+        let first = repo.commit("a.txt", "1", "feat: 初始");
+        repo.git(&["tag", "v1.0.0"]);
+        repo.commit("a.txt", "2", "fix: 修复");
+        let server = GitMcpServer::new();
+        let parse = |expression: &str| server.git_rev_parse(params(json!({ "expression": expression, "path": repo.path() })));
+
+        for expression in ["HEAD~1", "v1.0.0", &first.to_string()[..8]] {
+            let output = parse(expression).await.expect_success();
+            assert!(output.contains(&format!("→ {}", first)) && output.contains("feat: 初始"), "{}", output);
+        }
+        assert!(parse("HEAD~5").await.expect_failure().contains("无法解析 HEAD~5"));
+    }
+
+    #[tokio::test]
+    async fn list_commit_types_supports_json() {
+        let server = GitMcpServer::new();
+        let list = |format: &str| server.list_commit_types(params(json!({ "format": format })));
+
+        let types: Vec<serde_json::Value> = serde_json::from_str(&list("JSON").await.expect_success()).unwrap();
+        assert_eq!(types.len(), COMMIT_TYPES.len());
+        assert_eq!(types[0]["name"], COMMIT_TYPES[0].name);
+        assert!(list("markdown").await.expect_success().contains(COMMIT_TYPES[0].desc));
+        assert!(list("xml").await.expect_failure().contains("不支持的输出格式 xml"));
+    }
+
+    #[tokio::test]
+    async fn commit_on_protected_branch_requires_feature_branch() {
+        let repo = TestRepo::new();
+        repo.commit(".gitmcp.toml", "[policy]\nprotected_branches = [\"main\"]\n", "chore: 配置");
+        let base = repo.head();
+        repo.write("a.txt", "1");
+        let server = GitMcpServer::new();
+        let commit = |auto_branch: bool| {
+            server.git_commit(params(json!({ "message": "feat: 新功能", "auto_branch": auto_branch, "path": repo.path() })))
+        };
+
+        let output = commit(false).await.expect_failure();
+        assert!(output.contains("分支 main 受保护"), "{}", output);
+        assert_eq!(repo.head(), base);
+
+        let output = commit(true).await.expect_success();
+        assert!(output.contains("已自动创建并切换到 feature/auto-"), "{}", output);
+        assert!(repo.git(&["symbolic-ref", "--short", "HEAD"]).starts_with("feature/auto-"));
+        assert_eq!(repo.git(&["rev-parse", "main"]), base.to_string());
+    }
+
+    #[tokio::test]
+    async fn remote_status_reports_ahead_behind_and_gone_upstreams() {
+        let upstream = TestRepo::new();
+        upstream.commit("a.txt", "1", "feat: 初始");
+        upstream.git(&["branch", "old"]);
+        let local = TestRepo::clone_of(&upstream);
+        local.commit("b.txt", "2", "feat: 本地");
+        local.git(&["branch", "-q", "--track", "old", "origin/old"]);
+        local.git(&["update-ref", "-d", "refs/remotes/origin/old"]);
+        local.git(&["branch", "scratch"]);
+        let server = GitMcpServer::new();
+
+        let output = server.git_remote_status(params(json!({ "path": local.path() }))).await.expect_success();
+        assert!(output.contains("2 个分支的上游同步状态"), "{}", output);
+        assert!(output.contains("其中 1 个分支的上游已被删除；另有 1 个分支未配置上游"), "{}", output);
+        let json = output.split("```json\n").nth(1).and_then(|rest| rest.split("\n```").next()).unwrap();
+        let entries: serde_json::Value = serde_json::from_str(json).unwrap();
+        assert_eq!(entries[0]["branch"], "main");
+        assert_eq!((entries[0]["ahead"].as_u64(), entries[0]["behind"].as_u64()), (Some(1), Some(0)));
+        assert_eq!(entries[1]["branch"], "old");
+        assert_eq!(entries[1]["gone"], true);
+    }
+
+    #[test]
+    fn formats_dates_and_interpolates_details() {
+        assert_eq!(format_date(0), "1970-01-01");
+        assert_eq!(format_date(951_782_400), "2000-02-29");
+        assert_eq!(format_date(-1), "1969-12-31");
+
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        let details = vec!["{user} 在 {branch} 上修改".to_string(), "无占位符".to_string()];
+        let (resolved, unresolved) = interpolate_details(&details, &repo.path());
+        assert_eq!(resolved, ["Tester 在 main 上修改", "无占位符"]);
+        assert!(unresolved.is_empty());
+
+        repo.git(&["checkout", "-q", "--detach"]);
+        let (resolved, unresolved) = interpolate_details(&details, &repo.path());
+        assert_eq!(resolved[0], "Tester 在 {branch} 上修改");
+        assert_eq!(unresolved, ["{branch}"]);
+    }
+
+    #[test]
+    fn parses_commit_dates() {
+        // 2024-01-02 00:00:00 UTC
+        let day = 1_704_153_600;
+        let cases = [
+            ("2024-01-02", day, 0),
+            ("2024-01-02 03:04:05", day + 3 * 3600 + 4 * 60 + 5, 0),
+            ("2024-01-02T03:04Z", day + 3 * 3600 + 4 * 60, 0),
+            ("2024-01-02 08:00 +08:00", day, 480),
+            ("2024-01-02 08:00:00 +0800", day, 480),
+            ("2024-01-01 22:30 -01:30", day, -90),
+            ("1700000000", 1_700_000_000, 0),
+            ("@1700000000", 1_700_000_000, 0),
+        ];
+        for (raw, seconds, offset) in cases {
+            let time = parse_commit_date(raw).unwrap_or_else(|e| panic!("{}: {}", raw, e));
+            assert_eq!((time.seconds(), time.offset_minutes()), (seconds, offset), "{}", raw);
+        }
+
+        for raw in ["", "yesterday", "2024/01/02", "2024-13-01", "2024-01-32", "2024-02-30", "2023-02-29", "2024-04-31", "2024-01-02 24:00", "2024-01-02 12:60"] {
+            assert!(parse_commit_date(raw).is_err(), "{} 应解析失败", raw);
+        }
+        assert!(parse_commit_date("2024-02-29").is_ok());
+        assert!(parse_commit_date("now").is_ok());
+    }
+
+    #[tokio::test]
+    async fn amend_author_rewrites_unpushed_head_only() {
+        let upstream = TestRepo::new();
+        upstream.commit("a.txt", "1", "feat: 初始");
+        let repo = TestRepo::clone_of(&upstream);
+        let server = GitMcpServer::new();
+        let amend = |value: serde_json::Value| server.git_amend_author(params(value));
+
+        let output = amend(json!({ "name": "Other", "path": repo.path() })).await.expect_failure();
+        assert!(output.contains("已推送到 origin/main"), "{}", output);
+
+        repo.commit("b.txt", "2", "feat: 本地");
+        let tree = repo.git(&["rev-parse", "HEAD^{tree}"]);
+        amend(json!({ "name": "Other", "email": "other@example.com", "date": "2024-01-02 08:00 +08:00", "path": repo.path() }))
+            .await
+            .expect_success();
+        assert_eq!(repo.git(&["log", "-1", "--format=%an|%ae|%at|%s"]), "Other|other@example.com|1704153600|feat: 本地");
+        assert_eq!(repo.git(&["rev-parse", "HEAD^{tree}"]), tree);
+
+        assert!(amend(json!({ "path": repo.path() })).await.expect_failure().contains("至少提供"));
+        assert!(amend(json!({ "date": "2024-02-30", "path": repo.path() })).await.expect_failure().contains("无法解析时间"));
+    }
+
+    #[tokio::test]
+    async fn status_by_dir_summarizes_top_level_directories() {
+        let repo = TestRepo::new();
+        repo.commit("src/a.rs", "1", "feat: 初始");
+        repo.commit("src/b.rs", "1", "feat: b");
+        repo.write("src/a.rs", "2");
+        repo.write("src/c.rs", "new");
+        std::fs::remove_file(repo.dir.join("src/b.rs")).unwrap();
+        repo.write("README.md", "说明");
+        let server = GitMcpServer::new();
+
+        let output = server.git_status_by_dir(params(json!({ "path": repo.path() }))).await.expect_success();
+        assert!(output.contains("4 个变更文件分布在 2 个顶层目录"), "{}", output);
+        let src = output.find("- src/: 3 个变更（新增 1，修改 1，删除 1）").expect(&output);
+        let root = output.find("- ./: 1 个变更（新增 1）").expect(&output);
+        assert!(src < root, "{}", output);
+    }
+
+    #[tokio::test]
+    async fn parents_identify_root_and_merge_commits() {
+        let repo = TestRepo::new();
+        let root = repo.commit("a.txt", "1", "feat: 初始");
+        repo.git(&["checkout", "-q", "-b", "side"]);
+        let side = repo.commit("b.txt", "2", "feat: 分支");
+        repo.git(&["checkout", "-q", "main"]);
+        let main = repo.commit("c.txt", "3", "feat: 主线");
+        repo.git(&["merge", "-q", "--no-ff", "-m", "merge: side", "side"]);
+        let server = GitMcpServer::new();
+        let parents = |revision: &str| server.git_parents(params(json!({ "revision": revision, "path": repo.path() })));
+
+        let output = parents("HEAD").await.expect_success();
+        assert!(output.contains("有 2 个父提交"), "{}", output);
+        assert!(output.contains(&format!("1. {} feat: 主线\n2. {} feat: 分支", main, side)), "{}", output);
+        assert!(parents("side").await.expect_success().contains(&format!("1. {} feat: 初始", root)));
+        assert!(parents(&root.to_string()).await.expect_success().contains("是根提交"));
+    }
+
+    #[tokio::test]
+    async fn sparse_checkout_limits_and_restores_worktree() {
+        let repo = TestRepo::new();
+        repo.write("api/main.rs", "api");
+        repo.write("web/index.html", "web");
+        repo.git(&["add", "."]);
+        repo.git(&["commit", "-q", "-m", "feat: 初始"]);
+        let server = GitMcpServer::new();
+        let sparse = |value: serde_json::Value| server.git_sparse_checkout(params(value));
+
+        let output = sparse(json!({ "action": "list", "path": repo.path() })).await.expect_success();
+        assert!(output.contains("未启用稀疏检出"), "{}", output);
+
+        let output = sparse(json!({ "action": "set", "patterns": ["api"], "path": repo.path() })).await.expect_success();
+        assert!(output.contains("- api"), "{}", output);
+        assert!(repo.dir.join("api/main.rs").exists());
+        assert!(!repo.dir.join("web/index.html").exists());
+
+        sparse(json!({ "action": "disable", "path": repo.path() })).await.expect_success();
+        assert_eq!(repo.read("web/index.html"), "web");
+        assert!(sparse(json!({ "action": "set", "path": repo.path() })).await.expect_failure().contains("需要提供 patterns"));
+    }
+
+    #[tokio::test]
+    async fn commit_output_follows_verbosity() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        let server = GitMcpServer::new();
+        let commit = |message: &str, verbosity: &str| {
+            server.git_commit(params(json!({ "message": message, "verbosity": verbosity, "path": repo.path() })))
+        };
+
+        repo.write("a.txt", "2");
+        let output = commit("fix: 安静", "quiet").await.expect_success();
+        assert_eq!(output, format!("✅ {}", &repo.head().to_string()[..7]));
+
+        repo.write("b.txt", "new");
+        let output = commit("feat: 详细", "Verbose").await.expect_success();
+        assert!(output.contains(&format!("🔖 {}", repo.head())), "{}", output);
+        assert!(output.contains("提交的文件（1 个）：\n- 新增 b.txt"), "{}", output);
+
+        repo.write("b.txt", "changed");
+        assert!(commit("fix: 无效", "loud").await.expect_failure().contains("不支持的 verbosity loud"));
+    }
+
+    #[tokio::test]
+    async fn recent_branches_follow_checkout_order() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        for branch in ["alpha", "beta", "gone"] {
+            repo.git(&["branch", branch]);
+        }
+        for branch in ["alpha", "beta", "gone", "alpha", "main"] {
+            repo.git(&["checkout", "-q", branch]);
+        }
+        repo.git(&["branch", "-q", "-D", "gone"]);
+        let server = GitMcpServer::new();
+
+        let output = server.git_recent_branches(params(json!({ "path": repo.path() }))).await.expect_success();
+        assert!(output.contains("最近切换过的 3 个分支"), "{}", output);
+        let main = output.find("1. main（当前）").expect(&output);
+        let alpha = output.find("2. alpha").expect(&output);
+        let beta = output.find("3. beta").expect(&output);
+        assert!(main < alpha && alpha < beta, "{}", output);
+    }
+
+    #[tokio::test]
+    async fn commit_tree_applies_patch_without_touching_worktree() {
+        let repo = TestRepo::new();
+        let head = repo.commit("a.txt", "1\n", "feat: 初始");
+        repo.write("a.txt", "2\n");
+        let patch = repo.git(&["diff"]);
+        repo.git(&["checkout", "--", "a.txt"]);
+        let server = GitMcpServer::new();
+        let commit_tree = |patch: &str| {
+            server.git_commit_tree(params(json!({ "patch": patch, "message": "fix: 补丁", "path": repo.path() })))
+        };
+
+        let output = commit_tree(&patch).await.expect_success();
+        let oid = output.split_whitespace().nth(2).and_then(|w| w.split('（').next()).unwrap();
+        assert_eq!(repo.git(&["show", &format!("{}:a.txt", oid)]), "2");
+        assert_eq!(repo.git(&["rev-parse", &format!("{}^", oid)]), head.to_string());
+        assert_eq!(repo.head(), head);
+        assert_eq!(repo.read("a.txt"), "1\n");
+        assert_eq!(repo.git(&["status", "--porcelain"]), "");
+
+        repo.commit("a.txt", "3\n", "feat: 冲突");
+        assert!(commit_tree(&patch).await.expect_failure().contains("补丁无法应用到 HEAD"));
+    }
+
+    #[tokio::test]
+    async fn file_diff_summarizes_binary_changes() {
+        let repo = TestRepo::new();
+        repo.commit("logo.bin", "a\0b", "feat: 初始");
+        repo.write("logo.bin", "a\0bc");
+        let server = GitMcpServer::new();
+
+        let output = server
+            .git_file_diff(params(json!({ "file": "logo.bin", "path": repo.path() })))
+            .await
+            .expect_success();
+        assert!(output.contains("- 修改 logo.bin | 二进制文件已变更 (3 B → 4 B)"), "{}", output);
+        assert!(!output.contains("a\0b"), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn estimate_commit_size_counts_listed_files_only() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1\n2\n", "feat: 初始");
+        repo.write("a.txt", "1\n3\n4\n");
+        repo.write("new.txt", "x\n");
+        repo.write("other.txt", "不计入\n");
+        let server = GitMcpServer::new();
+        let estimate = |files: &[&str]| server.estimate_commit_size(params(json!({ "files": files, "path": repo.path() })));
+
+        let output = estimate(&["a.txt", "new.txt"]).await.expect_success();
+        assert!(output.contains("2 个文件预计提交规模：+3 -1，共 4 行变动，2 个 hunk"), "{}", output);
+        assert!(output.contains("- a.txt | +2 -1，1 个 hunk") && !output.contains("other.txt"), "{}", output);
+
+        assert!(estimate(&["missing.txt"]).await.expect_success().contains("没有变更"));
+        estimate(&[]).await.expect_failure();
+    }
+
+    #[tokio::test]
+    async fn notes_are_added_overwritten_and_removed() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        let server = GitMcpServer::new();
+        let notes = |action: &str, message: Option<&str>| {
+            server.git_notes(params(json!({ "action": action, "message": message, "path": repo.path() })))
+        };
+
+        assert!(notes("show", None).await.expect_success().contains("没有注释"));
+        notes("add", Some("review: pending")).await.expect_success();
+        let output = notes("add", Some("review: approved")).await.expect_success();
+        assert!(output.contains("已覆盖") && output.contains("review: pending"), "{}", output);
+        assert_eq!(repo.git(&["notes", "show", "HEAD"]), "review: approved");
+
+        notes("remove", None).await.expect_success();
+        assert!(!repo.try_git(&["notes", "show", "HEAD"]));
+        assert!(notes("add", None).await.expect_failure().contains("message"));
+        assert!(notes("edit", None).await.expect_failure().contains("不支持的操作 edit"));
+    }
+
+    #[tokio::test]
+    async fn is_rebased_on_detects_outdated_branches() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        repo.git(&["checkout", "-q", "-b", "feature"]);
+        repo.commit("b.txt", "2", "feat: 功能");
+        let server = GitMcpServer::new();
+        let check = || server.git_is_rebased_on(params(json!({ "branch": "feature", "base": "main", "path": repo.path() })));
+
+        let output = check().await.expect_success();
+        assert!(output.contains("feature 已基于 main 的最新提交，领先 1 个提交"), "{}", output);
+
+        repo.git(&["checkout", "-q", "main"]);
+        repo.commit("c.txt", "3", "feat: 主线");
+        let output = check().await.expect_success();
+        assert!(output.contains("feature 落后 main 1 个提交"), "{}", output);
+        assert!(output.contains("\"rebased\": false"), "{}", output);
+    }
+
+    #[test]
+    fn converts_remote_urls_to_web_urls() {
+        let cases = [
+            ("git@github.com:owner/repo.git", Some(("https://github.com/owner/repo", WebHost::GitHub))),
+            ("https://github.com/owner/repo.git", Some(("https://github.com/owner/repo", WebHost::GitHub))),
+            ("ssh://git@gitlab.example.com:2222/group/sub/repo.git", Some(("https://gitlab.example.com/group/sub/repo", WebHost::GitLab))),
+            ("https://user@bitbucket.org/team/repo", Some(("https://bitbucket.org/team/repo", WebHost::Bitbucket))),
+            ("https://git.example.com:8443/owner/repo/", Some(("https://git.example.com:8443/owner/repo", WebHost::Unknown))),
+            ("git@git.internal:tools.git", Some(("https://git.internal/tools", WebHost::Unknown))),
+            ("https://github.com", None),
+            ("/srv/git/repo.git", None),
+            ("", None),
+        ];
+        for (url, expected) in cases {
+            let expected = expected.map(|(base, host)| (base.to_string(), host));
+            assert_eq!(web_base_url(url), expected, "{}", url);
+        }
+    }
+
+    #[tokio::test]
+    async fn web_url_links_pushed_commits_and_files() {
+        let upstream = TestRepo::new();
+        let head = upstream.commit("src/main.rs", "fn main() {}", "feat: 初始");
+        let local = TestRepo::clone_of(&upstream);
+        local.git(&["remote", "set-url", "origin", "git@github.com:owner/repo.git"]);
+        let server = GitMcpServer::new();
+        let url = |value: serde_json::Value| server.git_web_url(params(value));
+
+        assert_eq!(url(json!({ "path": local.path() })).await.expect_success(), "🌐 https://github.com/owner/repo");
+        let output = url(json!({ "file": "./src/main.rs", "path": local.path() })).await.expect_success();
+        assert_eq!(output, format!("🌐 https://github.com/owner/repo/blob/{}/src/main.rs", head));
+
+        local.commit("b.txt", "2", "feat: 本地");
+        let output = url(json!({ "revision": "HEAD", "path": local.path() })).await.expect_success();
+        assert!(output.contains(&format!("/commit/{}", local.head())) && output.contains("尚未推送"), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn checkout_from_takes_files_from_another_branch() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        repo.git(&["checkout", "-q", "-b", "hotfix"]);
+        repo.commit("a.txt", "fixed", "fix: 修复 a");
+        repo.commit("b.txt", "hot", "fix: 修复 b");
+        repo.git(&["checkout", "-q", "main"]);
+        let server = GitMcpServer::new();
+        let take = |files: &[&str]| server.git_checkout_from(params(json!({ "branch": "hotfix", "files": files, "path": repo.path() })));
+
+        repo.write("a.txt", "本地改动");
+        let output = take(&["a.txt"]).await.expect_failure();
+        assert!(output.contains("- a.txt") && output.contains("已取消"), "{}", output);
+        assert_eq!(repo.read("a.txt"), "本地改动");
+
+        repo.git(&["checkout", "--", "a.txt"]);
+        let output = take(&["a.txt"]).await.expect_success();
+        assert!(output.contains("取来 1 个文件"), "{}", output);
+        assert_eq!(repo.read("a.txt"), "fixed");
+        assert_eq!(repo.git(&["diff", "--cached", "--name-only"]), "a.txt");
+        assert!(!repo.dir.join("b.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn default_branch_prefers_recorded_remote_head() {
+        let upstream = TestRepo::new();
+        upstream.commit("a.txt", "1", "feat: 初始");
+        let local = TestRepo::clone_of(&upstream);
+        upstream.git(&["branch", "-q", "-m", "trunk"]);
+        let server = GitMcpServer::new();
+        let query = || server.git_default_branch(params(json!({ "path": local.path() })));
+
+        let output = query().await.expect_success();
+        assert!(output.contains("origin 的默认分支: trunk（来源：远程查询）"), "{}", output);
+
+        local.git(&["fetch", "-q", "origin"]);
+        local.git(&["remote", "set-head", "origin", "trunk"]);
+        let output = query().await.expect_success();
+        assert!(output.contains("trunk（来源：本地 refs/remotes/origin/HEAD）"), "{}", output);
+
+        let output = server
+            .git_default_branch(params(json!({ "remote": "upstream", "path": local.path() })))
+            .await
+            .expect_failure();
+        assert!(output.contains("找不到远程仓库 upstream"), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn preview_commit_message_renders_without_committing() {
+        let repo = TestRepo::new();
+        repo.commit(".gitmcp.toml", "[policy]\nrequire_body_for_types = [\"feat\"]\n", "chore: 配置");
+        repo.write("a.txt", "1");
+        let server = GitMcpServer::new();
+        let preview = |details: &[&str]| {
+            server.preview_commit_message(params(json!({
+                "files": ["a.txt"],
+                "commit_type": "feat",
+                "short_desc": "添加登录",
+                "details": details,
+                "emoji": false,
+                "path": repo.path(),
+            })))
+        };
+
+        let output = preview(&["支持短信验证码"]).await.expect_success();
+        assert!(output.contains("feat: 添加登录\n\n详细描述：\n- 支持短信验证码"), "{}", output);
+        assert!(!output.contains("不符合提交规范"), "{}", output);
+
+        let output = preview(&[]).await.expect_success();
+        assert!(output.contains("smart_commit 会拒绝该组"), "{}", output);
+        assert_eq!(repo.git(&["status", "--porcelain"]), "?? a.txt");
+    }
+
+    #[test]
+    fn detects_installed_git() {
+        assert!(git_available());
+        let repo = TestRepo::new();
+        let output = run_git(&repo.path(), ["status", "--short"]).unwrap();
+        assert!(output.status.success());
+    }
+
+    #[tokio::test]
+    async fn repo_size_ranks_largest_tracked_files() {
+        let repo = TestRepo::new();
+        repo.commit("small.txt", "1", "feat: 初始");
+        repo.commit("big.txt", &"x".repeat(2048), "feat: 大文件");
+        repo.write("untracked.txt", &"y".repeat(4096));
+        let server = GitMcpServer::new();
+
+        let output = server
+            .git_repo_size(params(json!({ "path": repo.path() })))
+            .await
+            .expect_success();
+        assert!(output.contains("已跟踪文件: 2 个，共 2.0 KB"), "{}", output);
+        assert!(output.contains("- big.txt (2.0 KB)\n- small.txt (1 B)"), "{}", output);
+        assert!(!output.contains("untracked.txt"));
+
+        std::fs::remove_file(repo.dir.join("small.txt")).unwrap();
+        let output = server
+            .git_repo_size(params(json!({ "path": repo.path() })))
+            .await
+            .expect_success();
+        assert!(output.contains("另有 1 个已跟踪文件不在工作区"), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn changes_between_tags_groups_by_type() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        repo.git(&["tag", "v1.0.0"]);
+        repo.commit("b.txt", "2", "fix: 修复崩溃");
+        repo.commit("c.txt", "3", "随手改了点东西");
+        repo.commit("d.txt", "4", "feat(ui): 新按钮");
+        repo.git(&["tag", "v1.1.0"]);
+        let server = GitMcpServer::new();
+        let between = |from: &str, to: Option<&str>| {
+            server.git_changes_between_tags(params(json!({ "from_tag": from, "to_tag": to, "path": repo.path() })))
+        };
+
+        let output = between("v1.0.0", Some("v1.1.0")).await.expect_success();
+        assert!(output.contains("v1.0.0..v1.1.0 共 3 个提交"), "{}", output);
+        let feat = output.find("（feat）").unwrap();
+        let fix = output.find("（fix）").unwrap();
+        let other = output.find("### 其他").unwrap();
+        assert!(feat < fix && fix < other, "{}", output);
+        assert!(output.contains("新按钮") && !output.contains("初始"), "{}", output);
+
+        let output = between("v1.1.0", Some("v1.0.0")).await.expect_success();
+        assert!(output.contains("是否把两个标签写反了"), "{}", output);
+        assert!(between("v9.9.9", None).await.expect_failure().contains("标签 v9.9.9 不存在"));
+    }
+
+    #[tokio::test]
+    async fn can_ff_classifies_merge_outcome() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        repo.git(&["branch", "base"]);
+        repo.git(&["checkout", "-q", "-b", "ahead"]);
+        repo.commit("a.txt", "2", "feat: 前进");
+        repo.git(&["checkout", "-q", "-b", "clash", "base"]);
+        repo.commit("a.txt", "3", "feat: 冲突");
+        repo.git(&["checkout", "-q", "-b", "other", "base"]);
+        repo.commit("b.txt", "4", "feat: 另一个文件");
+        repo.git(&["checkout", "-q", "main"]);
+        let server = GitMcpServer::new();
+        let can_ff = |branch: &str| server.git_can_ff(params(json!({ "branch": branch, "path": repo.path() })));
+
+        assert!(can_ff("base").await.expect_success().contains("\"analysis\": \"up_to_date\""));
+        assert!(can_ff("ahead").await.expect_success().contains("\"fast_forward\": true"));
+
+        repo.git(&["merge", "-q", "--ff-only", "ahead"]);
+        assert!(can_ff("other").await.expect_success().contains("\"analysis\": \"normal\""));
+        let output = can_ff("clash").await.expect_success();
+        assert!(output.contains("\"analysis\": \"conflict\"") && output.contains("\"a.txt\""), "{}", output);
+        assert_eq!(repo.git(&["status", "--porcelain"]), "");
+    }
+
+    #[tokio::test]
+    async fn with_stash_pops_only_its_own_stash() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        repo.git(&["branch", "other"]);
+        repo.write("a.txt", "较早的储藏");
+        repo.git(&["stash", "push", "-q", "-m", "older"]);
+        let older = repo.git(&["rev-parse", "refs/stash"]);
+
+        let server = GitMcpServer::new();
+        let checkout = || {
+            server.git_with_stash(params(json!({ "operation": "checkout", "branch": "other", "path": repo.path() })))
+        };
+
+        // 工作区干净时不创建储藏，也不能弹出已有的储藏
+        checkout().await.expect_success();
+        assert_eq!(repo.git(&["rev-parse", "refs/stash"]), older);
+        assert_eq!(repo.read("a.txt"), "1");
+
+        repo.write("a.txt", "本次改动");
+        checkout().await.expect_success();
+        assert_eq!(repo.read("a.txt"), "本次改动");
+        assert_eq!(repo.git(&["rev-parse", "refs/stash"]), older);
+        assert_eq!(repo.git(&["stash", "list"]).lines().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn with_stash_pulls_from_upstream_remote() {
+        let upstream = TestRepo::new();
+        upstream.commit("a.txt", "1", "feat: 初始");
+        let local = TestRepo::clone_of(&upstream);
+        local.git(&["remote", "rename", "origin", "hub"]);
+        upstream.commit("b.txt", "2", "feat: 远程新提交");
+        local.write("a.txt", "本地改动");
+        let server = GitMcpServer::new();
+        let pull = |remote: Option<&str>| {
+            server.git_with_stash(params(json!({ "operation": "pull", "branch": "main", "remote": remote, "path": local.path() })))
+        };
+
+        let output = pull(None).await.expect_success();
+        assert!(output.contains("git pull --no-edit hub main"), "{}", output);
+        assert_eq!(local.read("b.txt"), "2");
+        assert_eq!(local.read("a.txt"), "本地改动");
+
+        let output = pull(Some("origin")).await.expect_failure();
+        assert!(output.contains("git pull --no-edit origin main 失败"), "{}", output);
+        assert_eq!(local.read("a.txt"), "本地改动");
+    }
+
+    #[test]
+    fn assigns_graph_lanes() {
+        let (root, a, b, merge) = (oid(1), oid(2), oid(3), oid(4));
+
+        // 线性历史始终在第 0 列
+        let linear = assign_lanes(&[(b, vec![a]), (a, vec![root]), (root, vec![])]);
+        assert_eq!(linear, vec![(0, vec![0]), (0, vec![0]), (0, vec![])]);
+
+        // merge 的第二父提交开出新泳道，两条泳道在共同祖先处汇合
+        let merged = assign_lanes(&[(merge, vec![a, b]), (a, vec![root]), (b, vec![root]), (root, vec![])]);
+        assert_eq!(merged, vec![(0, vec![0, 1]), (0, vec![0]), (1, vec![0]), (0, vec![])]);
+
+        // 两个无关的分支头分别占用一列
+        let heads = assign_lanes(&[(b, vec![root]), (a, vec![root]), (root, vec![])]);
+        assert_eq!(heads, vec![(0, vec![0]), (1, vec![0]), (0, vec![])]);
+    }
+
+    #[tokio::test]
+    async fn graph_reports_refs_and_lanes() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        repo.git(&["checkout", "-q", "-b", "side"]);
+        repo.commit("b.txt", "2", "feat: 分支");
+        repo.git(&["checkout", "-q", "main"]);
+        repo.commit("c.txt", "3", "feat: 主线");
+        repo.git(&["tag", "v1"]);
+        let server = GitMcpServer::new();
+
+        let output = server
+            .git_graph(params(json!({ "path": repo.path() })))
+            .await
+            .expect_success();
+        assert!(output.contains("3 个提交，2 条泳道"), "{}", output);
+        let json = output.split("```json\n").nth(1).unwrap().trim_end_matches("\n```");
+        let nodes: Vec<serde_json::Value> = serde_json::from_str(json).unwrap();
+        let main = nodes.iter().find(|n| n["subject"] == "feat: 主线").unwrap();
+        assert_eq!(main["refs"], json!(["HEAD", "main", "tag: v1"]));
+        assert_eq!(nodes.last().unwrap()["subject"], "feat: 初始");
+    }
+
+    #[tokio::test]
+    async fn identity_sets_local_user() {
+        let repo = TestRepo::new();
+        let server = GitMcpServer::new();
+        let identity = |name: Option<&str>, email: Option<&str>| {
+            server.git_identity(params(json!({ "name": name, "email": email, "path": repo.path() })))
+        };
+
+        let output = identity(None, None).await.expect_success();
+        assert!(output.contains("- user.name: Tester（来自仓库本地配置）"), "{}", output);
+
+        let output = identity(Some(" 张三 "), Some("zhang@example.com")).await.expect_success();
+        assert!(output.contains("张三 <zhang@example.com>"), "{}", output);
+        assert_eq!(repo.git(&["config", "--local", "user.name"]), "张三");
+        assert_eq!(repo.git(&["config", "--local", "user.email"]), "zhang@example.com");
+
+        assert!(identity(Some("李四"), None).await.expect_failure().contains("需要同时提供"));
+        assert_eq!(repo.git(&["config", "--local", "user.name"]), "张三");
+    }
+
+    #[tokio::test]
+    async fn commit_count_supports_revision_and_date() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        repo.git(&["tag", "v1"]);
+        repo.commit("a.txt", "2", "feat: 第二");
+        repo.commit("a.txt", "3", "feat: 第三");
+        let server = GitMcpServer::new();
+        let count = |since: Option<&str>| server.git_commit_count(params(json!({ "since": since, "path": repo.path() })));
+
+        assert!(count(None).await.expect_success().starts_with("🔢 3\n"));
+        let output = count(Some("v1")).await.expect_success();
+        assert!(output.starts_with("🔢 2\n") && output.contains("v1..HEAD"), "{}", output);
+        assert!(count(Some("2000-01-01")).await.expect_success().starts_with("🔢 3\n"));
+        assert!(count(Some("2999-01-01")).await.expect_success().starts_with("🔢 0\n"));
+        assert!(count(Some("没有这个")).await.expect_failure().contains("无法将 没有这个 解析为修订或日期"));
+    }
+
+    #[test]
+    fn normalizes_bodies() {
+        let cases = [
+            (None, None),
+            (Some(""), None),
+            (Some(" \n\n \n"), None),
+            (Some("一行"), Some("一行")),
+            (Some("\r\n第一段\r\n\r\n第二段\r\n\r\n"), Some("第一段\n\n第二段")),
+            (Some("旧式\r换行"), Some("旧式\n换行")),
+            (Some("\n\n  缩进保留\n"), Some("  缩进保留")),
+        ];
+        for (body, expected) in cases {
+            assert_eq!(normalize_body(body).as_deref(), expected, "{:?}", body);
+        }
+    }
+
+    #[test]
+    fn builds_commit_messages() {
+        let opts = |emoji: bool, footers: &[&str]| MessageOptions {
+            emoji,
+            footers: footers.iter().map(|f| f.to_string()).collect(),
+            details_header: "详细描述：",
+        };
+        let details = vec!["新增: a.rs".to_string(), "修改: b.rs".to_string()];
+
+        assert_eq!(build_commit_message("fix", "修复崩溃", &[], None, &opts(false, &[])), "fix: 修复崩溃");
+        assert_eq!(build_commit_message("fix", "修复崩溃", &[], None, &opts(true, &[])), "🐛 fix: 修复崩溃");
+        assert_eq!(
+            build_commit_message("docs", "更新文档", &details, None, &opts(false, &["Refs: ABC-1"])),
+            "docs: 更新文档\n\n详细描述：\n- 新增: a.rs\n- 修改: b.rs\n\nRefs: ABC-1"
+        );
+        // body 优先于 details
+        assert_eq!(
+            build_commit_message("feat", "登录", &details, Some("\r\n第一段\r\n\r\n第二段\r\n"), &opts(false, &[])),
+            "feat: 登录\n\n第一段\n\n第二段"
+        );
+        // 空白 body 退回 details
+        assert_eq!(
+            build_commit_message("feat", "登录", &details[..1], Some("  \n"), &opts(false, &[])),
+            "feat: 登录\n\n详细描述：\n- 新增: a.rs"
+        );
+        // 未知类型按 feat 处理
+        assert_eq!(build_commit_message("unknown", "x", &[], None, &opts(false, &[])), "feat: x");
+    }
+
+    #[tokio::test]
+    async fn commit_writes_multi_paragraph_body() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        repo.write("a.txt", "2");
+        let server = GitMcpServer::new();
+
+        server
+            .git_commit(params(json!({
+                "message": "fix: 修复崩溃",
+                "body": "\r\n第一段\r\n\r\n第二段\r\n",
+                "path": repo.path(),
+            })))
+            .await
+            .expect_success();
+        assert_eq!(repo.git(&["log", "-1", "--format=%B"]), "fix: 修复崩溃\n\n第一段\n\n第二段");
+    }
+
+    #[tokio::test]
+    async fn sync_check_fetches_and_compares_branches() {
+        let upstream = TestRepo::new();
+        upstream.commit("a.txt", "1", "feat: 初始");
+        let local = TestRepo::clone_of(&upstream);
+        let server = GitMcpServer::new();
+        let sync_check = || server.git_sync_check(params(json!({ "path": local.path() })));
+
+        assert!(sync_check().await.expect_success().contains("1 个跟踪分支全部同步"));
+
+        upstream.commit("b.txt", "2", "feat: 远程");
+        local.commit("c.txt", "3", "feat: 本地");
+        let output = sync_check().await.expect_success();
+        assert!(output.contains("有 1 个未同步"), "{}", output);
+        assert!(output.contains("\"state\": \"diverged\"") && output.contains("\"behind\": 1"), "{}", output);
+
+        let output = server.git_sync_check(params(json!({ "remote": "nope", "path": local.path() }))).await;
+        assert!(output.expect_failure().contains("找不到远程仓库 nope"));
+    }
+
+    #[tokio::test]
+    async fn commit_can_skip_failing_hooks() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        let hook = repo.dir.join(".git/hooks/pre-commit");
+        std::fs::write(&hook, "#!/bin/sh\nexit 1\n").unwrap();
+        std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755)).unwrap();
+        repo.write("a.txt", "2");
+        let server = GitMcpServer::new();
+        let commit = |no_verify: bool| {
+            server.git_commit(params(json!({ "message": "fix: 跳过钩子", "no_verify": no_verify, "path": repo.path() })))
+        };
+
+        commit(false).await.expect_failure();
+        assert_eq!(repo.git(&["log", "-1", "--format=%s"]), "feat: 初始");
+
+        let output = commit(true).await.expect_success();
+        assert!(output.contains("已使用 --no-verify 跳过"), "{}", output);
+        assert_eq!(repo.git(&["log", "-1", "--format=%s"]), "fix: 跳过钩子");
+    }
+
+    #[tokio::test]
+    async fn ignore_adds_rules_once() {
+        let repo = TestRepo::new();
+        repo.write(".gitignore", "# 构建产物\ntarget/");
+        let server = GitMcpServer::new();
+        let ignore = |action: &str, pattern: Option<&str>| {
+            server.git_ignore(params(json!({ "action": action, "pattern": pattern, "path": repo.path() })))
+        };
+
+        let output = ignore("add", Some(" *.log ")).await.expect_success();
+        assert!(output.contains("已将 *.log 追加到 .gitignore"), "{}", output);
+        assert_eq!(repo.read(".gitignore"), "# 构建产物\ntarget/\n*.log\n");
+        assert!(ignore("add", Some("target/")).await.expect_success().contains("已存在规则 target/"));
+        assert_eq!(repo.read(".gitignore"), "# 构建产物\ntarget/\n*.log\n");
+
+        assert!(ignore("list", None).await.expect_success().contains("共 2 条规则"));
+        assert!(ignore("check", Some("debug.log")).await.expect_success().contains("被忽略规则命中"));
+        assert!(ignore("add", None).await.expect_failure().contains("pattern"));
+    }
+
+    #[tokio::test]
+    async fn find_commits_matches_messages_by_regex() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        let hotfix = repo.commit("a.txt", "2", "fix: HOTFIX 登录");
+        repo.commit("a.txt", "3", "docs: 文档");
+        let server = GitMcpServer::new();
+        let find = |pattern: &str| server.git_find_commits(params(json!({ "pattern": pattern, "path": repo.path() })));
+
+        let output = find("(?i)hotfix").await.expect_success();
+        assert!(output.contains("找到 1 个匹配") && output.contains("已扫描 3 个"), "{}", output);
+        assert!(output.contains(&format!("\"hash\": \"{}\"", hotfix)), "{}", output);
+
+        assert!(find("发布").await.expect_success().contains("在 3 个提交中没有找到"));
+        assert!(find("(").await.expect_failure().contains("正则表达式 ( 无效"));
+    }
+
+    #[tokio::test]
+    async fn log_follows_first_parent_only() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        repo.git(&["checkout", "-q", "-b", "side"]);
+        repo.commit("b.txt", "2", "feat: 分支提交");
+        repo.git(&["checkout", "-q", "main"]);
+        repo.commit("c.txt", "3", "feat: 主线提交");
+        repo.git(&["merge", "-q", "--no-edit", "side"]);
+        let server = GitMcpServer::new();
+        let log = |first_parent: bool| server.git_log(params(json!({ "first_parent": first_parent, "path": repo.path() })));
+
+        assert!(log(false).await.expect_success().contains("分支提交"));
+        let output = log(true).await.expect_success();
+        assert!(output.contains("（仅主线）") && output.contains("主线提交"), "{}", output);
+        assert!(!output.contains("分支提交"), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn commit_env_reports_hooks_and_signing() {
+        let repo = TestRepo::new();
+        let server = GitMcpServer::new();
+        let commit_env = || server.git_commit_env(params(json!({ "path": repo.path() })));
+
+        let output = commit_env().await.expect_success();
+        assert!(output.contains("\"user.name\": \"Tester\"") && output.contains("\"pre_commit_hook\": false"), "{}", output);
+        assert!(!output.contains("🪝"), "{}", output);
+
+        repo.git(&["config", "core.hooksPath", "githooks"]);
+        repo.git(&["config", "commit.gpgsign", "true"]);
+        repo.write("githooks/commit-msg", "#!/bin/sh\n");
+        let output = commit_env().await.expect_success();
+        assert!(output.contains("\"commit_msg_hook\": true") && output.contains("commit.gpgsign"), "{}", output);
+        assert!(output.contains("🔏") && output.contains("🪝"), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn restore_file_rejects_paths_outside_repo() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "v1", "feat: 初始");
+        let outside = TestRepo::new();
+        outside.write("victim.txt", "keep");
+        let victim = outside.dir.join("victim.txt").display().to_string();
+        let escape = format!("../{}/victim.txt", outside.dir.file_name().unwrap().to_string_lossy());
+
+        let server = GitMcpServer::new();
+        for file in [victim.as_str(), escape.as_str(), ".git/config"] {
+            server
+                .git_restore_file(params(json!({ "file": file, "path": repo.path() })))
+                .await
+                .expect_failure();
+        }
+        assert_eq!(outside.read("victim.txt"), "keep");
+        assert!(repo.dir.join(".git/config").exists());
+    }
+
+    #[tokio::test]
+    async fn restore_file_restores_worktree_or_index() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "v1", "feat: 初始");
+        repo.commit("a.txt", "v2", "feat: 修改");
+        let server = GitMcpServer::new();
+        let restore = |revision: Option<&str>, staged: bool| {
+            server.git_restore_file(params(json!({ "file": "a.txt", "revision": revision, "staged": staged, "path": repo.path() })))
+        };
+
+        // 只恢复暂存区时工作区保持不变
+        restore(Some("HEAD~1"), true).await.expect_success();
+        assert_eq!(repo.read("a.txt"), "v2");
+        assert_eq!(repo.git(&["show", ":a.txt"]), "v1");
+
+        repo.write("a.txt", "local edit");
+        restore(None, false).await.expect_success();
+        assert_eq!(repo.read("a.txt"), "v2");
+        assert_eq!(repo.git(&["show", ":a.txt"]), "v1");
+    }
+
+    #[tokio::test]
+    async fn is_ancestor_compares_commits() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        repo.git(&["tag", "v1"]);
+        repo.commit("a.txt", "2", "feat: 第二");
+        let server = GitMcpServer::new();
+        let is_ancestor = |commit: &str, of: &str| {
+            server.git_is_ancestor(params(json!({ "commit": commit, "of": of, "path": repo.path() })))
+        };
+
+        let output = is_ancestor("v1", "HEAD").await.expect_success();
+        assert!(output.contains("是 HEAD") && output.contains("\"ancestor\": true"), "{}", output);
+        assert!(is_ancestor("HEAD", "v1").await.expect_success().contains("\"ancestor\": false"));
+        assert!(is_ancestor("nope", "HEAD").await.expect_failure().contains("无法解析 nope"));
+    }
+
+    #[tokio::test]
+    async fn squash_combines_recent_commits() {
+        let repo = TestRepo::new();
+        let base = repo.commit("a.txt", "1", "feat: 初始");
+        repo.commit("b.txt", "2", "feat: 第二");
+        let head = repo.commit("c.txt", "3", "fix: 第三");
+        let server = GitMcpServer::new();
+        let squash = |count: u32| server.git_squash(params(json!({ "count": count, "path": repo.path() })));
+
+        squash(1).await.expect_failure();
+        squash(5).await.expect_failure();
+        assert_eq!(repo.head(), head);
+
+        squash(2).await.expect_success();
+        let git = repo.repo();
+        let squashed = git.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(squashed.parent_id(0).unwrap(), base);
+        assert_eq!(squashed.tree_id(), git.find_commit(head).unwrap().tree_id());
+        assert_eq!(squashed.message().unwrap(), "feat: 第二\n\n- fix: 第三");
+    }
+
+    #[tokio::test]
+    async fn squash_refuses_pushed_and_merge_commits() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        let pushed = repo.commit("b.txt", "2", "feat: 已推送");
+        repo.commit("c.txt", "3", "feat: 未推送");
+        repo.git(&["update-ref", "refs/remotes/origin/main", &pushed.to_string()]);
+        let server = GitMcpServer::new();
+        let squash = |message: Option<&str>| {
+            server.git_squash(params(json!({ "count": 2, "message": message, "path": repo.path() })))
+        };
+
+        assert!(squash(None).await.expect_failure().contains("已推送"));
+
+        repo.git(&["update-ref", "-d", "refs/remotes/origin/main"]);
+        repo.git(&["checkout", "-q", "-b", "side", "HEAD~1"]);
+        repo.commit("d.txt", "4", "feat: 分支");
+        repo.git(&["checkout", "-q", "main"]);
+        repo.git(&["merge", "-q", "--no-ff", "-m", "merge side", "side"]);
+        let head = repo.head();
+        assert!(squash(Some("x")).await.expect_failure().contains("合并提交"));
+        assert_eq!(repo.head(), head);
+    }
+
+    #[tokio::test]
+    async fn tag_list_orders_by_version() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        repo.git(&["tag", "v1.2.0"]);
+        repo.commit("a.txt", "2", "feat: 第二");
+        repo.git(&["tag", "-a", "v1.10.0", "-m", "十月发布\n\n修复若干问题"]);
+        repo.git(&["tag", "nightly"]);
+        let server = GitMcpServer::new();
+        let tag_list = |pattern: Option<&str>| server.git_tag_list(params(json!({ "pattern": pattern, "path": repo.path() })));
+
+        let output = tag_list(None).await.expect_success();
+        assert!(output.contains("共 3 个标签"), "{}", output);
+        let newer = output.find("🏷️ v1.10.0（附注）").unwrap();
+        let older = output.find("🔖 v1.2.0（轻量）").unwrap();
+        assert!(newer < older, "{}", output);
+        assert!(output.contains("说明: 十月发布\n\n         修复若干问题"), "{}", output);
+        assert!(output.contains("创建者: Tester <tester@example.com>"), "{}", output);
+
+        assert!(tag_list(Some("release-*")).await.expect_success().contains("没有匹配 release-* 的标签"));
+    }
+
+    #[test]
+    fn suggests_ignore_rules() {
+        let cases = [
+            ("node_modules/", Some("node_modules/")),
+            ("web/node_modules/", Some("node_modules/")),
+            ("web/node_modules/pkg/index.js", Some("node_modules/")),
+            ("target/", Some("target/")),
+            ("dist/", Some("dist/")),
+            ("src/__pycache__/", Some("__pycache__/")),
+            ("app/mod.pyc", Some("*.pyc")),
+            ("logs/server.log", Some("*.log")),
+            ("docs/.DS_Store", Some(".DS_Store")),
+            ("Thumbs.db", Some("Thumbs.db")),
+            ("archive.log/", None),
+            ("target", None),
+            ("src/main.rs", None),
+            ("distribution/", None),
+        ];
+        for (path, expected) in cases {
+            assert_eq!(ignore_suggestion(path).map(|(_, rule)| rule), expected, "{}", path);
+        }
+    }
+
+    #[tokio::test]
+    async fn suggest_gitignore_inspects_untracked_files() {
+        let repo = TestRepo::new();
+        repo.commit("src/main.rs", "fn main() {}", "feat: 初始");
+        let server = GitMcpServer::new();
+        let suggest = || server.suggest_gitignore(params(json!({ "path": repo.path() })));
+
+        repo.write("notes.md", "笔记");
+        assert!(suggest().await.expect_success().contains("没有发现常见的应忽略文件"));
+
+        repo.write("target/debug/app", "bin");
+        repo.write("logs/a.log", "1");
+        repo.write("web/node_modules/pkg/index.js", "js");
+        let output = suggest().await.expect_success();
+        assert!(output.contains("发现 3 条建议"), "{}", output);
+        assert!(output.contains("target/  ← target/") && output.contains("node_modules/  ← web/node_modules/"), "{}", output);
+        assert!(!output.contains("notes.md"), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn add_to_last_commit_amends_only_listed_files() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1", "feat: 初始");
+        let head = repo.commit("b.txt", "2", "feat: 登录");
+        repo.write("forgot.txt", "遗漏的文件");
+        repo.write("a.txt", "staged elsewhere");
+        repo.git(&["add", "a.txt"]);
+
+        let server = GitMcpServer::new();
+        let add = |files: &[&str]| server.git_add_to_last_commit(params(json!({ "files": files, "path": repo.path() })));
+        add(&["forgot.txt"]).await.expect_success();
+
+        let git = repo.repo();
+        let amended = git.head().unwrap().peel_to_commit().unwrap();
+        assert_ne!(amended.id(), head);
+        assert_eq!(amended.message().unwrap().trim_end(), "feat: 登录");
+        assert_eq!(amended.parent_id(0).unwrap(), git.find_commit(head).unwrap().parent_id(0).unwrap());
+        let tree = amended.tree().unwrap();
+        assert!(tree.get_path(std::path::Path::new("forgot.txt")).is_ok());
+        assert_eq!(repo.git(&["show", "HEAD:a.txt"]), "1");
+        assert_eq!(repo.git(&["diff", "--cached", "--name-only"]), "a.txt");
+
+        repo.git(&["update-ref", "refs/remotes/origin/main", "HEAD"]);
+        repo.write("late.txt", "又忘了");
+        assert!(add(&["late.txt"]).await.expect_failure().contains("已推送"));
+    }
+
+    #[tokio::test]
+    async fn merge_preview_reports_conflicts_without_changing_state() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "base", "feat: 初始");
+        repo.git(&["checkout", "-q", "-b", "side"]);
+        repo.commit("a.txt", "theirs", "feat: 对方");
+        repo.commit("b.txt", "new", "feat: 新文件");
+        repo.git(&["checkout", "-q", "main"]);
+        let head = repo.commit("a.txt", "ours", "feat: 本方");
+        repo.write("b.txt", "本地未跟踪");
+        let server = GitMcpServer::new();
+        let preview = |branch: &str| server.git_merge_preview(params(json!({ "branch": branch, "path": repo.path() })));
+
+        let output = preview("side").await.expect_success();
+        assert!(output.contains("会在 1 个文件上产生冲突"), "{}", output);
+        assert!(output.contains("\"path\": \"a.txt\"") && output.contains("\"fast_forward\": false"), "{}", output);
+        assert!(output.contains("\"changed_files\": [\n    \"b.txt\"\n  ]"), "{}", output);
+        assert!(output.contains("\"dirty_overlap\": [\n    \"b.txt\"\n  ]"), "{}", output);
+        assert_eq!(repo.head(), head);
+        assert_eq!(repo.git(&["status", "--porcelain"]), "?? b.txt");
+
+        assert!(preview("main").await.expect_success().contains("无需合并"));
+    }
 }